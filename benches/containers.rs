@@ -0,0 +1,136 @@
+//! Compares the container types on their core operations.
+//!
+//! Run with `cargo bench`. Useful questions these answer:
+//! - how much `get` (clone) costs over `with` (borrow)
+//! - what a failed downcast (`TypeMismatch`) costs
+//! - how a single mutex behaves under contention
+//! - what pre-sizing a `TypeStore` saves when registering many types
+
+use std::hint::black_box;
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use sovran_typemap::{TypeMap, TypeStore, TypeStoreValue};
+
+#[derive(Clone)]
+struct Payload {
+    name: String,
+    values: Vec<u64>,
+}
+
+fn payload() -> Payload {
+    Payload {
+        name: "payload".to_string(),
+        values: (0..64).collect(),
+    }
+}
+
+fn single_threaded(c: &mut Criterion) {
+    let mut group = c.benchmark_group("single_threaded");
+
+    let map: TypeMap<u32> = TypeMap::new();
+    map.set(1, payload()).unwrap();
+    group.bench_function("TypeMap/set", |b| {
+        b.iter(|| map.set(black_box(2), black_box(42u64)).unwrap())
+    });
+    group.bench_function("TypeMap/get", |b| {
+        b.iter(|| map.get::<Payload>(black_box(&1)).unwrap())
+    });
+    group.bench_function("TypeMap/with", |b| {
+        b.iter(|| {
+            map.with(black_box(&1), |p: &Payload| p.values.len())
+                .unwrap()
+        })
+    });
+    group.bench_function("TypeMap/type_mismatch", |b| {
+        b.iter(|| map.with(black_box(&1), |s: &String| s.len()).is_err())
+    });
+
+    let store = TypeStore::new();
+    store.set(payload()).unwrap();
+    group.bench_function("TypeStore/set", |b| {
+        b.iter(|| store.set(black_box(42u64)).unwrap())
+    });
+    group.bench_function("TypeStore/get", |b| {
+        b.iter(|| store.get::<Payload>().unwrap())
+    });
+    group.bench_function("TypeStore/with", |b| {
+        b.iter(|| store.with(|p: &Payload| p.name.len()).unwrap())
+    });
+
+    let mut value_store = TypeStoreValue::new();
+    value_store.set(payload());
+    group.bench_function("TypeStoreValue/set", |b| {
+        b.iter(|| value_store.set(black_box(42u64)))
+    });
+    group.bench_function("TypeStoreValue/get", |b| {
+        b.iter(|| value_store.get::<Payload>().unwrap())
+    });
+    group.bench_function("TypeStoreValue/with", |b| {
+        b.iter(|| value_store.with(|p: &Payload| p.name.len()).unwrap())
+    });
+
+    group.finish();
+}
+
+/// Runs `iters` `with_mut` calls spread across `threads` threads contending on
+/// the same key, returning the wall-clock time for all of them.
+fn contended_with_mut(map: &TypeMap<u32>, threads: u64, iters: u64) -> Duration {
+    let barrier = Arc::new(Barrier::new(threads as usize + 1));
+    let per_thread = iters / threads + 1;
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let map = map.clone();
+            let barrier = Arc::clone(&barrier);
+            thread::spawn(move || {
+                barrier.wait();
+                for _ in 0..per_thread {
+                    map.with_mut(&1, |n: &mut u64| *n += 1).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    barrier.wait();
+    let start = Instant::now();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    start.elapsed()
+}
+
+fn contended(c: &mut Criterion) {
+    let mut group = c.benchmark_group("contended_with_mut");
+    for threads in [1u64, 2, 4, 8] {
+        let map: TypeMap<u32> = TypeMap::new();
+        map.set(1, 0u64).unwrap();
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, &t| {
+            b.iter_custom(|iters| contended_with_mut(&map, t, iters))
+        });
+    }
+    group.finish();
+}
+
+/// Stores one value under each of 32 distinct types.
+fn register_services(store: &TypeStore) {
+    macro_rules! register {
+        ($($n:literal)*) => { $( store.set(black_box([0u8; $n])).unwrap(); )* };
+    }
+    register!(1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31 32);
+}
+
+fn registration(c: &mut Criterion) {
+    let mut group = c.benchmark_group("registration");
+    group.bench_function("TypeStore/new", |b| {
+        b.iter(|| register_services(&TypeStore::new()))
+    });
+    group.bench_function("TypeStore/with_capacity", |b| {
+        b.iter(|| register_services(&TypeStore::with_capacity(32)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, single_threaded, contended, registration);
+criterion_main!(benches);
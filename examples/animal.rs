@@ -57,14 +57,18 @@ fn main() -> Result<(), MapError> {
     }) {
         Ok(_) => println!("Successfully accessed dog"),
         Err(MapError::KeyNotFound(key)) => println!("{} not found in store", key),
-        Err(MapError::TypeMismatch) => println!("Value is not a Dog"),
+        Err(MapError::TypeMismatch { .. }) => println!("Value is not a Dog"),
         Err(MapError::LockError) => println!("Failed to acquire lock"),
+        Err(e) => println!("Other error: {}", e),
     }
 
     // Alternative pattern using if let for concise code
-    if let Ok(_) = store.with(&"cat1".to_string(), |cat: &Cat| {
-        println!("Cat named {} says: {}", cat.name, cat.make_sound());
-    }) {
+    if store
+        .with(&"cat1".to_string(), |cat: &Cat| {
+            println!("Cat named {} says: {}", cat.name, cat.make_sound());
+        })
+        .is_ok()
+    {
         println!("Successfully accessed cat");
     } else {
         println!("Failed to access cat");
@@ -83,7 +87,7 @@ fn main() -> Result<(), MapError> {
         // This should fail with TypeMismatch
     }) {
         Ok(_) => println!("This shouldn't happen"),
-        Err(MapError::TypeMismatch) => println!("Correctly detected type mismatch"),
+        Err(MapError::TypeMismatch { .. }) => println!("Correctly detected type mismatch"),
         Err(e) => println!("Unexpected error: {}", e),
     }
 
@@ -0,0 +1,24 @@
+//! Derive macros for `sovran-typemap`.
+//!
+//! Enable these through the `derive` feature of `sovran-typemap` rather than
+//! depending on this crate directly.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Implements `sovran_typemap::ServiceKey` for a type.
+///
+/// The trait's methods all have default implementations, so the generated
+/// impl is empty; deriving simply opts the type in.
+#[proc_macro_derive(ServiceKey)]
+pub fn derive_service_key(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::sovran_typemap::ServiceKey for #name #ty_generics #where_clause {}
+    }
+    .into()
+}
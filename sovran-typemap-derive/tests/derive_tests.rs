@@ -0,0 +1,42 @@
+use sovran_typemap::{MapError, ServiceKey, TypeStore};
+
+#[derive(Clone, Debug, PartialEq, ServiceKey)]
+struct DatabaseConfig {
+    host: String,
+    port: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, ServiceKey)]
+struct Wrapper<T: Clone + Send + Sync + 'static>(T);
+
+#[test]
+fn test_derived_accessors() -> Result<(), MapError> {
+    let store = TypeStore::new();
+    assert!(matches!(
+        DatabaseConfig::from_store(&store),
+        Err(MapError::KeyNotFound(_))
+    ));
+
+    DatabaseConfig {
+        host: "localhost".to_string(),
+        port: 5432,
+    }
+    .store_in(&store)?;
+
+    DatabaseConfig::with_mut_in(&store, |config| config.port = 6543)?;
+    assert_eq!(DatabaseConfig::with_in(&store, |config| config.port)?, 6543);
+    assert_eq!(DatabaseConfig::from_store(&store)?.host, "localhost");
+
+    Ok(())
+}
+
+#[test]
+fn test_derive_on_generic_type() -> Result<(), MapError> {
+    let store = TypeStore::new();
+    Wrapper(7u8).store_in(&store)?;
+    Wrapper("seven".to_string()).store_in(&store)?;
+
+    assert_eq!(Wrapper::<u8>::from_store(&store)?, Wrapper(7));
+    assert_eq!(store.len()?, 2);
+    Ok(())
+}
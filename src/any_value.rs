@@ -1,33 +1,140 @@
-use std::any::{Any, TypeId};
+use std::any::{type_name, Any, TypeId};
+use std::time::Instant;
+
+use crate::error::MapError;
+
+/// Type name recorded for values whose concrete type isn't known statically
+pub(crate) const UNKNOWN_TYPE_NAME: &str = "<unknown>";
+
+/// Strips module paths from a type name, including inside generic arguments, so
+/// `alloc::vec::Vec<my_app::User>` becomes `Vec<User>`
+pub(crate) fn short_type_name(name: &str) -> String {
+    let mut short = String::with_capacity(name.len());
+    // Where the path segment currently being copied starts in `short`
+    let mut segment_start = 0;
+    let mut chars = name.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            short.truncate(segment_start);
+        } else {
+            short.push(c);
+            if !(c.is_alphanumeric() || c == '_') {
+                segment_start = short.len();
+            }
+        }
+    }
+    short
+}
 
 /// A container for type-erased values that preserves type information
+///
+/// This is the entry type stored inside [`TypeMap`](crate::TypeMap). Most code never
+/// sees it; it is public so that [`TypeMap::raw`](crate::TypeMap::raw) can hand out
+/// the underlying map. Use the accessor methods rather than relying on its layout.
 #[derive(Debug)]
-pub(crate) struct AnyValue {
+pub struct AnyValue {
     pub(crate) type_id: TypeId,
+    /// The `type_name` of the stored value, captured at insert time
+    pub(crate) type_name: &'static str,
     pub(crate) value: Box<dyn Any + Send + Sync>,
+    /// Access tick used by LRU-bounded maps to find the eviction candidate
+    pub(crate) last_access: u64,
+    /// Tick this entry is filed under in an LRU-bounded map's access index, or 0
+    /// if it isn't indexed; may lag behind `last_access`
+    pub(crate) lru_tick: u64,
+    /// Set by `TypeMap::lock_key`; a locked entry can't be overwritten, mutated or removed
+    pub(crate) locked: bool,
+    /// Tick of the last write through `TypeMap`, for `TypeMap::wait_for_change`
+    pub(crate) version: u64,
+    /// Reads and writes through `TypeMap` since the key was inserted, for `TypeMap::stats`
+    pub(crate) reads: u64,
+    pub(crate) writes: u64,
+    /// When the key was inserted; kept when the value is overwritten
+    pub(crate) created_at: Instant,
 }
 
 impl AnyValue {
     /// Create a new AnyValue from a value of any type that implements Any, Send, and Sync
-    pub(crate) fn new<T: 'static + Any + Send + Sync>(value: T) -> Self {
+    pub fn new<T: 'static + Any + Send + Sync>(value: T) -> Self {
         Self {
             type_id: TypeId::of::<T>(),
+            type_name: type_name::<T>(),
             value: Box::new(value),
+            last_access: 0,
+            lru_tick: 0,
+            locked: false,
+            version: 0,
+            reads: 0,
+            writes: 0,
+            created_at: Instant::now(),
+        }
+    }
+
+    /// Create a new AnyValue from an already type-erased box, preserving the
+    /// runtime type of the boxed value
+    pub fn from_boxed(value: Box<dyn Any + Send + Sync>) -> Self {
+        Self {
+            // Deref first so we get the boxed value's TypeId, not the Box's
+            type_id: (*value).type_id(),
+            type_name: UNKNOWN_TYPE_NAME,
+            value,
+            last_access: 0,
+            lru_tick: 0,
+            locked: false,
+            version: 0,
+            reads: 0,
+            writes: 0,
+            created_at: Instant::now(),
+        }
+    }
+
+    /// Check if the stored type name matches `name`, either as the full path
+    /// (`my_app::config::DatabaseConfig`) or as the bare type name (`DatabaseConfig`)
+    pub(crate) fn matches_name(&self, name: &str) -> bool {
+        if self.type_name == name {
+            return true;
+        }
+        let path = self.type_name.split('<').next().unwrap_or(self.type_name);
+        path.rsplit("::").next() == Some(name)
+    }
+
+    /// Build the `TypeMismatch` error for an access to this value as type `T`
+    pub(crate) fn mismatch<T: 'static>(&self) -> MapError {
+        MapError::TypeMismatch {
+            expected: type_name::<T>(),
+            found: self.type_name,
         }
     }
 
     /// Check if the contained value is of type T
-    pub(crate) fn is_type<T: 'static>(&self) -> bool {
+    pub fn is_type<T: 'static>(&self) -> bool {
         self.type_id == TypeId::of::<T>()
     }
 
     /// Get a reference to the contained value if it is of type T
-    pub(crate) fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
         self.value.downcast_ref::<T>()
     }
 
     /// Get a mutable reference to the contained value if it is of type T
-    pub(crate) fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T> {
+    pub fn downcast_mut<T: 'static>(&mut self) -> Option<&mut T> {
         self.value.downcast_mut::<T>()
     }
+
+    /// The `TypeId` of the contained value
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The type name of the contained value, or `"<unknown>"` if it was stored
+    /// from an already type-erased box
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// Consume the wrapper and return the type-erased value
+    pub fn into_boxed(self) -> Box<dyn Any + Send + Sync> {
+        self.value
+    }
 }
@@ -0,0 +1,173 @@
+// src/batcher.rs
+use std::any::Any;
+use std::fmt;
+use std::marker::PhantomData;
+
+use crate::error::MapError;
+use crate::store::TypeStore;
+
+/// A pending mutation waiting to be applied to the stored value.
+type Update<T> = Box<dyn FnOnce(&mut T) + Send>;
+
+/// A handle that buffers mutations to one [`TypeStore`] value and applies them
+/// in batches.
+///
+/// Each [`push`](Batcher::push) queues a mutation locally without touching the
+/// store. Once `flush_every` mutations are queued they are all applied in order
+/// under a single lock acquisition, so high-frequency updates to one value (a
+/// counter, a running total) take the store's lock once per batch instead of
+/// once per update.
+///
+/// Queued mutations are not visible to other readers until the batch is flushed.
+/// Dropping the batcher flushes anything still pending; errors during that final
+/// flush are ignored, so call [`flush`](Batcher::flush) explicitly if you need
+/// to observe them.
+///
+/// Created with [`TypeStore::batcher`].
+///
+/// # Examples
+///
+/// ```
+/// use sovran_typemap::{TypeStore, MapError};
+///
+/// fn main() -> Result<(), MapError> {
+///     let store = TypeStore::new();
+///     store.set(0u64)?;
+///
+///     let mut hits = store.batcher::<u64>(100);
+///     for _ in 0..250 {
+///         hits.push(|n| *n += 1)?;
+///     }
+///
+///     // Two full batches have been applied, the rest are still queued
+///     assert_eq!(store.get::<u64>()?, 200);
+///     assert_eq!(hits.pending(), 50);
+///
+///     hits.flush()?;
+///     assert_eq!(store.get::<u64>()?, 250);
+///     Ok(())
+/// }
+/// ```
+pub struct Batcher<T: 'static> {
+    store: TypeStore,
+    updates: Vec<Update<T>>,
+    flush_every: usize,
+    // fn() -> T keeps the batcher Send regardless of T's auto traits
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T: 'static + Any + Send + Sync> Batcher<T> {
+    pub(crate) fn new(store: TypeStore, flush_every: usize) -> Self {
+        let flush_every = flush_every.max(1);
+        Self {
+            store,
+            updates: Vec::with_capacity(flush_every),
+            flush_every,
+            _type: PhantomData,
+        }
+    }
+
+    /// Queues a mutation, flushing the batch if it is now full.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the batch is flushed and no value of
+    ///   type `T` is stored (and no default is registered)
+    pub fn push<F>(&mut self, f: F) -> Result<(), MapError>
+    where
+        F: FnOnce(&mut T) + Send + 'static,
+    {
+        self.updates.push(Box::new(f));
+        if self.updates.len() >= self.flush_every {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Applies all queued mutations in order under a single lock acquisition.
+    ///
+    /// Does nothing if no mutations are queued. If the flush fails the queued
+    /// mutations are discarded.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if no value of type `T` is stored (and no
+    ///   default is registered)
+    pub fn flush(&mut self) -> Result<(), MapError> {
+        if self.updates.is_empty() {
+            return Ok(());
+        }
+        let updates = std::mem::take(&mut self.updates);
+        self.store.with_mut(|value: &mut T| {
+            for update in updates {
+                update(value);
+            }
+        })
+    }
+
+    /// Returns the number of mutations queued but not yet applied.
+    pub fn pending(&self) -> usize {
+        self.updates.len()
+    }
+}
+
+impl<T: 'static> fmt::Debug for Batcher<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Batcher")
+            .field("type", &std::any::type_name::<T>())
+            .field("pending", &self.updates.len())
+            .field("flush_every", &self.flush_every)
+            .finish()
+    }
+}
+
+impl<T: 'static> Drop for Batcher<T> {
+    fn drop(&mut self) {
+        if self.updates.is_empty() {
+            return;
+        }
+        let updates = std::mem::take(&mut self.updates);
+        let _ = self.store.with_mut(|value: &mut T| {
+            for update in updates {
+                update(value);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flushes_on_drop() -> Result<(), MapError> {
+        let store = TypeStore::new();
+        store.set(Vec::<u32>::new())?;
+
+        {
+            let mut batcher = store.batcher::<Vec<u32>>(10);
+            for i in 0..3 {
+                batcher.push(move |v| v.push(i))?;
+            }
+            assert!(store.get::<Vec<u32>>()?.is_empty());
+        }
+
+        assert_eq!(store.get::<Vec<u32>>()?, vec![0, 1, 2]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_missing_value() {
+        let store = TypeStore::new();
+        let mut batcher = store.batcher::<u32>(2);
+
+        assert!(batcher.push(|n| *n += 1).is_ok());
+        assert!(matches!(
+            batcher.push(|n| *n += 1),
+            Err(MapError::KeyNotFound(_))
+        ));
+        assert_eq!(batcher.pending(), 0);
+    }
+}
@@ -0,0 +1,331 @@
+// src/cow_store.rs
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arc_swap::ArcSwap;
+
+use crate::error::MapError;
+use crate::store::TypeStore;
+
+type Items = HashMap<TypeId, Arc<dyn Any + Send + Sync>>;
+
+/// A thread-safe, type-keyed store with lock-free reads and copy-on-write updates.
+///
+/// `CowTypeStore` has the same one-value-per-type shape as `TypeStore`, but keeps
+/// the whole map behind an `ArcSwap` instead of a `Mutex`. Reads load the current
+/// map without taking any lock, so they never wait on each other or on a writer.
+/// Every write copies the map, changes the copy, and swaps it in. Values are
+/// held behind `Arc`, so the copy clones one pointer per stored type rather than
+/// the values themselves, but a write still costs time proportional to the number
+/// of types stored. Use it for data that is read constantly and written rarely,
+/// such as configuration that is reloaded now and then.
+///
+/// Values can't be mutated in place; replace them with `set` instead, or swap in
+/// a whole new set of values with `reload`. Concurrent writers never lose each
+/// other's changes. Cloning yields another handle to the same storage.
+///
+/// Requires the `cow` feature.
+///
+/// # Examples
+///
+/// ```
+/// use sovran_typemap::{CowTypeStore, MapError};
+///
+/// #[derive(Clone, Debug)]
+/// struct Config { max_connections: u32 }
+///
+/// fn main() -> Result<(), MapError> {
+///     let store = CowTypeStore::new();
+///     store.set(Config { max_connections: 10 });
+///
+///     // Hot path: no locking
+///     let max = store.with(|c: &Config| c.max_connections)?;
+///     assert_eq!(max, 10);
+///
+///     // Reload: copies the map and swaps it in
+///     store.set(Config { max_connections: 20 });
+///     assert_eq!(store.get::<Config>()?.max_connections, 20);
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct CowTypeStore {
+    items: Arc<ArcSwap<Items>>,
+}
+
+impl std::fmt::Debug for CowTypeStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CowTypeStore")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl CowTypeStore {
+    /// Creates a new, empty CowTypeStore.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::CowTypeStore;
+    ///
+    /// let store = CowTypeStore::new();
+    /// assert!(store.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a value, using its type as the key.
+    ///
+    /// If a value of this type already exists, it will be replaced. This copies
+    /// the map, so it costs time proportional to the number of stored types.
+    /// Readers that loaded the map before the swap keep seeing the old value
+    /// until they finish.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::CowTypeStore;
+    ///
+    /// let store = CowTypeStore::new();
+    /// store.set(42i32);
+    /// assert!(store.contains::<i32>());
+    /// ```
+    pub fn set<V>(&self, value: V)
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        let value: Arc<dyn Any + Send + Sync> = Arc::new(value);
+        self.items.rcu(|current| {
+            let mut next = Items::clone(current);
+            next.insert(TypeId::of::<V>(), Arc::clone(&value));
+            next
+        });
+    }
+
+    /// Retrieves a clone of a value by its type.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::KeyNotFound` if no value of this type exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{CowTypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = CowTypeStore::new();
+    /// store.set("hello".to_string());
+    /// assert_eq!(store.get::<String>()?, "hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get<V: 'static + Clone>(&self) -> Result<V, MapError> {
+        self.with(|value: &V| value.clone())
+    }
+
+    /// Accesses a value by type with a read-only closure, without locking.
+    ///
+    /// The closure sees a consistent snapshot of the store, and since no lock is
+    /// held it may call back into this store, including `set`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::KeyNotFound` if no value of this type exists.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{CowTypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = CowTypeStore::new();
+    /// store.set(vec![1, 2, 3]);
+    ///
+    /// let sum = store.with(|v: &Vec<i32>| v.iter().sum::<i32>())?;
+    /// assert_eq!(sum, 6);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with<V: 'static, F, R>(&self, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        let items = self.items.load();
+        let value = items
+            .get(&TypeId::of::<V>())
+            .ok_or_else(|| MapError::KeyNotFound(type_name::<V>().to_string()))?;
+
+        // Type is guaranteed to match since TypeId is the key
+        Ok(f(value.downcast_ref::<V>().unwrap()))
+    }
+
+    /// Removes a value by its type.
+    ///
+    /// Returns `true` if a value was removed, `false` if no value of that type
+    /// existed. Like `set`, this copies the map when a value is removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::CowTypeStore;
+    ///
+    /// let store = CowTypeStore::new();
+    /// store.set(42i32);
+    /// assert!(store.remove::<i32>());
+    /// assert!(!store.remove::<i32>());
+    /// ```
+    pub fn remove<V: 'static>(&self) -> bool {
+        let id = TypeId::of::<V>();
+        let previous = self.items.rcu(|current| {
+            let mut next = Items::clone(current);
+            next.remove(&id);
+            next
+        });
+        previous.contains_key(&id)
+    }
+
+    /// Atomically replaces the whole contents with the values of a `TypeStore`.
+    ///
+    /// This is the hot-reload path: build the new configuration in an ordinary
+    /// `TypeStore`, then swap it in with one pointer store. Readers already inside
+    /// `with` finish on the old values; every read that starts afterwards sees only
+    /// the new ones, never a mix. Types missing from `new` are gone after the swap.
+    ///
+    /// The values are moved out of `new`, so any other handle sharing its storage
+    /// is left empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the `TypeStore`'s lock cannot be acquired;
+    /// the current contents are kept.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{CowTypeStore, MapError, TypeStore};
+    /// # fn main() -> Result<(), MapError> {
+    /// let live = CowTypeStore::new();
+    /// live.set(10u32);
+    /// live.set("stale".to_string());
+    ///
+    /// let next = TypeStore::new();
+    /// next.set(20u32)?;
+    /// live.reload(next)?;
+    ///
+    /// assert_eq!(live.get::<u32>()?, 20);
+    /// assert!(!live.contains::<String>());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reload(&self, new: TypeStore) -> Result<(), MapError> {
+        let items: Items = new
+            .lock_items()?
+            .drain()
+            .map(|(id, entry)| (id, Arc::from(entry.value)))
+            .collect();
+        self.items.store(Arc::new(items));
+        Ok(())
+    }
+
+    /// Checks if a value of the given type exists.
+    pub fn contains<V: 'static>(&self) -> bool {
+        self.items.load().contains_key(&TypeId::of::<V>())
+    }
+
+    /// Gets the number of values in the store.
+    pub fn len(&self) -> usize {
+        self.items.load().len()
+    }
+
+    /// Checks if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.items.load().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_set_get_remove() -> Result<(), MapError> {
+        let store = CowTypeStore::new();
+        store.set(1u32);
+        store.set("one".to_string());
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.get::<u32>()?, 1);
+
+        store.set(2u32);
+        assert_eq!(store.with(|n: &u32| *n)?, 2);
+        assert!(matches!(store.get::<i64>(), Err(MapError::KeyNotFound(_))));
+
+        assert!(store.remove::<u32>());
+        assert!(!store.remove::<u32>());
+        assert!(!store.contains::<u32>());
+        assert_eq!(store.get::<String>()?, "one");
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_keeps_snapshot() -> Result<(), MapError> {
+        let store = CowTypeStore::new();
+        store.set(1u32);
+
+        // Writing from inside a read doesn't deadlock or disturb the reader
+        let seen = store.with(|n: &u32| {
+            store.set(2u32);
+            *n
+        })?;
+        assert_eq!(seen, 1);
+        assert_eq!(store.get::<u32>()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload_swaps_everything_at_once() -> Result<(), MapError> {
+        let store = CowTypeStore::new();
+        store.set(1u32);
+        store.set("old".to_string());
+
+        let next = TypeStore::new();
+        next.set(2u32)?;
+        next.set(2u64)?;
+
+        // A reader already in progress keeps its snapshot across the reload
+        let seen = store.with(|n: &u32| {
+            store.reload(next.clone()).unwrap();
+            *n
+        })?;
+        assert_eq!(seen, 1);
+
+        assert_eq!(store.get::<u32>()?, 2);
+        assert_eq!(store.get::<u64>()?, 2);
+        assert!(!store.contains::<String>());
+        assert!(next.is_empty()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_writers_keep_all_types() {
+        let store = CowTypeStore::new();
+        let handles: Vec<_> = (0..4u8)
+            .map(|t| {
+                let store = store.clone();
+                thread::spawn(move || match t {
+                    0 => store.set(0u8),
+                    1 => store.set(1u16),
+                    2 => store.set(2u32),
+                    _ => store.set(3u64),
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(store.len(), 4);
+        assert_eq!(store.get::<u64>().unwrap(), 3);
+    }
+}
@@ -0,0 +1,116 @@
+// src/cross.rs
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::error::MapError;
+use crate::map::TypeMap;
+use crate::store::TypeStore;
+
+/// Reads a value from a [`TypeMap`] and a value from a [`TypeStore`] together,
+/// holding both locks for the duration of the closure.
+///
+/// Nesting `map.with(..)` inside `store.with(..)` in one place and the reverse
+/// in another can deadlock two threads against each other. This function always
+/// acquires the two locks in the same global order (by the address of each
+/// container's storage), so any number of threads can call it on the same pair
+/// of containers without deadlocking.
+///
+/// # Errors
+///
+/// - Returns `MapError::LockError` if either internal lock cannot be acquired
+/// - Returns `MapError::KeyNotFound` if `key` is not in the map, or no value of
+///   type `B` is in the store (and no default is registered)
+/// - Returns `MapError::TypeMismatch` if the map's value is not of type `A`
+///
+/// # Examples
+///
+/// ```
+/// use sovran_typemap::{with_map_and_store, MapError, TypeMap, TypeStore};
+///
+/// #[derive(Debug)]
+/// struct Limits { max_sessions: usize }
+///
+/// fn main() -> Result<(), MapError> {
+///     let sessions: TypeMap<String> = TypeMap::new();
+///     let services = TypeStore::new();
+///
+///     sessions.set("active".to_string(), vec!["ann", "bob"])?;
+///     services.set(Limits { max_sessions: 2 })?;
+///
+///     let full = with_map_and_store(
+///         &sessions,
+///         &"active".to_string(),
+///         &services,
+///         |active: &Vec<&str>, limits: &Limits| active.len() >= limits.max_sessions,
+///     )?;
+///     assert!(full);
+///     Ok(())
+/// }
+/// ```
+pub fn with_map_and_store<K, A, B, F, R>(
+    map: &TypeMap<K>,
+    key: &K,
+    store: &TypeStore,
+    f: F,
+) -> Result<R, MapError>
+where
+    K: Clone + Eq + Hash + Debug,
+    A: 'static,
+    B: 'static,
+    F: FnOnce(&A, &B) -> R,
+{
+    let map_first = (Arc::as_ptr(&map.items) as usize) < (Arc::as_ptr(&store.items) as usize);
+    let (mut map_guard, mut store_guard);
+    if map_first {
+        map_guard = map.lock_items()?;
+        store_guard = store.lock_items()?;
+    } else {
+        store_guard = store.lock_items()?;
+        map_guard = map.lock_items()?;
+    }
+
+    let entry = store.entry_or_default::<B>(&mut store_guard)?;
+    // Type is guaranteed to match since TypeId is the key
+    let b = entry.downcast_ref::<B>().unwrap();
+    map.read_locked(&mut map_guard, key, |a: &A| f(a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_concurrent_callers() {
+        let map: TypeMap<u8> = TypeMap::new();
+        let store = TypeStore::new();
+        map.set(1, 10u32).unwrap();
+        store.set(5u32).unwrap();
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let map = map.clone();
+                let store = store.clone();
+                thread::spawn(move || {
+                    for _ in 0..500 {
+                        let sum = with_map_and_store(&map, &1, &store, |a: &u32, b: &u32| a + b);
+                        assert_eq!(sum.unwrap(), 15);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert!(matches!(
+            with_map_and_store(&map, &2, &store, |_: &u32, _: &u32| ()),
+            Err(MapError::KeyNotFound(_))
+        ));
+        assert!(matches!(
+            with_map_and_store(&map, &1, &store, |_: &u32, _: &String| ()),
+            Err(MapError::KeyNotFound(_))
+        ));
+    }
+}
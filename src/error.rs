@@ -8,7 +8,36 @@ pub enum MapError {
     /// The requested key was not found
     KeyNotFound(String),
     /// Attempted to access a value with a type that doesn't match what was stored
-    TypeMismatch,
+    TypeMismatch {
+        /// The type that was requested
+        expected: &'static str,
+        /// The type that is stored, or `"<unknown>"` if it was stored type-erased
+        found: &'static str,
+    },
+    /// Timed out waiting to acquire the lock on the store
+    Timeout,
+    /// One or more required types are not present in the store
+    MissingTypes(Vec<&'static str>),
+    /// The same key was passed more than once where distinct keys are required
+    DuplicateKey(String),
+    /// The value is already borrowed in a way that conflicts with this access
+    BorrowConflict,
+    /// The key has been locked with `lock_key` and can no longer be modified
+    Locked(String),
+    /// The entry was stored under a different trait than the one requested
+    WrongTrait {
+        /// The trait that was requested
+        expected: &'static str,
+        /// The trait the entry was stored under
+        found: &'static str,
+    },
+    /// A lazy initializer needed the value it was initializing
+    CircularDependency {
+        /// The types being initialized, outermost first, ending with the repeated type
+        chain: Vec<&'static str>,
+    },
+    /// A closure passed to a `_catch` method panicked; holds the panic message
+    ClosurePanicked(String),
 }
 
 impl fmt::Display for MapError {
@@ -16,7 +45,23 @@ impl fmt::Display for MapError {
         match self {
             MapError::LockError => write!(f, "Failed to acquire lock"),
             MapError::KeyNotFound(key) => write!(f, "Key not found in store: {}", key),
-            MapError::TypeMismatch => write!(f, "Type mismatch for the requested key"),
+            MapError::TypeMismatch { expected, found } => {
+                write!(f, "Type mismatch: expected {}, found {}", expected, found)
+            }
+            MapError::Timeout => write!(f, "Timed out waiting to acquire lock"),
+            MapError::MissingTypes(names) => {
+                write!(f, "Missing required types: {}", names.join(", "))
+            }
+            MapError::DuplicateKey(key) => write!(f, "Duplicate key in request: {}", key),
+            MapError::BorrowConflict => write!(f, "Value is already borrowed"),
+            MapError::Locked(key) => write!(f, "Key is locked against modification: {}", key),
+            MapError::WrongTrait { expected, found } => {
+                write!(f, "Entry stored as {} but requested as {}", found, expected)
+            }
+            MapError::CircularDependency { chain } => {
+                write!(f, "Circular dependency: {}", chain.join(" -> "))
+            }
+            MapError::ClosurePanicked(message) => write!(f, "Closure panicked: {}", message),
         }
     }
 }
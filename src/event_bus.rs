@@ -0,0 +1,234 @@
+// src/event_bus.rs
+use std::sync::Arc;
+
+use crate::error::MapError;
+use crate::store::TypeStore;
+
+/// A handler that can be subscribed to events of type `E` on an [`EventBus`].
+///
+/// Any `Fn(&E) + Send + Sync` closure is a handler, so most callers never
+/// implement this trait directly.
+pub trait Handler<E>: Send + Sync + 'static {
+    /// Handle a published event.
+    fn handle(&self, event: &E);
+}
+
+impl<E, F> Handler<E> for F
+where
+    F: Fn(&E) + Send + Sync + 'static,
+{
+    fn handle(&self, event: &E) {
+        self(event)
+    }
+}
+
+/// The handler list for a single event type, stored in the bus's `TypeStore`
+/// so each event type gets its own slot.
+struct Handlers<E: 'static>(Vec<Arc<dyn Handler<E>>>);
+
+/// A thread-safe, type-keyed publish/subscribe event bus.
+///
+/// Handlers are registered per event type and every handler subscribed to `E`
+/// is invoked when an `E` is published. Internally each event type's handler
+/// list lives in a [`TypeStore`] slot, so dispatch is type-safe without any
+/// string keys.
+///
+/// Handlers are invoked after the internal lock is released, so a handler may
+/// itself subscribe or publish without deadlocking.
+///
+/// # Examples
+///
+/// ```
+/// use sovran_typemap::{EventBus, MapError};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// struct UserCreated { name: String }
+///
+/// fn main() -> Result<(), MapError> {
+///     let bus = EventBus::new();
+///     let created = Arc::new(AtomicUsize::new(0));
+///
+///     let counter = Arc::clone(&created);
+///     bus.subscribe(move |event: &UserCreated| {
+///         println!("Welcome, {}!", event.name);
+///         counter.fetch_add(1, Ordering::SeqCst);
+///     })?;
+///
+///     let delivered = bus.publish(UserCreated { name: "Alice".to_string() })?;
+///     assert_eq!(delivered, 1);
+///     assert_eq!(created.load(Ordering::SeqCst), 1);
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EventBus {
+    handlers: TypeStore,
+}
+
+impl EventBus {
+    /// Creates a new EventBus with no subscribers.
+    pub fn new() -> Self {
+        Self {
+            handlers: TypeStore::new(),
+        }
+    }
+
+    /// Registers a handler for events of type `E`.
+    ///
+    /// Handlers for the same event type are invoked in subscription order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn subscribe<E, H>(&self, handler: H) -> Result<(), MapError>
+    where
+        E: 'static,
+        H: Handler<E>,
+    {
+        self.handlers.with_mut_or_insert_with(
+            || Handlers::<E>(Vec::new()),
+            |handlers| handlers.0.push(Arc::new(handler)),
+        )
+    }
+
+    /// Publishes an event to every handler subscribed to its type.
+    ///
+    /// Returns the number of handlers the event was delivered to, which is
+    /// zero when nothing is subscribed to `E`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn publish<E: 'static>(&self, event: E) -> Result<usize, MapError> {
+        let handlers = match self
+            .handlers
+            .with(|handlers: &Handlers<E>| handlers.0.clone())
+        {
+            Ok(handlers) => handlers,
+            Err(MapError::KeyNotFound(_)) => return Ok(0),
+            Err(e) => return Err(e),
+        };
+
+        for handler in &handlers {
+            handler.handle(&event);
+        }
+        Ok(handlers.len())
+    }
+
+    /// Gets the number of handlers subscribed to events of type `E`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn subscriber_count<E: 'static>(&self) -> Result<usize, MapError> {
+        match self
+            .handlers
+            .with(|handlers: &Handlers<E>| handlers.0.len())
+        {
+            Err(MapError::KeyNotFound(_)) => Ok(0),
+            result => result,
+        }
+    }
+
+    /// Removes every handler subscribed to events of type `E`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(true)` if any handlers were removed, `Ok(false)` otherwise.
+    pub fn clear<E: 'static>(&self) -> Result<bool, MapError> {
+        self.handlers.remove::<Handlers<E>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Ping(u32);
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Pong(String);
+
+    struct Recorder {
+        seen: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl Handler<Ping> for Recorder {
+        fn handle(&self, event: &Ping) {
+            self.seen.lock().unwrap().push(event.0);
+        }
+    }
+
+    #[test]
+    fn test_publish_without_subscribers() -> Result<(), MapError> {
+        let bus = EventBus::new();
+        assert_eq!(bus.publish(Ping(1))?, 0);
+        assert_eq!(bus.subscriber_count::<Ping>()?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_is_per_event_type() -> Result<(), MapError> {
+        let bus = EventBus::new();
+        let pings = Arc::new(Mutex::new(Vec::new()));
+        let pongs = Arc::new(Mutex::new(Vec::new()));
+
+        bus.subscribe(Recorder {
+            seen: Arc::clone(&pings),
+        })?;
+        let sink = Arc::clone(&pongs);
+        bus.subscribe(move |pong: &Pong| sink.lock().unwrap().push(pong.0.clone()))?;
+
+        assert_eq!(bus.publish(Ping(1))?, 1);
+        assert_eq!(bus.publish(Ping(2))?, 1);
+        assert_eq!(bus.publish(Pong("hello".to_string()))?, 1);
+
+        assert_eq!(*pings.lock().unwrap(), vec![1, 2]);
+        assert_eq!(*pongs.lock().unwrap(), vec!["hello".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiple_handlers_in_subscription_order() -> Result<(), MapError> {
+        let bus = EventBus::new();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        for id in 0..3 {
+            let order = Arc::clone(&order);
+            bus.subscribe(move |_: &Ping| order.lock().unwrap().push(id))?;
+        }
+
+        assert_eq!(bus.subscriber_count::<Ping>()?, 3);
+        assert_eq!(bus.publish(Ping(0))?, 3);
+        assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+
+        assert!(bus.clear::<Ping>()?);
+        assert_eq!(bus.publish(Ping(0))?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_handler_can_publish() -> Result<(), MapError> {
+        let bus = EventBus::new();
+        let pongs = Arc::new(Mutex::new(Vec::new()));
+
+        let inner = bus.clone();
+        bus.subscribe(move |ping: &Ping| {
+            inner.publish(Pong(format!("pong {}", ping.0))).unwrap();
+        })?;
+        let sink = Arc::clone(&pongs);
+        bus.subscribe(move |pong: &Pong| sink.lock().unwrap().push(pong.0.clone()))?;
+
+        bus.publish(Ping(7))?;
+        assert_eq!(*pongs.lock().unwrap(), vec!["pong 7".to_string()]);
+        Ok(())
+    }
+}
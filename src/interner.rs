@@ -0,0 +1,101 @@
+// src/interner.rs
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use crate::error::MapError;
+
+/// A shared pool of `Arc<str>` keys, so identical key strings share one allocation.
+///
+/// A `TypeMap<Arc<str>>` only ever holds one copy of each key, but the same key
+/// strings often recur across many maps, or are rebuilt by callers for every
+/// insert. Interning through a `KeyInterner` hands back the pooled `Arc<str>` for
+/// strings it has seen before, so every map using the interner points at the
+/// same allocation.
+///
+/// Cloning a `KeyInterner` yields another handle to the same pool. Interned
+/// strings stay in the pool until [`purge`](KeyInterner::purge) drops the ones
+/// nothing else references.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use sovran_typemap::{KeyInterner, MapError, TypeMap};
+///
+/// fn main() -> Result<(), MapError> {
+///     let interner = KeyInterner::new();
+///     let requests: TypeMap<Arc<str>> = TypeMap::new();
+///     let errors: TypeMap<Arc<str>> = TypeMap::new();
+///
+///     requests.set_interned(&interner, "/api/users", 120u64)?;
+///     errors.set_interned(&interner, "/api/users", 3u64)?;
+///
+///     // Both maps share one allocation for the key
+///     let a = &requests.keys()?[0];
+///     let b = &errors.keys()?[0];
+///     assert!(Arc::ptr_eq(a, b));
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct KeyInterner {
+    pool: Arc<Mutex<HashSet<Arc<str>>>>,
+}
+
+impl KeyInterner {
+    /// Creates a new, empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the pooled `Arc<str>` equal to `key`, adding it to the pool first if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn intern(&self, key: &str) -> Result<Arc<str>, MapError> {
+        let mut pool = self.pool.lock().map_err(|_| MapError::LockError)?;
+        if let Some(existing) = pool.get(key) {
+            return Ok(Arc::clone(existing));
+        }
+        let interned: Arc<str> = Arc::from(key);
+        pool.insert(Arc::clone(&interned));
+        Ok(interned)
+    }
+
+    /// Drops pooled strings that are no longer referenced outside the pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of strings removed from the pool.
+    pub fn purge(&self) -> Result<usize, MapError> {
+        let mut pool = self.pool.lock().map_err(|_| MapError::LockError)?;
+        let before = pool.len();
+        pool.retain(|key| Arc::strong_count(key) > 1);
+        Ok(before - pool.len())
+    }
+
+    /// Returns the number of distinct strings in the pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn len(&self) -> Result<usize, MapError> {
+        let pool = self.pool.lock().map_err(|_| MapError::LockError)?;
+        Ok(pool.len())
+    }
+
+    /// Checks if the pool is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn is_empty(&self) -> Result<bool, MapError> {
+        let pool = self.pool.lock().map_err(|_| MapError::LockError)?;
+        Ok(pool.is_empty())
+    }
+}
@@ -14,7 +14,8 @@
 //! - **Ergonomic API**: Simple methods with closures for storing, retrieving, and modifying values
 //! - **Multiple Container Types**: Choose the right container for your use case
 //! - **Flexible**: Supports any type that implements `Any + Send + Sync`
-//! - **No macros**: Pure runtime solution without complex macro magic
+//! - **No macros**: Pure runtime solution without complex macro magic (an opt-in
+//!   `derive` feature provides `#[derive(ServiceKey)]` for typed accessors)
 //! - **No Unsafe Code**: Relies entirely on safe Rust
 //!
 //! ## Container Types
@@ -24,7 +25,9 @@
 //! | [`TypeMap<K>`] | Any hashable type | ✅ | ❌ | General-purpose storage with explicit keys |
 //! | [`TypeStore`] | Type itself | ✅ | ❌ | Service locator / DI container |
 //! | [`TypeStoreValue`] | Type itself | ❌ | ✅ | Cloneable state, single-threaded contexts |
+//! | [`LocalTypeStore`] | Type itself | ❌ | ❌ | `!Send` values on a single thread |
 //! | [`TraitTypeMap<K>`] | Any hashable type | ✅ | ❌ | Polymorphic access via trait interfaces |
+//! | `CowTypeStore` (`cow` feature) | Type itself | ✅ | ❌ | Read-mostly data with lock-free reads |
 //!
 //! ## Quick Examples
 //!
@@ -133,19 +136,76 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ## Thread Safety
+//!
+//! [`TypeMap<K>`], [`TypeStore`], [`TraitTypeMap<K>`] and [`EventBus`] are `Send + Sync`
+//! (for `Send + Sync` keys), so they can be shared across threads directly or behind
+//! an `Arc`. This is checked at compile time inside the crate. The flip side is that
+//! every stored value must be `Send + Sync` too, so storing something like an `Rc`
+//! is rejected by the compiler rather than failing at runtime:
+//!
+//! ```compile_fail,E0277
+//! use std::rc::Rc;
+//! use sovran_typemap::TypeMap;
+//!
+//! let map: TypeMap<String> = TypeMap::new();
+//! map.set("shared".to_string(), Rc::new(5)).unwrap();
+//! ```
+//!
+//! Use [`LocalTypeStore`] for `!Send` values that never leave one thread.
 
 mod any_value;
+mod batcher;
+#[cfg(feature = "cow")]
+mod cow_store;
+mod cross;
 mod error;
+mod event_bus;
+mod interner;
+mod local_store;
+mod lock;
 mod map;
+mod path;
+mod service_key;
 mod store;
 mod store_value;
 mod traits;
+mod typed_view;
+mod visitor;
 
+pub use any_value::AnyValue;
+pub use batcher::Batcher;
+#[cfg(feature = "cow")]
+pub use cow_store::CowTypeStore;
+pub use cross::with_map_and_store;
 pub use error::MapError;
-pub use map::TypeMap;
-pub use store::TypeStore;
+pub use event_bus::{EventBus, Handler};
+pub use interner::KeyInterner;
+pub use local_store::LocalTypeStore;
+pub use map::{EntryStats, MapCtx, TypeMap, TypeMapParts};
+pub use path::Descend;
+pub use service_key::ServiceKey;
+#[cfg(feature = "derive")]
+pub use sovran_typemap_derive::ServiceKey;
+pub use store::{FnKey, OverrideGuard, RequiredType, StoreTuple, TypeStore};
 pub use store_value::{CloneAny, TypeStoreValue};
-pub use traits::TraitTypeMap;
+pub use traits::{OnConflict, RemovedEntry, TraitTypeMap};
+pub use typed_view::TypedView;
+pub use visitor::Visitor;
+
+// The shared containers must stay Send + Sync; a field change that breaks
+// that fails the build here instead of in downstream crates.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<TypeMap<String>>();
+    assert_send_sync::<TypeStore>();
+    assert_send_sync::<TraitTypeMap<String>>();
+    assert_send_sync::<EventBus>();
+    assert_send_sync::<KeyInterner>();
+    #[cfg(feature = "cow")]
+    assert_send_sync::<CowTypeStore>();
+};
 
 // Re-export std::any for convenience
 pub use std::any::{Any, TypeId};
@@ -0,0 +1,281 @@
+// src/local_store.rs
+use std::any::{type_name, Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::MapError;
+
+type Slot = Rc<RefCell<Box<dyn Any>>>;
+
+/// A single-threaded container that stores exactly one value per type,
+/// including values that are not `Send` or `Sync`.
+///
+/// `LocalTypeStore` has the same type-keyed shape as `TypeStore`, but is built on
+/// `Rc<RefCell<_>>` instead of `Arc<Mutex<_>>`, so it only requires `Any + 'static`.
+/// Use it for thread-bound resources such as GL handles or `Rc`-based services.
+/// Cloning yields another handle to the same storage.
+///
+/// Each value is borrowed independently, so a closure passed to `with_mut` for one
+/// type may freely access other types. Overlapping borrows of the *same* value that
+/// `RefCell` would reject return `MapError::BorrowConflict` instead of panicking.
+///
+/// # Examples
+///
+/// ```
+/// use sovran_typemap::{LocalTypeStore, MapError};
+/// use std::rc::Rc;
+///
+/// // Rc makes this type !Send, so it can't go in a TypeStore
+/// struct TextureCache { textures: Vec<Rc<str>> }
+///
+/// fn main() -> Result<(), MapError> {
+///     let store = LocalTypeStore::new();
+///     store.set(TextureCache { textures: Vec::new() });
+///
+///     store.with_mut(|cache: &mut TextureCache| {
+///         cache.textures.push(Rc::from("grass.png"));
+///     })?;
+///
+///     let count = store.with(|cache: &TextureCache| cache.textures.len())?;
+///     assert_eq!(count, 1);
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Default)]
+pub struct LocalTypeStore {
+    items: Rc<RefCell<HashMap<TypeId, Slot>>>,
+}
+
+impl std::fmt::Debug for LocalTypeStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LocalTypeStore")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl LocalTypeStore {
+    /// Creates a new, empty LocalTypeStore.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::LocalTypeStore;
+    ///
+    /// let store = LocalTypeStore::new();
+    /// assert!(store.is_empty());
+    /// ```
+    pub fn new() -> Self {
+        Self {
+            items: Rc::new(RefCell::new(HashMap::new())),
+        }
+    }
+
+    /// Looks up the slot for `V` without holding the map borrow past the call.
+    fn slot<V: 'static>(&self) -> Result<Slot, MapError> {
+        self.items
+            .borrow()
+            .get(&TypeId::of::<V>())
+            .cloned()
+            .ok_or_else(|| MapError::KeyNotFound(type_name::<V>().to_string()))
+    }
+
+    /// Stores a value, using its type as the key.
+    ///
+    /// If a value of this type already exists, it will be replaced. Replacing a
+    /// value that is currently borrowed is allowed; the outstanding borrow keeps
+    /// seeing the old value until it ends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::LocalTypeStore;
+    /// use std::rc::Rc;
+    ///
+    /// let store = LocalTypeStore::new();
+    /// store.set(Rc::new(42i32));
+    /// assert!(store.contains::<Rc<i32>>());
+    /// ```
+    pub fn set<V: 'static>(&self, value: V) {
+        self.items
+            .borrow_mut()
+            .insert(TypeId::of::<V>(), Rc::new(RefCell::new(Box::new(value))));
+    }
+
+    /// Retrieves a clone of a value by its type.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::KeyNotFound` if no value of this type exists
+    /// - Returns `MapError::BorrowConflict` if the value is being mutated
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{LocalTypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = LocalTypeStore::new();
+    /// store.set("hello".to_string());
+    /// assert_eq!(store.get::<String>()?, "hello");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get<V: 'static + Clone>(&self) -> Result<V, MapError> {
+        self.with(|value: &V| value.clone())
+    }
+
+    /// Accesses a value by type with a read-only closure.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::KeyNotFound` if no value of this type exists
+    /// - Returns `MapError::BorrowConflict` if the value is being mutated
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{LocalTypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = LocalTypeStore::new();
+    /// store.set(vec![1, 2, 3]);
+    ///
+    /// let sum = store.with(|v: &Vec<i32>| v.iter().sum::<i32>())?;
+    /// assert_eq!(sum, 6);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with<V: 'static, F, R>(&self, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        let slot = self.slot::<V>()?;
+        let value = slot.try_borrow().map_err(|_| MapError::BorrowConflict)?;
+
+        // Type is guaranteed to match since TypeId is the key
+        Ok(f(value.downcast_ref::<V>().unwrap()))
+    }
+
+    /// Accesses a value by type with a read-write closure.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::KeyNotFound` if no value of this type exists
+    /// - Returns `MapError::BorrowConflict` if the value is already borrowed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{LocalTypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = LocalTypeStore::new();
+    /// store.set(0u32);
+    ///
+    /// store.with_mut(|n: &mut u32| *n += 1)?;
+    ///
+    /// // Borrowing the same value again from inside the closure is a conflict
+    /// let nested = store.with_mut(|_: &mut u32| store.with(|n: &u32| *n))?;
+    /// assert!(matches!(nested, Err(MapError::BorrowConflict)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_mut<V: 'static, F, R>(&self, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        let slot = self.slot::<V>()?;
+        let mut value = slot
+            .try_borrow_mut()
+            .map_err(|_| MapError::BorrowConflict)?;
+
+        // Type is guaranteed to match since TypeId is the key
+        Ok(f(value.downcast_mut::<V>().unwrap()))
+    }
+
+    /// Removes a value by its type.
+    ///
+    /// Returns `true` if a value was removed, `false` if no value of that type
+    /// existed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::LocalTypeStore;
+    ///
+    /// let store = LocalTypeStore::new();
+    /// store.set(42i32);
+    /// assert!(store.remove::<i32>());
+    /// assert!(!store.remove::<i32>());
+    /// ```
+    pub fn remove<V: 'static>(&self) -> bool {
+        self.items.borrow_mut().remove(&TypeId::of::<V>()).is_some()
+    }
+
+    /// Checks if a value of the given type exists.
+    pub fn contains<V: 'static>(&self) -> bool {
+        self.items.borrow().contains_key(&TypeId::of::<V>())
+    }
+
+    /// Gets the number of values in the store.
+    pub fn len(&self) -> usize {
+        self.items.borrow().len()
+    }
+
+    /// Checks if the store is empty.
+    pub fn is_empty(&self) -> bool {
+        self.items.borrow().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Handle(Rc<u32>);
+
+    #[test]
+    fn test_not_send_values() -> Result<(), MapError> {
+        let store = LocalTypeStore::new();
+        store.set(Handle(Rc::new(7)));
+
+        assert_eq!(store.with(|h: &Handle| *h.0)?, 7);
+        assert!(matches!(
+            store.with(|_: &String| ()),
+            Err(MapError::KeyNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_borrows_are_per_type() -> Result<(), MapError> {
+        let store = LocalTypeStore::new();
+        store.set(1i32);
+        store.set(Handle(Rc::new(2)));
+
+        // Different types can be borrowed while one is mutably borrowed
+        store.with_mut(|n: &mut i32| -> Result<(), MapError> {
+            *n += store.with(|h: &Handle| *h.0 as i32)?;
+            Ok(())
+        })??;
+        assert_eq!(store.get::<i32>()?, 3);
+
+        // Shared borrows of the same type don't conflict, mutable ones do
+        let inner = store.with(|_: &i32| store.with(|n: &i32| *n))?;
+        assert_eq!(inner?, 3);
+        let inner = store.with(|_: &i32| store.with_mut(|n: &mut i32| *n += 1))?;
+        assert!(matches!(inner, Err(MapError::BorrowConflict)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_clones_share_storage() {
+        let store = LocalTypeStore::new();
+        let other = store.clone();
+        other.set(5u8);
+
+        assert!(store.contains::<u8>());
+        assert_eq!(store.len(), 1);
+        assert!(store.remove::<u8>());
+        assert!(other.is_empty());
+    }
+}
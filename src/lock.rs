@@ -0,0 +1,142 @@
+// src/lock.rs
+use std::ops::{Deref, DerefMut};
+use std::sync::{Mutex, MutexGuard, TryLockError};
+
+use crate::error::MapError;
+
+#[cfg(debug_assertions)]
+thread_local! {
+    /// Addresses of the container mutexes the current thread is holding.
+    static HELD: std::cell::RefCell<Vec<usize>> = const { std::cell::RefCell::new(Vec::new()) };
+}
+
+/// A container lock guard that, in debug builds, remembers which thread holds it.
+///
+/// Locking a `std::sync::Mutex` the current thread already holds never returns,
+/// which is what happens when a `with` closure calls back into the same
+/// container. In debug builds every container lock goes through [`lock`] or
+/// [`try_lock`], which panic with an explanation instead of hanging. In release
+/// builds this is a plain `MutexGuard` and the bookkeeping compiles away.
+pub(crate) struct TrackedGuard<'a, T> {
+    guard: MutexGuard<'a, T>,
+    #[cfg(debug_assertions)]
+    addr: usize,
+}
+
+/// Panics if the current thread already holds `mutex`, then records that it will.
+#[cfg(debug_assertions)]
+fn enter<T>(mutex: &Mutex<T>, container: &'static str, hint: &'static str) -> usize {
+    let addr = mutex as *const Mutex<T> as usize;
+    let reentrant = HELD.with(|held| held.borrow().contains(&addr));
+    if reentrant {
+        panic!("reentrant lock on {} — {}", container, hint);
+    }
+    addr
+}
+
+#[cfg(not(debug_assertions))]
+#[inline(always)]
+fn enter<T>(_mutex: &Mutex<T>, _container: &'static str, _hint: &'static str) -> usize {
+    0
+}
+
+impl<'a, T> TrackedGuard<'a, T> {
+    #[cfg_attr(not(debug_assertions), allow(unused_variables))]
+    fn new(guard: MutexGuard<'a, T>, addr: usize) -> Self {
+        #[cfg(debug_assertions)]
+        HELD.with(|held| held.borrow_mut().push(addr));
+        Self {
+            guard,
+            #[cfg(debug_assertions)]
+            addr,
+        }
+    }
+}
+
+/// Locks a container's mutex, panicking in debug builds if this thread already holds it.
+///
+/// `container` and `hint` make up the panic message, e.g. `"TypeMap"` and a
+/// pointer to the API that avoids the nested call.
+pub(crate) fn lock<'a, T>(
+    mutex: &'a Mutex<T>,
+    container: &'static str,
+    hint: &'static str,
+) -> Result<TrackedGuard<'a, T>, MapError> {
+    let addr = enter(mutex, container, hint);
+
+    let guard = mutex.lock().map_err(|_| MapError::LockError)?;
+    Ok(TrackedGuard::new(guard, addr))
+}
+
+/// Locks a container's mutex, recovering the data if it is poisoned.
+pub(crate) fn lock_lenient<'a, T>(
+    mutex: &'a Mutex<T>,
+    container: &'static str,
+    hint: &'static str,
+) -> TrackedGuard<'a, T> {
+    let addr = enter(mutex, container, hint);
+
+    let guard = mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    TrackedGuard::new(guard, addr)
+}
+
+/// Attempts to lock a container's mutex without blocking, returning `None` if
+/// another thread holds it.
+///
+/// Re-entry still panics in debug builds rather than returning `None`, since
+/// waiting on a lock this thread holds can never succeed.
+pub(crate) fn try_lock<'a, T>(
+    mutex: &'a Mutex<T>,
+    container: &'static str,
+    hint: &'static str,
+) -> Result<Option<TrackedGuard<'a, T>>, MapError> {
+    let addr = enter(mutex, container, hint);
+    match mutex.try_lock() {
+        Ok(guard) => Ok(Some(TrackedGuard::new(guard, addr))),
+        Err(TryLockError::Poisoned(_)) => Err(MapError::LockError),
+        Err(TryLockError::WouldBlock) => Ok(None),
+    }
+}
+
+/// Attempts to lock a container's mutex for formatting, never blocking or panicking.
+///
+/// Unlike [`try_lock`] this skips the re-entry check, since a `Display` impl may
+/// run inside one of the container's own closures; a mutex held by this or any
+/// other thread just reports `"<busy>"`. On failure, returns the placeholder to
+/// print instead of the contents.
+pub(crate) fn peek<T>(mutex: &Mutex<T>) -> Result<MutexGuard<'_, T>, &'static str> {
+    match mutex.try_lock() {
+        Ok(guard) => Ok(guard),
+        Err(TryLockError::WouldBlock) => Err("<busy>"),
+        Err(TryLockError::Poisoned(_)) => Err("<poisoned>"),
+    }
+}
+
+impl<T> Deref for TrackedGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for TrackedGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+#[cfg(debug_assertions)]
+impl<T> Drop for TrackedGuard<'_, T> {
+    fn drop(&mut self) {
+        // `try_with` because the thread-local may already be gone during thread teardown
+        let _ = HELD.try_with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(pos) = held.iter().rposition(|&addr| addr == self.addr) {
+                held.swap_remove(pos);
+            }
+        });
+    }
+}
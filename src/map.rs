@@ -1,11 +1,26 @@
-use std::any::Any;
-use std::collections::HashMap;
-use std::fmt::Debug;
-use std::hash::Hash;
-use std::sync::{Arc, Mutex};
+use std::any::{Any, TypeId};
+use std::borrow::Borrow;
+use std::cell::{RefCell, RefMut};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{self, Debug};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::any_value::AnyValue;
+use crate::any_value::{AnyValue, UNKNOWN_TYPE_NAME};
 use crate::error::MapError;
+use crate::interner::KeyInterner;
+use crate::lock::{self, TrackedGuard};
+use crate::path::Descend;
+use crate::traits::OnConflict;
+use crate::typed_view::TypedView;
+use crate::visitor::Visitor;
+
+/// Explains how to avoid the nested call in the debug-build reentrancy panic.
+const REENTRY_HINT: &str = "use with_ctx to reach other keys from inside a closure";
 
 /// A thread-safe heterogeneous container with type-safety
 ///
@@ -13,6 +28,10 @@ use crate::error::MapError;
 /// while maintaining type-safety through runtime checks. It provides a convenient way
 /// to share state between components without requiring all components to know about all types.
 ///
+/// Cloning a `TypeMap` is cheap and yields another handle to the *same* storage, not a
+/// deep copy; see [`share`](TypeMap::share). There's no need to wrap it in an outer `Arc`.
+/// For an independent copy, see [`try_deep_clone`](TypeMap::try_deep_clone).
+///
 /// # Examples
 ///
 /// ```
@@ -43,6 +62,222 @@ use crate::error::MapError;
 #[derive(Clone, Debug)]
 pub struct TypeMap<K> {
     pub(crate) items: Arc<Mutex<HashMap<K, AnyValue>>>,
+    capacity: Option<usize>,
+    /// Load factor below which a removal shrinks the table, if auto-shrink is on
+    shrink_below: Option<f64>,
+    /// Longest any method waits for the items lock before failing with `Timeout`
+    lock_timeout: Option<Duration>,
+    clock: Arc<AtomicU64>,
+    /// Keys of LRU-bounded entries by the tick they are filed under, oldest first
+    lru_order: Arc<Mutex<BTreeMap<u64, K>>>,
+    // Lock order: `items` is always acquired before `lru_order`, `cloners`,
+    // `descenders` and `counts`
+    cloners: Arc<Mutex<HashMap<TypeId, CloneFn>>>,
+    descenders: Arc<Mutex<HashMap<TypeId, DescendFn>>>,
+    /// Live entries per stored type, updated under the items lock
+    counts: Arc<TypeCounts>,
+    /// Lets `wait_for_change` sleep until a write happens
+    change_signal: Arc<ChangeSignal>,
+}
+
+/// Wakes `wait_for_change` callers after writes.
+///
+/// Writes only touch the mutex and condvar while someone is waiting, so maps that
+/// never call `wait_for_change` pay one atomic load per write.
+#[derive(Debug, Default)]
+struct ChangeSignal {
+    /// Bumped after every write made while `waiters` is non-zero
+    generation: Mutex<u64>,
+    changed: Condvar,
+    /// Number of `wait_for_change` calls in progress
+    waiters: AtomicUsize,
+}
+
+/// Live entries per stored type, for `count_of_type`.
+///
+/// Each type gets an atomic counter the first time it is stored. Counters are never
+/// removed, so once a type has been seen, updating or reading its count only takes
+/// the table's read lock.
+///
+/// Handing the storage out through `raw` or `into_parts` lets it change behind the
+/// counters' back, so it bumps `exposed`. The counters are trusted again once they
+/// have been rebuilt at a moment when only map handles hold the storage.
+#[derive(Debug, Default)]
+struct TypeCounts {
+    by_type: RwLock<HashMap<TypeId, AtomicUsize>>,
+    /// Bumped every time the storage is handed out
+    exposed: AtomicU64,
+    /// The value of `exposed` when the counters were last rebuilt
+    synced: AtomicU64,
+}
+
+impl TypeCounts {
+    /// Counts the entries of an existing map.
+    fn of<K>(store: &HashMap<K, AnyValue>) -> Self {
+        let counts = Self::default();
+        counts.rebuild(store);
+        counts
+    }
+
+    /// Counts for storage that may already be shared outside any map.
+    fn untrusted() -> Self {
+        let counts = Self::default();
+        counts.exposed.store(1, Ordering::SeqCst);
+        counts
+    }
+
+    fn get(&self, type_id: TypeId) -> usize {
+        self.by_type
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&type_id)
+            .map_or(0, |count| count.load(Ordering::Relaxed))
+    }
+
+    fn add(&self, type_id: TypeId) {
+        if let Some(count) = self
+            .by_type
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&type_id)
+        {
+            count.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.by_type
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(type_id)
+            .or_default()
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn sub(&self, type_id: TypeId) {
+        if let Some(count) = self
+            .by_type
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&type_id)
+        {
+            // Saturate: while the storage is exposed the counters may be off
+            let _ = count.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1));
+        }
+    }
+
+    /// Recounts every type from the entries. Must be called with the items lock held.
+    fn rebuild<K>(&self, store: &HashMap<K, AnyValue>) {
+        let mut by_type = self
+            .by_type
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        for count in by_type.values() {
+            count.store(0, Ordering::Relaxed);
+        }
+        for value in store.values() {
+            by_type
+                .entry(value.type_id)
+                .or_default()
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn is_trusted(&self) -> bool {
+        self.exposed.load(Ordering::SeqCst) == self.synced.load(Ordering::SeqCst)
+    }
+}
+
+/// Counts a `wait_for_change` call as in progress until dropped.
+struct Waiting<'a>(&'a AtomicUsize);
+
+impl<'a> Waiting<'a> {
+    fn enter(waiters: &'a AtomicUsize) -> Self {
+        waiters.fetch_add(1, Ordering::SeqCst);
+        Waiting(waiters)
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Access counters for one entry of a [`TypeMap`], returned by [`TypeMap::stats`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EntryStats {
+    /// Keyed reads since the key was inserted
+    pub reads: u64,
+    /// Writes since the key was inserted, including the insert itself
+    pub writes: u64,
+    /// When the key was inserted
+    pub created_at: Instant,
+}
+
+/// The storage of a [`TypeMap`] together with the state its handles share,
+/// returned by [`TypeMap::into_parts`]
+///
+/// `items` is the backing store itself, for code built on a plain
+/// `Arc<Mutex<HashMap>>`. The rest, the version clock, the `wait_for_change`
+/// signal, the per-type counts and the clone and descent registrations, stays
+/// private and is carried back by [`TypeMap::from_parts`], so a handle rebuilt from
+/// the parts keeps stamping increasing versions and wakes waiters on the original
+/// handles. Parts can also be made from a bare `Arc<Mutex<HashMap<K, AnyValue>>>`
+/// with `From`.
+#[derive(Debug)]
+pub struct TypeMapParts<K> {
+    /// The backing store, shared with every handle built from these parts
+    pub items: Arc<Mutex<HashMap<K, AnyValue>>>,
+    clock: Arc<AtomicU64>,
+    lru_order: Arc<Mutex<BTreeMap<u64, K>>>,
+    cloners: Arc<Mutex<HashMap<TypeId, CloneFn>>>,
+    descenders: Arc<Mutex<HashMap<TypeId, DescendFn>>>,
+    counts: Arc<TypeCounts>,
+    change_signal: Arc<ChangeSignal>,
+}
+
+/// Wraps storage that didn't come from `into_parts`, such as a map built by hand.
+///
+/// The version clock starts past the highest version or access tick already
+/// stored, so later writes still get versions greater than any the entries have.
+/// There is no signal to share, so `wait_for_change` callers on other handles over
+/// the same storage are not woken by writes through maps built from these parts.
+impl<K> From<Arc<Mutex<HashMap<K, AnyValue>>>> for TypeMapParts<K> {
+    fn from(items: Arc<Mutex<HashMap<K, AnyValue>>>) -> Self {
+        let latest = items
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .values()
+            .map(|entry| entry.version.max(entry.last_access))
+            .max()
+            .unwrap_or(0);
+        Self {
+            items,
+            clock: Arc::new(AtomicU64::new(latest)),
+            lru_order: Arc::default(),
+            cloners: Arc::default(),
+            descenders: Arc::default(),
+            counts: Arc::new(TypeCounts::untrusted()),
+            change_signal: Arc::default(),
+        }
+    }
+}
+
+/// Deep-copies a stored value for `try_deep_clone`.
+type CloneFn = fn(&AnyValue) -> AnyValue;
+
+/// Clones the stored `T`, type-erased for the cloners table.
+fn clone_entry<T: 'static + Clone + Any + Send + Sync>(value: &AnyValue) -> AnyValue {
+    // Type is guaranteed to match since the cloner is registered under T's TypeId
+    AnyValue::new(value.downcast_ref::<T>().unwrap().clone())
+}
+
+/// Steps one path segment into a value for `with_path`.
+type DescendFn = for<'a> fn(&'a dyn Any, &str) -> Option<&'a dyn Any>;
+
+/// Descends into a `T`, type-erased for the descenders table.
+fn descend_entry<'a, T: Descend>(value: &'a dyn Any, segment: &str) -> Option<&'a dyn Any> {
+    // Type is guaranteed to match since the descender is registered under T's TypeId
+    value.downcast_ref::<T>().unwrap().descend(segment)
 }
 
 impl<K> TypeMap<K>
@@ -65,117 +300,197 @@ where
     pub fn new() -> Self {
         Self {
             items: Arc::new(Mutex::new(HashMap::new())),
+            capacity: None,
+            shrink_below: None,
+            lock_timeout: None,
+            clock: Arc::new(AtomicU64::new(0)),
+            lru_order: Arc::default(),
+            cloners: Arc::new(Mutex::new(HashMap::new())),
+            descenders: Arc::new(Mutex::new(HashMap::new())),
+            counts: Arc::default(),
+            change_signal: Arc::default(),
         }
     }
 
-    /// Stores a value of any type that implements Any, Send, and Sync
-    ///
-    /// # Errors
+    /// Creates a new TypeMap holding a single entry
     ///
-    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    /// This is shorthand for `new()` followed by `set`, without the `Result`: an
+    /// insert into a fresh map can't fail.
     ///
     /// # Examples
     ///
     /// ```
-    /// # use sovran_typemap::{TypeMap, MapError};
-    /// # fn main() -> Result<(), MapError> {
-    /// let store: TypeMap<String> = TypeMap::new();
-    ///
-    /// // Store values of different types
-    /// store.set("number".to_string(), 42i32)?;
-    /// store.set("text".to_string(), "Hello, world!".to_string())?;
-    /// store.set("complex".to_string(), (1, "tuple", true))?;
+    /// use sovran_typemap::TypeMap;
     ///
-    /// // Overwrite an existing value
-    /// store.set("number".to_string(), 100i32)?;
-    /// # Ok(())
-    /// # }
+    /// let store = TypeMap::singleton("port".to_string(), 8080u16);
+    /// assert_eq!(store.len_or_zero(), 1);
+    /// assert_eq!(store.get::<u16>(&"port".to_string()).unwrap(), 8080);
     /// ```
-    pub fn set<V>(&self, key: K, value: V) -> Result<(), MapError>
+    pub fn singleton<V>(key: K, value: V) -> Self
     where
         V: 'static + Any + Send + Sync,
     {
-        let mut store = self.items.lock().map_err(|_| MapError::LockError)?;
-        store.insert(key, AnyValue::new(value));
-        Ok(())
+        let map = Self::new();
+        // A fresh map has no locked keys and no capacity, so this can't fail or evict
+        let _ = map.insert_locked(&mut map.lock_items_lenient(), key, AnyValue::new(value));
+        map
     }
 
-    /// Stores a value generated by a closure
+    /// Bounds the map to at most `capacity` entries
     ///
-    /// This is useful for lazy initialization or complex value construction where
-    /// you want to avoid creating the value if the lock can't be acquired.
+    /// Chain this onto [`new`](Self::new), alone or with the other `with_*` settings,
+    /// before sharing the map: the setting belongs to the handle it is applied to and
+    /// is carried by clones made from it afterwards, not by clones made before.
     ///
-    /// # Errors
+    /// The map tracks access order, updated on `set`, `get`, `with` and `with_mut`.
+    /// When inserting a new key would exceed the capacity, the least-recently-used
+    /// entry is evicted first. Use `set_evicting` to receive the evicted entry.
+    /// Keys locked with `lock_key` are never evicted; if every entry is locked, the
+    /// map grows past its capacity rather than fail the insert.
     ///
-    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    /// Access order is kept in an index ordered by access time, so finding the
+    /// eviction candidate costs O(log n) rather than a scan of every entry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is zero.
     ///
     /// # Examples
     ///
     /// ```
     /// # use sovran_typemap::{TypeMap, MapError};
     /// # fn main() -> Result<(), MapError> {
-    /// let store: TypeMap<String> = TypeMap::new();
+    /// let cache = TypeMap::<String>::new().with_lru_capacity(2);
     ///
-    /// // Lazily construct a complex value
-    /// store.set_with("user_data".to_string(), || {
-    ///     // Imagine this is an expensive operation
-    ///     let mut data = Vec::new();
-    ///     for i in 0..1000 {
-    ///         data.push(i * 2);
-    ///     }
-    ///     data
-    /// })?;
+    /// cache.set("a".to_string(), 1i32)?;
+    /// cache.set("b".to_string(), 2i32)?;
     ///
-    /// // Access the constructed data
-    /// store.with(&"user_data".to_string(), |data: &Vec<i32>| {
-    ///     println!("First value: {}", data.first().unwrap_or(&0));
-    /// })?;
+    /// // Touch "a" so "b" becomes the least recently used
+    /// cache.get::<i32>(&"a".to_string())?;
+    ///
+    /// cache.set("c".to_string(), 3i32)?;
+    /// assert!(cache.contains_key(&"a".to_string())?);
+    /// assert!(!cache.contains_key(&"b".to_string())?);
+    /// assert_eq!(cache.len()?, 2);
     /// # Ok(())
     /// # }
     /// ```
+    pub fn with_lru_capacity(self, capacity: usize) -> Self {
+        assert!(capacity > 0, "TypeMap LRU capacity must be non-zero");
+        Self {
+            capacity: Some(capacity),
+            ..self
+        }
+    }
+
+    /// Makes the map give memory back after mass removals
     ///
-    /// Handling potential errors:
+    /// Like the other `with_*` settings, chain this onto [`new`](Self::new) before
+    /// sharing the map.
+    ///
+    /// A `HashMap` never shrinks on its own, so a map that grew to millions of
+    /// entries during a spike keeps that allocation after they are removed. In this
+    /// mode, whenever a removal leaves the ratio of entries to allocated capacity
+    /// below `load_threshold`, the table is shrunk to fit. Shrinking rehashes every
+    /// remaining entry, which is a noticeable pause on a large map. Because the ratio
+    /// is back near full afterwards, another shrink only happens once enough further
+    /// entries are removed, so the cost is spread across those removals.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `load_threshold` is not strictly between `0.0` and `1.0`.
+    ///
+    /// # Examples
     ///
     /// ```
     /// # use sovran_typemap::{TypeMap, MapError};
-    /// # fn main() {
-    /// let store: TypeMap<String> = TypeMap::new();
+    /// # fn main() -> Result<(), MapError> {
+    /// let sessions = TypeMap::<u64>::new().with_auto_shrink(0.25);
+    /// sessions.set_all((0..10_000).map(|id| (id, "session")))?;
+    /// let grown = sessions.raw().lock().unwrap().capacity();
     ///
-    /// // Handle potential errors from set_with
-    /// match store.set_with("config".to_string(), || {
-    ///     // In a real scenario, this might load from a file
-    ///     std::collections::HashMap::<String, String>::new()
-    /// }) {
-    ///     Ok(()) => println!("Configuration stored successfully"),
-    ///     Err(MapError::LockError) => eprintln!("Failed to acquire lock - try again later"),
-    ///     Err(e) => eprintln!("Unexpected error: {}", e),
-    /// }
+    /// sessions.remove_many(0..9_990)?;
+    /// assert!(sessions.raw().lock().unwrap().capacity() < grown / 100);
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn set_with<V, F>(&self, key: K, f: F) -> Result<(), MapError>
-    where
-        V: 'static + Any + Send + Sync,
-        F: FnOnce() -> V,
-    {
-        let value = f();
-        self.set(key, value)
+    pub fn with_auto_shrink(self, load_threshold: f64) -> Self {
+        assert!(
+            load_threshold > 0.0 && load_threshold < 1.0,
+            "TypeMap auto-shrink threshold must be between 0.0 and 1.0"
+        );
+        Self {
+            shrink_below: Some(load_threshold),
+            ..self
+        }
     }
 
-    /// Retrieves a clone of a value from the store
+    /// Returns the auto-shrink threshold, or `None` if auto-shrink is off
+    pub fn auto_shrink_threshold(&self) -> Option<f64> {
+        self.shrink_below
+    }
+
+    /// Makes the map's methods wait at most `timeout` for its lock
     ///
-    /// This provides a convenient way to get a copy of a value when the `Clone`
-    /// trait is available. For more complex operations or to avoid cloning,
-    /// use `with` instead.
+    /// Like the other `with_*` settings, chain this onto [`new`](Self::new) before
+    /// sharing the map.
     ///
-    /// # Type Parameters
+    /// Every method that would otherwise block on the map's lock indefinitely fails
+    /// with `MapError::Timeout` once `timeout` has elapsed instead. This turns a
+    /// closure that holds the lock far too long, or a lock-order deadlock, into an
+    /// error the caller can log and recover from rather than a frozen thread.
     ///
-    /// * `V` - The type of the value to retrieve. Must match the type that was stored.
+    /// The lock is acquired by polling with a short back-off, so under contention a
+    /// waiter may notice the lock was released up to a millisecond late. The
+    /// infallible `len_or_zero` and `is_empty_lenient` still wait indefinitely.
+    /// Maps created with [`new`](Self::new) have no timeout.
     ///
-    /// # Errors
+    /// # Examples
     ///
-    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
-    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
-    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<(), MapError> {
+    /// // Settings compose: a bounded cache that also refuses to wait forever
+    /// let store = TypeMap::<String>::new()
+    ///     .with_lru_capacity(100)
+    ///     .with_lock_timeout(Duration::from_millis(50));
+    /// store.set("jobs".to_string(), 0u32)?;
+    ///
+    /// let stuck = store.clone();
+    /// let holder = std::thread::spawn(move || {
+    ///     stuck.with(&"jobs".to_string(), |_: &u32| std::thread::sleep(Duration::from_millis(500)))
+    /// });
+    /// std::thread::sleep(Duration::from_millis(50));
+    ///
+    /// assert!(matches!(store.get::<u32>(&"jobs".to_string()), Err(MapError::Timeout)));
+    /// # holder.join().unwrap()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_lock_timeout(self, timeout: Duration) -> Self {
+        Self {
+            lock_timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// Returns the lock timeout, or `None` if methods wait for the lock indefinitely
+    pub fn lock_timeout(&self) -> Option<Duration> {
+        self.lock_timeout
+    }
+
+    /// Returns the LRU capacity, or `None` if the map is unbounded
+    pub fn lru_capacity(&self) -> Option<usize> {
+        self.capacity
+    }
+
+    /// Returns another handle over the same underlying storage
+    ///
+    /// This is equivalent to `clone()`, spelled out for call sites where it matters
+    /// that the data is shared rather than copied. Writes through either handle are
+    /// visible to both, and both honor the same LRU capacity. The storage lives until
+    /// the last handle is dropped.
     ///
     /// # Examples
     ///
@@ -183,54 +498,46 @@ where
     /// # use sovran_typemap::{TypeMap, MapError};
     /// # fn main() -> Result<(), MapError> {
     /// let store: TypeMap<String> = TypeMap::new();
-    /// store.set("answer".to_string(), 42i32)?;
-    ///
-    /// // Get a clone of the value
-    /// let value = store.get::<i32>(&"answer".to_string())?;
-    /// assert_eq!(value, 42);
-    ///
-    /// // Handle possible errors
-    /// match store.get::<String>(&"non_existent".to_string()) {
-    ///     Ok(value) => println!("Value: {}", value),
-    ///     Err(MapError::KeyNotFound(key)) => println!("Key not found {}", key),
-    ///     Err(MapError::TypeMismatch) => println!("Type mismatch"),
-    ///     Err(MapError::LockError) => println!("Failed to acquire lock"),
-    /// }
+    /// let sibling = store.share();
     ///
-    /// // Type mismatch example
-    /// store.set("name".to_string(), "Alice".to_string())?;
-    /// match store.get::<i32>(&"name".to_string()) {
-    ///     Ok(value) => println!("Value: {}", value),
-    ///     Err(MapError::TypeMismatch) => println!("The value is not an i32"),
-    ///     Err(e) => println!("Other error: {}", e),
-    /// }
+    /// sibling.set("answer".to_string(), 42i32)?;
+    /// assert_eq!(store.get::<i32>(&"answer".to_string())?, 42);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn get<V>(&self, key: &K) -> Result<V, MapError>
-    where
-        V: 'static + Clone,
-    {
-        self.with(key, |val: &V| val.clone())
+    pub fn share(&self) -> TypeMap<K> {
+        self.clone()
     }
 
-    /// Gets a value by executing a closure with read access
+    /// Marks a type as cloneable for [`try_deep_clone`](Self::try_deep_clone)
     ///
-    /// This method allows for arbitrary operations on the stored value without
-    /// requiring the value to implement `Clone`. It's useful for inspecting values,
-    /// computing derived values, or performing operations that don't require ownership.
+    /// Stored values are type-erased, so the map can't tell on its own which of them
+    /// implement `Clone`. Registering a type records how to clone it. Handles that
+    /// share this map's storage share its registrations too.
     ///
-    /// # Type Parameters
+    /// # Errors
     ///
-    /// * `V` - The type of the value to access. Must match the type that was stored.
-    /// * `F` - A closure that takes a reference to the value and returns a result.
-    /// * `R` - The return type of the closure.
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn register_clone<V>(&self) -> Result<(), MapError>
+    where
+        V: 'static + Clone + Any + Send + Sync,
+    {
+        let mut cloners = self.cloners.lock().map_err(|_| MapError::LockError)?;
+        cloners.insert(TypeId::of::<V>(), clone_entry::<V>);
+        Ok(())
+    }
+
+    /// Creates an independent copy of the map, deep-cloning every entry whose type
+    /// was registered with `register_clone`
+    ///
+    /// Entries of unregistered types are skipped. The copy is taken under a single
+    /// lock, so it is consistent. It has its own storage, keeps the LRU capacity,
+    /// lock state, and clone and descent registrations, and is unaffected by later
+    /// changes to this map.
     ///
     /// # Errors
     ///
-    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
-    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
-    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
     ///
     /// # Examples
     ///
@@ -238,65 +545,82 @@ where
     /// # use sovran_typemap::{TypeMap, MapError};
     /// # fn main() -> Result<(), MapError> {
     /// let store: TypeMap<String> = TypeMap::new();
-    /// store.set("users".to_string(), vec!["Alice", "Bob", "Charlie"])?;
-    ///
-    /// // Read and compute something from the value
-    /// let user_count = store.with(&"users".to_string(), |users: &Vec<&str>| users.len())?;
-    /// println!("Number of users: {}", user_count);
+    /// store.register_clone::<Vec<u32>>()?;
+    /// store.set("scores".to_string(), vec![90u32, 75])?;
+    /// store.set("socket".to_string(), std::sync::Mutex::new(0u8))?; // not cloneable
     ///
-    /// // Checking if a specific value exists
-    /// let has_alice = store.with(&"users".to_string(), |users: &Vec<&str>| {
-    ///     users.contains(&"Alice")
-    /// })?;
+    /// let snapshot = store.try_deep_clone()?;
+    /// store.with_mut(&"scores".to_string(), |s: &mut Vec<u32>| s.push(60))?;
     ///
-    /// // Handle potential errors with pattern matching
-    /// match store.with(&"settings".to_string(), |settings: &std::collections::HashMap<String, String>| {
-    ///     settings.get("theme").cloned()
-    /// }) {
-    ///     Ok(Some(theme)) => println!("Current theme: {}", theme),
-    ///     Ok(None) => println!("Theme setting not found"),
-    ///     Err(MapError::KeyNotFound(_)) => println!("Settings not initialized"),
-    ///     Err(MapError::TypeMismatch) => println!("Settings has unexpected type"),
-    ///     Err(e) => println!("Error: {}", e),
-    /// }
+    /// assert_eq!(snapshot.get::<Vec<u32>>(&"scores".to_string())?, vec![90, 75]);
+    /// assert!(!snapshot.contains_key(&"socket".to_string())?);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn with<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
-    where
-        F: FnOnce(&V) -> R,
-    {
-        let guard = self.items.lock().map_err(|_| MapError::LockError)?;
-        let value = guard
-            .get(key)
-            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
-
-        if !value.is_type::<V>() {
-            return Err(MapError::TypeMismatch);
-        }
+    pub fn try_deep_clone(&self) -> Result<TypeMap<K>, MapError> {
+        let store = self.lock_items()?;
+        let cloners = self.cloners.lock().map_err(|_| MapError::LockError)?;
+        let descenders = self
+            .descenders
+            .lock()
+            .map_err(|_| MapError::LockError)?
+            .clone();
+        let mut lru_order = BTreeMap::new();
+        let items: HashMap<K, AnyValue> = store
+            .iter()
+            .filter_map(|(key, value)| {
+                let clone = cloners.get(&value.type_id)?;
+                let mut copy = clone(value);
+                copy.last_access = value.last_access;
+                if self.capacity.is_some() && value.lru_tick != 0 {
+                    copy.lru_tick = value.last_access;
+                    lru_order.insert(value.last_access, key.clone());
+                }
+                copy.locked = value.locked;
+                copy.version = value.version;
+                Some((key.clone(), copy))
+            })
+            .collect();
+        let counts = TypeCounts::of(&items);
 
-        // This is safe because we've checked the type
-        let reference = value.downcast_ref::<V>().unwrap();
-        Ok(f(reference))
+        Ok(TypeMap {
+            items: Arc::new(Mutex::new(items)),
+            capacity: self.capacity,
+            shrink_below: self.shrink_below,
+            lock_timeout: self.lock_timeout,
+            clock: Arc::new(AtomicU64::new(self.clock.load(Ordering::Relaxed))),
+            lru_order: Arc::new(Mutex::new(lru_order)),
+            cloners: Arc::new(Mutex::new(cloners.clone())),
+            descenders: Arc::new(Mutex::new(descenders)),
+            counts: Arc::new(counts),
+            change_signal: Arc::default(),
+        })
     }
 
-    /// Gets a value by executing a closure with write access
-    ///
-    /// This method allows for modifying the stored value in place without
-    /// replacing it entirely. It's useful for updating collections or
-    /// complex structures.
-    ///
-    /// # Type Parameters
+    /// Marks a type as traversable by [`get_path`](Self::get_path) and [`with_path`](Self::with_path)
     ///
-    /// * `V` - The type of the value to access. Must match the type that was stored.
-    /// * `F` - A closure that takes a mutable reference to the value and returns a result.
-    /// * `R` - The return type of the closure.
+    /// Once registered, path lookups that reach a value of type `T` continue into it
+    /// through its [`Descend`] implementation. Handles that share this map's storage
+    /// share its registrations too.
     ///
     /// # Errors
     ///
-    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
-    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
-    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn register_descent<T: Descend>(&self) -> Result<(), MapError> {
+        let mut descenders = self.descenders.lock().map_err(|_| MapError::LockError)?;
+        descenders.insert(TypeId::of::<T>(), descend_entry::<T>);
+        Ok(())
+    }
+
+    /// Returns a handle to the underlying `Arc<Mutex<HashMap<K, AnyValue>>>`
+    ///
+    /// This is an escape hatch for operations the `TypeMap` API doesn't cover. The
+    /// returned `Arc` shares storage with this map. Locking it directly bypasses the
+    /// map's own bookkeeping: LRU capacity is not enforced for entries inserted
+    /// through it, and reads through it don't count as recent use. While the
+    /// returned `Arc` is alive, [`count_of_type`](Self::count_of_type) scans the
+    /// entries rather than trust its counters. The shape of the returned type is not
+    /// covered by semver guarantees.
     ///
     /// # Examples
     ///
@@ -304,73 +628,310 @@ where
     /// # use sovran_typemap::{TypeMap, MapError};
     /// # fn main() -> Result<(), MapError> {
     /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("a".to_string(), 1i32)?;
+    /// store.set("b".to_string(), "two".to_string())?;
     ///
-    /// // Initialize a vector
-    /// store.set("numbers".to_string(), vec![1, 2, 3])?;
-    ///
-    /// // Modify the vector in place
-    /// store.with_mut(&"numbers".to_string(), |numbers: &mut Vec<i32>| {
-    ///     numbers.push(4);
-    ///     numbers.push(5);
-    /// })?;
+    /// // Keep only the i32 entries, something the map doesn't offer directly
+    /// let raw = store.raw();
+    /// raw.lock().unwrap().retain(|_, value| value.is_type::<i32>());
     ///
-    /// // Verify the modification
-    /// let count = store.with(&"numbers".to_string(), |numbers: &Vec<i32>| {
-    ///     assert_eq!(numbers, &[1, 2, 3, 4, 5]);
-    ///     numbers.len()
-    /// })?;
-    /// println!("Vector now has {} elements", count);
+    /// assert_eq!(store.len()?, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn raw(&self) -> Arc<Mutex<HashMap<K, AnyValue>>> {
+        let raw = Arc::clone(&self.items);
+        // Mark the counts untrusted only after the extra `Arc` exists, so a resync
+        // racing with this call can't miss it
+        self.counts.exposed.fetch_add(1, Ordering::SeqCst);
+        raw
+    }
+
+    /// Moves the entries out of the map if this is the only handle to its storage
     ///
-    /// // Example with a HashMap
-    /// store.set("counters".to_string(), std::collections::HashMap::<String, i32>::new())?;
+    /// Useful in tests and at teardown to inspect or take ownership of everything
+    /// that was stored without cloning it out. If any other handle shares the
+    /// storage, including clones, typed views and `Arc`s returned by [`raw`](Self::raw),
+    /// the map is handed back unchanged as the error. A poisoned lock doesn't stop
+    /// the entries being recovered.
     ///
-    /// // Update counter values
-    /// let result = store.with_mut(&"counters".to_string(), |counters: &mut std::collections::HashMap<String, i32>| {
-    ///     *counters.entry("visits".to_string()).or_insert(0) += 1;
-    ///     counters.get("visits").copied() // Return current count
-    /// })?;
-    /// println!("Visit count: {}", result.unwrap_or(0));
+    /// # Examples
     ///
-    /// // Error handling example
-    /// match store.with_mut(&"config".to_string(), |config: &mut std::collections::HashMap<String, String>| {
-    ///     config.insert("theme".to_string(), "dark".to_string())
-    /// }) {
-    ///     Ok(old_theme) => println!("Previous theme: {:?}", old_theme),
-    ///     Err(MapError::KeyNotFound(_)) => println!("Config not found"),
-    ///     Err(MapError::TypeMismatch) => println!("Config has wrong type"),
-    ///     Err(e) => println!("Error: {}", e),
-    /// }
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("answer".to_string(), 42u32)?;
+    ///
+    /// // Shared storage can't be taken apart
+    /// let other = store.clone();
+    /// let store = store.try_into_inner().unwrap_err();
+    /// drop(other);
+    ///
+    /// let entries = store.try_into_inner().unwrap();
+    /// assert_eq!(entries["answer"].downcast_ref::<u32>(), Some(&42));
     /// # Ok(())
     /// # }
     /// ```
-    pub fn with_mut<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    pub fn try_into_inner(self) -> Result<HashMap<K, AnyValue>, Self> {
+        match Arc::try_unwrap(self.items) {
+            Ok(items) => Ok(items
+                .into_inner()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())),
+            Err(items) => Err(Self { items, ..self }),
+        }
+    }
+
+    /// Consumes this handle and returns its storage and shared state as [`TypeMapParts`]
+    ///
+    /// `parts.items` hands the storage itself to code built on a plain
+    /// `Arc<Mutex<HashMap>>`, without copying any entries. Other handles to the map
+    /// keep working on the same storage. The same caveats as [`raw`](Self::raw)
+    /// apply to changes made through `items`. Pass the parts to
+    /// [`from_parts`](Self::from_parts) to wrap them in a `TypeMap` again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("port".to_string(), 8080u16)?;
+    ///
+    /// let parts = store.into_parts();
+    /// assert_eq!(parts.items.lock().unwrap().len(), 1);
+    ///
+    /// let store = TypeMap::from_parts(parts);
+    /// assert_eq!(store.get::<u16>(&"port".to_string())?, 8080);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_parts(self) -> TypeMapParts<K> {
+        self.counts.exposed.fetch_add(1, Ordering::SeqCst);
+        TypeMapParts {
+            items: self.items,
+            clock: self.clock,
+            lru_order: self.lru_order,
+            cloners: self.cloners,
+            descenders: self.descenders,
+            counts: self.counts,
+            change_signal: self.change_signal,
+        }
+    }
+
+    /// Creates a TypeMap over existing storage, the inverse of [`into_parts`](Self::into_parts)
+    ///
+    /// The map reads and writes the storage in place, so two components can agree on
+    /// one backing store even if only one of them uses `TypeMap`. Parts from
+    /// `into_parts` carry the original map's version clock, change signal and
+    /// registrations, so the new handle behaves like a clone of it. A bare
+    /// `Arc<Mutex<HashMap<K, AnyValue>>>` is accepted too; see [`TypeMapParts`] for
+    /// what that leaves out. Either way the new handle starts with default
+    /// settings; chain `with_*` settings onto it as with [`new`](Self::new). While
+    /// other `Arc`s to the storage are alive,
+    /// [`count_of_type`](Self::count_of_type) scans it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use std::sync::{Arc, Mutex};
+    /// # use sovran_typemap::{AnyValue, TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let items = Arc::new(Mutex::new(HashMap::new()));
+    /// items.lock().unwrap().insert("retries".to_string(), AnyValue::new(3u8));
+    ///
+    /// let store = TypeMap::from_parts(Arc::clone(&items));
+    /// store.set("timeout".to_string(), 30u32)?;
+    ///
+    /// assert_eq!(store.get::<u8>(&"retries".to_string())?, 3);
+    /// assert_eq!(items.lock().unwrap().len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_parts(parts: impl Into<TypeMapParts<K>>) -> Self {
+        let parts = parts.into();
+        Self {
+            items: parts.items,
+            clock: parts.clock,
+            lru_order: parts.lru_order,
+            cloners: parts.cloners,
+            descenders: parts.descenders,
+            counts: parts.counts,
+            change_signal: parts.change_signal,
+            ..Self::new()
+        }
+    }
+
+    /// Stamps an entry with a new version and wakes any `wait_for_change` callers.
+    ///
+    /// Must be called with the items lock held, after the write.
+    fn record_write(&self, value: &mut AnyValue) {
+        value.writes += 1;
+        value.version = self.clock.fetch_add(1, Ordering::Relaxed) + 1;
+        // A waiter registers before it checks the entry under the items lock, which
+        // we still hold, so a waiter that missed this write is already counted
+        let signal = &*self.change_signal;
+        if signal.waiters.load(Ordering::SeqCst) == 0 {
+            return;
+        }
+        *signal
+            .generation
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) += 1;
+        signal.changed.notify_all();
+    }
+
+    /// Records a read on an entry, for `stats` and LRU ordering.
+    ///
+    /// Must be called with the items lock held.
+    fn record_read(&self, value: &mut AnyValue) {
+        value.reads += 1;
+        self.touch(value);
+    }
+
+    /// Records an access on an entry when the map is LRU-bounded.
+    ///
+    /// This only stamps the entry, so it stays O(1) and doesn't need the key; the
+    /// access index catches up lazily when `lru_key` next looks at the entry.
+    ///
+    /// Must be called with the items lock held so the ordering stays consistent.
+    fn touch(&self, value: &mut AnyValue) {
+        if self.capacity.is_some() {
+            value.last_access = self.clock.fetch_add(1, Ordering::Relaxed) + 1;
+        }
+    }
+
+    /// Acquires the items lock, giving up with `Timeout` after the map's lock
+    /// timeout if it has one.
+    ///
+    /// In debug builds this panics if the current thread already holds the lock,
+    /// which would otherwise deadlock silently.
+    pub(crate) fn lock_items(&self) -> Result<TrackedGuard<'_, HashMap<K, AnyValue>>, MapError> {
+        match self.lock_timeout {
+            Some(timeout) => self.lock_within(timeout),
+            None => lock::lock(&self.items, "TypeMap", REENTRY_HINT),
+        }
+    }
+
+    /// Acquires the items lock, recovering the data if the lock is poisoned.
+    fn lock_items_lenient(&self) -> TrackedGuard<'_, HashMap<K, AnyValue>> {
+        lock::lock_lenient(&self.items, "TypeMap", REENTRY_HINT)
+    }
+
+    /// Acquires the items lock, polling until `timeout` has elapsed.
+    ///
+    /// `std::sync::Mutex` has no timed lock, so this retries `try_lock` with a
+    /// short, growing back-off until the deadline passes.
+    fn lock_within(
+        &self,
+        timeout: Duration,
+    ) -> Result<TrackedGuard<'_, HashMap<K, AnyValue>>, MapError> {
+        let deadline = Instant::now() + timeout;
+        let mut backoff = Duration::from_micros(10);
+        loop {
+            if let Some(guard) = lock::try_lock(&self.items, "TypeMap", REENTRY_HINT)? {
+                return Ok(guard);
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(MapError::Timeout);
+            }
+            thread::sleep(backoff.min(deadline - now));
+            backoff = (backoff * 2).min(Duration::from_millis(1));
+        }
+    }
+
+    /// Runs a read-only closure against a typed entry of the already-locked map.
+    ///
+    /// The key may be any borrowed form of `K`, e.g. `&str` for `Arc<str>` keys.
+    pub(crate) fn read_locked<Q, V: 'static, F, R>(
+        &self,
+        store: &mut HashMap<K, AnyValue>,
+        key: &Q,
+        f: F,
+    ) -> Result<R, MapError>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + Debug + ?Sized,
+        F: FnOnce(&V) -> R,
+    {
+        let value = store
+            .get_mut(key)
+            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
+
+        if !value.is_type::<V>() {
+            return Err(value.mismatch::<V>());
+        }
+        self.record_read(value);
+
+        // This is safe because we've checked the type
+        let reference = value.downcast_ref::<V>().unwrap();
+        Ok(f(reference))
+    }
+
+    /// Runs a read-write closure against a typed entry of the already-locked map.
+    fn write_locked<V: 'static, F, R>(
+        &self,
+        store: &mut HashMap<K, AnyValue>,
+        key: &K,
+        f: F,
+    ) -> Result<R, MapError>
     where
         F: FnOnce(&mut V) -> R,
     {
-        let mut guard = self.items.lock().map_err(|_| MapError::LockError)?;
-        let value = guard
+        let value = store
             .get_mut(key)
             .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
 
+        if value.locked {
+            return Err(MapError::Locked(format!("{:?}", key)));
+        }
         if !value.is_type::<V>() {
-            return Err(MapError::TypeMismatch);
+            return Err(value.mismatch::<V>());
         }
+        self.touch(value);
 
         // This is safe because we've checked the type
         let reference = value.downcast_mut::<V>().unwrap();
-        Ok(f(reference))
+        let result = f(reference);
+        self.record_write(value);
+        Ok(result)
     }
 
-    /// Removes a value from the store
+    /// Creates a handle onto this map that is pinned to values of type `T`
     ///
-    /// # Errors
+    /// The view shares this map's storage, so writes through either are visible to
+    /// both, but its `get`, `set`, `with` and `with_mut` are fixed to `T`. Use it to
+    /// give a component access to its own values without handing over the whole map.
     ///
-    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    /// # Examples
     ///
-    /// # Returns
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// let counters = store.typed_view::<u64>();
     ///
-    /// Returns `Ok(true)` if the key was present and the value was removed.
-    /// Returns `Ok(false)` if the key was not present.
+    /// counters.set("hits".to_string(), 1)?;
+    /// counters.with_mut(&"hits".to_string(), |hits| *hits += 1)?;
+    /// assert_eq!(store.get::<u64>(&"hits".to_string())?, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn typed_view<T>(&self) -> TypedView<K, T>
+    where
+        T: 'static + Any + Send + Sync,
+    {
+        TypedView::new(self.clone())
+    }
+
+    /// Stores a value of any type that implements Any, Send, and Sync
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
     ///
     /// # Examples
     ///
@@ -378,46 +939,36 @@ where
     /// # use sovran_typemap::{TypeMap, MapError};
     /// # fn main() -> Result<(), MapError> {
     /// let store: TypeMap<String> = TypeMap::new();
-    /// store.set("temp".to_string(), "This is temporary".to_string())?;
-    ///
-    /// // Remove the value
-    /// let was_removed = store.remove(&"temp".to_string())?;
-    /// assert!(was_removed);
-    ///
-    /// // Check that it's gone
-    /// match store.get::<String>(&"temp".to_string()) {
-    ///     Err(MapError::KeyNotFound(key)) => println!("Key `{}` was successfully removed", key),
-    ///     Ok(_) => println!("Key still exists"),
-    ///     Err(e) => println!("Error: {}", e),
-    /// }
     ///
-    /// // Removing a non-existent key
-    /// let was_removed = store.remove(&"nonexistent".to_string())?;
-    /// assert!(!was_removed);
+    /// // Store values of different types
+    /// store.set("number".to_string(), 42i32)?;
+    /// store.set("text".to_string(), "Hello, world!".to_string())?;
+    /// store.set("complex".to_string(), (1, "tuple", true))?;
     ///
-    /// // Using pattern matching for error handling
-    /// match store.remove(&"another_key".to_string()) {
-    ///     Ok(true) => println!("Key was found and removed"),
-    ///     Ok(false) => println!("Key did not exist"),
-    ///     Err(MapError::LockError) => println!("Failed to acquire lock"),
-    ///     Err(e) => println!("Unexpected error: {}", e),
-    /// }
+    /// // Overwrite an existing value
+    /// store.set("number".to_string(), 100i32)?;
     /// # Ok(())
     /// # }
     /// ```
-    pub fn remove(&self, key: &K) -> Result<bool, MapError> {
-        let mut store = self.items.lock().map_err(|_| MapError::LockError)?;
-        Ok(store.remove(key).is_some())
+    pub fn set<V>(&self, key: K, value: V) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        self.set_evicting(key, value).map(|_| ())
     }
 
-    /// Checks if a key exists in the store
+    /// Converts a value into `V` and stores it
     ///
-    /// This method only checks for the existence of the key and does not validate
-    /// the type of the stored value.
+    /// Saves the `.to_string()`/`.into()` at call sites that build values inline. The
+    /// stored type is `V`, not `U`, so later reads must ask for `V`.
+    ///
+    /// Because many types convert into many others, the target type usually can't be
+    /// inferred; name it with a turbofish as in `set_into::<String, _>`.
     ///
     /// # Errors
     ///
-    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
     ///
     /// # Examples
     ///
@@ -425,76 +976,2170 @@ where
     /// # use sovran_typemap::{TypeMap, MapError};
     /// # fn main() -> Result<(), MapError> {
     /// let store: TypeMap<String> = TypeMap::new();
-    /// store.set("config".to_string(), std::collections::HashMap::<String, String>::new())?;
     ///
-    /// // Check if a key exists
-    /// let has_config = store.contains_key(&"config".to_string())?;
-    /// assert!(has_config);
+    /// // Stores a `String` built from the `&str` literal
+    /// store.set_into::<String, _>("name".to_string(), "sovran")?;
+    /// assert_eq!(store.get::<String>(&"name".to_string())?, "sovran");
     ///
-    /// let has_users = store.contains_key(&"users".to_string())?;
-    /// assert!(!has_users);
+    /// // Widen a number on the way in
+    /// store.set_into::<u64, _>("limit".to_string(), 10u8)?;
+    /// assert_eq!(store.get::<u64>(&"limit".to_string())?, 10);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_into<V, U>(&self, key: K, value: U) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        U: Into<V>,
+    {
+        self.set(key, value.into())
+    }
+
+    /// Stores a value and returns the entry evicted to make room for it, if any
     ///
-    /// // Use in conditional logic
-    /// if !store.contains_key(&"initialized".to_string())? {
-    ///     store.set("initialized".to_string(), true)?;
-    ///     println!("Store initialized for the first time");
-    /// }
+    /// For unbounded maps this behaves exactly like `set` and always returns `Ok(None)`.
+    /// For maps created with `with_lru_capacity`, inserting a new key into a full map
+    /// evicts the least-recently-used entry and hands it back as its key and boxed value.
+    /// Overwriting an existing key never evicts.
     ///
-    /// // Error handling
-    /// match store.contains_key(&"settings".to_string()) {
-    ///     Ok(true) => println!("Settings exist"),
-    ///     Ok(false) => println!("Settings do not exist"),
-    ///     Err(e) => println!("Error checking settings: {}", e),
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let cache = TypeMap::<String>::new().with_lru_capacity(1);
+    ///
+    /// assert!(cache.set_evicting("first".to_string(), 1i32)?.is_none());
+    ///
+    /// if let Some((key, value)) = cache.set_evicting("second".to_string(), 2i32)? {
+    ///     assert_eq!(key, "first");
+    ///     assert_eq!(value.downcast_ref::<i32>(), Some(&1));
     /// }
     /// # Ok(())
     /// # }
     /// ```
-    pub fn contains_key(&self, key: &K) -> Result<bool, MapError> {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
-        Ok(store.contains_key(key))
+    #[allow(clippy::type_complexity)]
+    pub fn set_evicting<V>(
+        &self,
+        key: K,
+        value: V,
+    ) -> Result<Option<(K, Box<dyn Any + Send + Sync>)>, MapError>
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        let mut store = self.lock_items()?;
+        self.insert_locked(&mut store, key, AnyValue::new(value))
     }
 
-    /// Gets all keys in the store
+    /// Stores a value, refusing to replace an entry of a different type
+    ///
+    /// The type check and the insert happen under one lock, so a concurrent writer
+    /// can't slip a different type in between. Backs `TypedView::set`.
+    pub(crate) fn set_same_type<V>(&self, key: K, value: V) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        let mut store = self.lock_items()?;
+        if let Some(existing) = store.get(&key) {
+            if !existing.is_type::<V>() {
+                return Err(existing.mismatch::<V>());
+            }
+        }
+        self.insert_locked(&mut store, key, AnyValue::new(value))
+            .map(|_| ())
+    }
+
+    /// Stores an already type-erased value
+    ///
+    /// This is for hosts that receive opaque boxes (for example across a plugin
+    /// boundary) and need to relay them through the map without knowing the
+    /// concrete type statically. The runtime type of the boxed value is preserved,
+    /// so readers downcast with `get`, `with` and friends as usual.
     ///
     /// # Errors
     ///
-    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
     ///
     /// # Examples
     ///
     /// ```
     /// # use sovran_typemap::{TypeMap, MapError};
+    /// # use std::any::Any;
     /// # fn main() -> Result<(), MapError> {
     /// let store: TypeMap<String> = TypeMap::new();
-    /// store.set("user".to_string(), "Alice".to_string())?;
-    /// store.set("count".to_string(), 42i32)?;
-    /// store.set("active".to_string(), true)?;
     ///
-    /// // Get all keys
-    /// let keys = store.keys()?;
+    /// let opaque: Box<dyn Any + Send + Sync> = Box::new(42u64);
+    /// store.set_boxed("answer".to_string(), opaque)?;
     ///
-    /// // Keys are returned in arbitrary order, so sort for stable testing
-    /// let mut sorted_keys = keys.clone();
-    /// sorted_keys.sort();
+    /// assert_eq!(store.get::<u64>(&"answer".to_string())?, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_boxed(&self, key: K, value: Box<dyn Any + Send + Sync>) -> Result<(), MapError> {
+        let mut store = self.lock_items()?;
+        self.insert_locked(&mut store, key, AnyValue::from_boxed(value))?;
+        Ok(())
+    }
+
+    /// Stores every key-value pair from an iterator under a single lock acquisition
     ///
-    /// assert_eq!(sorted_keys, vec!["active".to_string(), "count".to_string(), "user".to_string()]);
-    /// println!("Store contains {} keys", keys.len());
+    /// This avoids re-locking for each entry during bulk loads. All values share the
+    /// same type `V`; for LRU-bounded maps, eviction applies to each insert in order.
     ///
-    /// // Use keys to iterate over stored values
-    /// for key in keys {
-    ///     // We need to handle different types differently
-    ///     if let Ok(value) = store.get::<String>(&key) {
-    ///         println!("{}: String = {}", key, value);
-    ///     } else if let Ok(value) = store.get::<i32>(&key) {
-    ///         println!("{}: i32 = {}", key, value);
-    ///     } else if let Ok(value) = store.get::<bool>(&key) {
-    ///         println!("{}: bool = {}", key, value);
-    ///     } else {
-    ///         println!("{}: unknown type", key);
-    ///     }
-    /// }
+    /// # Errors
     ///
-    /// // Error handling
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if any key has been locked with `lock_key`; nothing is stored
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    ///
+    /// store.set_all(vec![
+    ///     ("one".to_string(), 1i32),
+    ///     ("two".to_string(), 2i32),
+    /// ])?;
+    ///
+    /// assert_eq!(store.len()?, 2);
+    /// assert_eq!(store.get::<i32>(&"two".to_string())?, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_all<V, I>(&self, entries: I) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        I: IntoIterator<Item = (K, V)>,
+    {
+        let mut store = self.lock_items()?;
+        let entries: Vec<(K, V)> = entries.into_iter().collect();
+        for (key, _) in &entries {
+            Self::check_unlocked(&store, key)?;
+        }
+        for (key, value) in entries {
+            self.insert_locked(&mut store, key, AnyValue::new(value))?;
+        }
+        Ok(())
+    }
+
+    /// Moves every entry out of `other` into this map
+    ///
+    /// Stored values aren't `Clone`, so merging moves them rather than copying, and
+    /// `other` is consumed. Both maps are locked for the whole merge, always in the
+    /// same order, so concurrent merges in opposite directions can't deadlock.
+    /// `other` is left empty and then dropped; since `TypeMap` handles share storage,
+    /// any other handle onto `other`'s storage sees it emptied too. Absorbing a
+    /// handle onto this map's own storage does nothing.
+    ///
+    /// When a key exists in both maps, `on_conflict` decides which entry survives:
+    /// [`OnConflict::Overwrite`] takes the incoming one, [`OnConflict::Keep`] keeps
+    /// the existing one and drops the incoming one. Moved entries keep their
+    /// `lock_key` state, count as recently used, and may evict older entries from an
+    /// LRU-bounded map.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if either internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if `on_conflict` is `Overwrite` and a key present in
+    ///   both maps has been locked with `lock_key` in this one; neither map is modified
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{MapError, OnConflict, TypeMap};
+    /// # fn main() -> Result<(), MapError> {
+    /// let settings: TypeMap<String> = TypeMap::new();
+    /// settings.set("theme".to_string(), "dark".to_string())?;
+    ///
+    /// let imported: TypeMap<String> = TypeMap::new();
+    /// imported.set("theme".to_string(), "light".to_string())?;
+    /// imported.set("font_size".to_string(), 14u8)?;
+    ///
+    /// settings.absorb(imported, OnConflict::Keep)?;
+    /// assert_eq!(settings.get::<String>(&"theme".to_string())?, "dark");
+    /// assert_eq!(settings.get::<u8>(&"font_size".to_string())?, 14);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn absorb(&self, other: TypeMap<K>, on_conflict: OnConflict) -> Result<(), MapError> {
+        if Arc::ptr_eq(&self.items, &other.items) {
+            return Ok(());
+        }
+        let self_first = (Arc::as_ptr(&self.items) as usize) < (Arc::as_ptr(&other.items) as usize);
+        let (mut store, mut incoming);
+        if self_first {
+            store = self.lock_items()?;
+            incoming = other.lock_items()?;
+        } else {
+            incoming = other.lock_items()?;
+            store = self.lock_items()?;
+        }
+
+        if on_conflict == OnConflict::Overwrite {
+            if let Some(key) = incoming
+                .keys()
+                .find(|key| store.get(*key).is_some_and(|entry| entry.locked))
+            {
+                return Err(MapError::Locked(format!("{:?}", key)));
+            }
+        }
+        other.lock_lru_order().clear();
+        for (key, mut value) in incoming.drain() {
+            other.adjust_count(value.type_id, false);
+            value.lru_tick = 0;
+            if on_conflict == OnConflict::Keep && store.contains_key(&key) {
+                continue;
+            }
+            self.insert_locked(&mut store, key, value)?;
+        }
+        Ok(())
+    }
+
+    /// Inserts an entry into the already-locked map, evicting the LRU entry if the
+    /// map is bounded and full.
+    #[allow(clippy::type_complexity)]
+    fn insert_locked(
+        &self,
+        store: &mut HashMap<K, AnyValue>,
+        key: K,
+        mut value: AnyValue,
+    ) -> Result<Option<(K, Box<dyn Any + Send + Sync>)>, MapError> {
+        Self::check_unlocked(store, &key)?;
+
+        let evicted = match self.capacity {
+            Some(capacity) if store.len() >= capacity && !store.contains_key(&key) => self
+                .lru_key(store)
+                .and_then(|k| self.remove_locked(store, &k))
+                .map(|(k, entry)| (k, entry.value)),
+            _ => None,
+        };
+
+        if let Some(existing) = store.get(&key) {
+            value.reads = existing.reads;
+            value.writes = existing.writes;
+            value.created_at = existing.created_at;
+            value.lru_tick = existing.lru_tick;
+        }
+        self.touch(&mut value);
+        self.index_access(&key, &mut value);
+        self.record_write(&mut value);
+        self.adjust_count(value.type_id, true);
+        if let Some(replaced) = store.insert(key, value) {
+            self.adjust_count(replaced.type_id, false);
+        }
+        Ok(evicted)
+    }
+
+    /// Removes an entry from the already-locked map.
+    ///
+    /// Every removal goes through here so the per-type counts stay accurate and
+    /// auto-shrink is applied.
+    fn remove_locked(&self, store: &mut HashMap<K, AnyValue>, key: &K) -> Option<(K, AnyValue)> {
+        let removed = store.remove_entry(key)?;
+        self.adjust_count(removed.1.type_id, false);
+        if removed.1.lru_tick != 0 {
+            self.lock_lru_order().remove(&removed.1.lru_tick);
+        }
+        if let Some(threshold) = self.shrink_below {
+            if (store.len() as f64) < threshold * store.capacity() as f64 {
+                store.shrink_to_fit();
+            }
+        }
+        Some(removed)
+    }
+
+    /// Records that an entry of the given type was added or removed.
+    ///
+    /// Must be called with the items lock held so the counts change in the same
+    /// order as the entries.
+    fn adjust_count(&self, type_id: TypeId, added: bool) {
+        if added {
+            self.counts.add(type_id);
+        } else {
+            self.counts.sub(type_id);
+        }
+    }
+
+    /// Acquires the LRU access index. No user code runs under it, so it is read
+    /// through a poisoning.
+    fn lock_lru_order(&self) -> std::sync::MutexGuard<'_, BTreeMap<u64, K>> {
+        self.lru_order
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// Files an entry in the LRU access index under its latest access tick.
+    ///
+    /// Must be called with the items lock held.
+    fn index_access(&self, key: &K, value: &mut AnyValue) {
+        if self.capacity.is_none() {
+            return;
+        }
+        let mut order = self.lock_lru_order();
+        if value.lru_tick != 0 {
+            order.remove(&value.lru_tick);
+        }
+        value.lru_tick = value.last_access;
+        order.insert(value.lru_tick, key.clone());
+    }
+
+    /// Finds the least-recently-used entry that may be evicted. Locked entries are
+    /// never evicted.
+    ///
+    /// Entries are filed under a tick that is never newer than their last access,
+    /// so the oldest filing whose tick is still current is the true LRU entry.
+    /// Filings left behind by later reads are moved up as they are reached, so each
+    /// read costs at most one re-filing and finding the victim is O(log n)
+    /// amortized. Entries the index doesn't know about, such as ones inserted
+    /// through `raw`, are only considered if no indexed entry can be evicted.
+    fn lru_key(&self, store: &mut HashMap<K, AnyValue>) -> Option<K> {
+        let mut order = self.lock_lru_order();
+        let mut locked = Vec::new();
+        let mut victim = None;
+        while let Some((tick, key)) = order.pop_first() {
+            let Some(entry) = store.get_mut(&key).filter(|entry| entry.lru_tick == tick) else {
+                // Left behind by a change made through `raw`
+                continue;
+            };
+            if entry.last_access != tick {
+                entry.lru_tick = entry.last_access;
+                order.insert(entry.lru_tick, key);
+            } else if entry.locked {
+                locked.push((tick, key));
+            } else {
+                victim = Some(key);
+                break;
+            }
+        }
+        order.extend(locked);
+        drop(order);
+
+        victim.or_else(|| {
+            store
+                .iter()
+                .filter(|(_, entry)| !entry.locked && entry.lru_tick == 0)
+                .min_by_key(|(_, entry)| entry.last_access)
+                .map(|(k, _)| k.clone())
+        })
+    }
+
+    /// Fails with `MapError::Locked` if `key` is present and has been locked.
+    fn check_unlocked(store: &HashMap<K, AnyValue>, key: &K) -> Result<(), MapError> {
+        match store.get(key) {
+            Some(entry) if entry.locked => Err(MapError::Locked(format!("{:?}", key))),
+            _ => Ok(()),
+        }
+    }
+
+    /// Stores a value generated by a closure
+    ///
+    /// This is useful for lazy initialization or complex value construction where
+    /// you want to avoid creating the value if the lock can't be acquired.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    ///
+    /// // Lazily construct a complex value
+    /// store.set_with("user_data".to_string(), || {
+    ///     // Imagine this is an expensive operation
+    ///     let mut data = Vec::new();
+    ///     for i in 0..1000 {
+    ///         data.push(i * 2);
+    ///     }
+    ///     data
+    /// })?;
+    ///
+    /// // Access the constructed data
+    /// store.with(&"user_data".to_string(), |data: &Vec<i32>| {
+    ///     println!("First value: {}", data.first().unwrap_or(&0));
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// Handling potential errors:
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() {
+    /// let store: TypeMap<String> = TypeMap::new();
+    ///
+    /// // Handle potential errors from set_with
+    /// match store.set_with("config".to_string(), || {
+    ///     // In a real scenario, this might load from a file
+    ///     std::collections::HashMap::<String, String>::new()
+    /// }) {
+    ///     Ok(()) => println!("Configuration stored successfully"),
+    ///     Err(MapError::LockError) => eprintln!("Failed to acquire lock - try again later"),
+    ///     Err(e) => eprintln!("Unexpected error: {}", e),
+    /// }
+    /// # }
+    /// ```
+    pub fn set_with<V, F>(&self, key: K, f: F) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        F: FnOnce() -> V,
+    {
+        let value = f();
+        self.set(key, value)
+    }
+
+    /// Stores a value under a key computed from the value itself
+    ///
+    /// Use this when the key is derived from the value, such as a user keyed by
+    /// its id. Computing the key here, rather than separately at each call site,
+    /// keeps the key from drifting out of sync with the value it names.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the computed key has been locked with `lock_key`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// #[derive(Clone, Debug)]
+    /// struct User { id: u64, name: String }
+    ///
+    /// let users: TypeMap<u64> = TypeMap::new();
+    /// users.set_by(User { id: 7, name: "Ann".to_string() }, |user| user.id)?;
+    ///
+    /// assert_eq!(users.get::<User>(&7)?.name, "Ann");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_by<V, F>(&self, value: V, key_fn: F) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        F: FnOnce(&V) -> K,
+    {
+        let key = key_fn(&value);
+        self.set(key, value)
+    }
+
+    /// Stores a lazily constructed value only if the key is absent
+    ///
+    /// The presence check and insert happen under one lock, and `f` only runs when the
+    /// key is missing, so an expensive value is built at most once even when several
+    /// threads race to initialize it. Unlike `set_with`, an existing value is never
+    /// overwritten, whatever its type.
+    ///
+    /// `f` runs while the lock is held, so it must not call back into this map.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(true)` if the value was inserted, `Ok(false)` if the key already existed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    ///
+    /// let inserted = store.set_if_absent_with("table".to_string(), || vec![0u8; 1024])?;
+    /// assert!(inserted);
+    ///
+    /// // The initializer doesn't run again
+    /// let inserted = store.set_if_absent_with("table".to_string(), || -> Vec<u8> {
+    ///     unreachable!("already initialized")
+    /// })?;
+    /// assert!(!inserted);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_if_absent_with<V, F>(&self, key: K, f: F) -> Result<bool, MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        F: FnOnce() -> V,
+    {
+        let mut store = self.lock_items()?;
+        if store.contains_key(&key) {
+            return Ok(false);
+        }
+        self.insert_locked(&mut store, key, AnyValue::new(f()))?;
+        Ok(true)
+    }
+
+    /// Gets a clone of the value for `key`, inserting one from `f` if the key is absent
+    ///
+    /// This is the strict variant: if the key already holds a value of a *different*
+    /// type, that value is left untouched and `TypeMismatch` is returned rather than
+    /// silently replacing it. Use [`get_or_insert_with_force`](Self::get_or_insert_with_force)
+    /// to overwrite instead. The check and insert happen under one lock, and `f` only
+    /// runs when the key is missing.
+    ///
+    /// `f` runs while the lock is held, so it must not call back into this map.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::TypeMismatch` if the key holds a value of a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    ///
+    /// let retries = store.get_or_insert_with_checked("retries".to_string(), || 3u32)?;
+    /// assert_eq!(retries, 3);
+    ///
+    /// // A wrong-typed value is an error, and is left in place
+    /// store.set("name".to_string(), "svc".to_string())?;
+    /// let result = store.get_or_insert_with_checked("name".to_string(), || 0u32);
+    /// assert!(matches!(result, Err(MapError::TypeMismatch { .. })));
+    /// assert_eq!(store.get::<String>(&"name".to_string())?, "svc");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_or_insert_with_checked<V, F>(&self, key: K, f: F) -> Result<V, MapError>
+    where
+        V: 'static + Any + Send + Sync + Clone,
+        F: FnOnce() -> V,
+    {
+        let mut store = self.lock_items()?;
+        if store.contains_key(&key) {
+            return self.read_locked(&mut store, &key, V::clone);
+        }
+        let value = f();
+        self.insert_locked(&mut store, key, AnyValue::new(value.clone()))?;
+        Ok(value)
+    }
+
+    /// Gets a clone of the value for `key`, inserting one from `f` if the key is absent
+    /// or holds a value of a different type
+    ///
+    /// This is the permissive counterpart to
+    /// [`get_or_insert_with_checked`](Self::get_or_insert_with_checked): a wrong-typed
+    /// value is replaced by the result of `f`. The check and insert happen under one
+    /// lock, and `f` only runs when a new value is needed.
+    ///
+    /// `f` runs while the lock is held, so it must not call back into this map.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if a wrong-typed value would be replaced but the key is locked
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("timeout".to_string(), "30s".to_string())?;
+    ///
+    /// let timeout = store.get_or_insert_with_force("timeout".to_string(), || 30u64)?;
+    /// assert_eq!(timeout, 30);
+    /// assert_eq!(store.get::<u64>(&"timeout".to_string())?, 30);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_or_insert_with_force<V, F>(&self, key: K, f: F) -> Result<V, MapError>
+    where
+        V: 'static + Any + Send + Sync + Clone,
+        F: FnOnce() -> V,
+    {
+        let mut store = self.lock_items()?;
+        if store.get(&key).is_some_and(|entry| entry.is_type::<V>()) {
+            return self.read_locked(&mut store, &key, V::clone);
+        }
+        let value = f();
+        self.insert_locked(&mut store, key, AnyValue::new(value.clone()))?;
+        Ok(value)
+    }
+
+    /// Gets a clone of the value memoized for `input`, recomputing it if `input` has changed
+    ///
+    /// The entry is stored as `(input_hash, value)`. If the key is absent, or the
+    /// stored hash differs from the hash of `input`, `compute` runs and its result
+    /// replaces the entry; otherwise the memoized value is returned without calling
+    /// `compute`. Inputs are compared by hash only, so two inputs that collide are
+    /// treated as the same. The check and insert happen under one lock.
+    ///
+    /// `compute` runs while the lock is held, so it must not call back into this map.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::TypeMismatch` if the key holds something other than a `(u64, V)`
+    /// - Returns `MapError::Locked` if the value needs recomputing but the key is locked
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let cache: TypeMap<String> = TypeMap::new();
+    /// let key = "word_count".to_string();
+    /// let mut runs = 0;
+    ///
+    /// let mut count = |text: &str| {
+    ///     cache.get_or_recompute(key.clone(), text, |text: &&str| {
+    ///         runs += 1;
+    ///         text.split_whitespace().count()
+    ///     })
+    /// };
+    /// assert_eq!(count("a b c")?, 3);
+    /// assert_eq!(count("a b c")?, 3); // memoized
+    /// assert_eq!(count("a b")?, 2); // input changed
+    /// assert_eq!(runs, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_or_recompute<V, I, F>(&self, key: K, input: I, compute: F) -> Result<V, MapError>
+    where
+        V: 'static + Any + Send + Sync + Clone,
+        I: Hash,
+        F: FnOnce(&I) -> V,
+    {
+        let mut hasher = DefaultHasher::new();
+        input.hash(&mut hasher);
+        let input_hash = hasher.finish();
+
+        let mut store = self.lock_items()?;
+        if store.contains_key(&key) {
+            let memoized = self.read_locked(&mut store, &key, |(hash, value): &(u64, V)| {
+                (*hash == input_hash).then(|| value.clone())
+            })?;
+            if let Some(value) = memoized {
+                return Ok(value);
+            }
+        }
+        let value = compute(&input);
+        self.insert_locked(&mut store, key, AnyValue::new((input_hash, value.clone())))?;
+        Ok(value)
+    }
+
+    /// Retrieves a clone of a value from the store
+    ///
+    /// This provides a convenient way to get a copy of a value when the `Clone`
+    /// trait is available. It always clones, which is costly for large values;
+    /// for more complex operations or to avoid cloning, use `with` instead.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `V` - The type of the value to retrieve. Must match the type that was stored.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("answer".to_string(), 42i32)?;
+    ///
+    /// // Get a clone of the value
+    /// let value = store.get::<i32>(&"answer".to_string())?;
+    /// assert_eq!(value, 42);
+    ///
+    /// // Handle possible errors
+    /// match store.get::<String>(&"non_existent".to_string()) {
+    ///     Ok(value) => println!("Value: {}", value),
+    ///     Err(MapError::KeyNotFound(key)) => println!("Key not found {}", key),
+    ///     Err(MapError::TypeMismatch { .. }) => println!("Type mismatch"),
+    ///     Err(MapError::LockError) => println!("Failed to acquire lock"),
+    ///     Err(e) => println!("Other error: {}", e),
+    /// }
+    ///
+    /// // Type mismatch example
+    /// store.set("name".to_string(), "Alice".to_string())?;
+    /// match store.get::<i32>(&"name".to_string()) {
+    ///     Ok(value) => println!("Value: {}", value),
+    ///     Err(MapError::TypeMismatch { .. }) => println!("The value is not an i32"),
+    ///     Err(e) => println!("Other error: {}", e),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get<V>(&self, key: &K) -> Result<V, MapError>
+    where
+        V: 'static + Clone,
+    {
+        self.with(key, |val: &V| val.clone())
+    }
+
+    /// Stores a value behind an `Arc` so it can be shared with [`get_arc`](Self::get_arc)
+    ///
+    /// This is shorthand for `set(key, Arc::new(value))`.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    pub fn set_arc<V>(&self, key: K, value: V) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        self.set(key, Arc::new(value))
+    }
+
+    /// Retrieves a shared handle to a value stored as an `Arc<V>`
+    ///
+    /// Only the `Arc` is cloned, so callers can keep the value beyond the lock
+    /// without deep-copying it, however large it is. The value must have been stored
+    /// as an `Arc<V>`, e.g. with [`set_arc`](Self::set_arc); a plain `V` under the
+    /// key is a type mismatch. The shared value is immutable: later writes replace
+    /// the `Arc` in the map and don't affect handles already handed out.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value is not an `Arc<V>`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set_arc("lexicon".to_string(), vec!["a".to_string(); 100_000])?;
+    ///
+    /// let lexicon = store.get_arc::<Vec<String>>(&"lexicon".to_string())?;
+    /// store.remove(&"lexicon".to_string())?;
+    ///
+    /// // The handle outlives both the lock and the entry
+    /// assert_eq!(lexicon.len(), 100_000);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_arc<V>(&self, key: &K) -> Result<Arc<V>, MapError>
+    where
+        V: 'static + Send + Sync,
+    {
+        self.with(key, |value: &Arc<V>| Arc::clone(value))
+    }
+
+    /// Retrieves a clone of a value, or `None` if it can't be read
+    ///
+    /// This is a best-effort convenience over `get` for call sites that don't need
+    /// to distinguish why a read failed: a missing key, a type mismatch and a
+    /// poisoned lock all collapse to `None`. Use `get` when the distinction matters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("retries".to_string(), 3u32)?;
+    ///
+    /// assert_eq!(store.get_opt::<u32>(&"retries".to_string()), Some(3));
+    /// assert_eq!(store.get_opt::<String>(&"retries".to_string()), None);
+    /// assert_eq!(store.get_opt::<u32>(&"timeout".to_string()), None);
+    ///
+    /// let retries = store.get_opt::<u32>(&"retries".to_string()).unwrap_or(1);
+    /// # assert_eq!(retries, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_opt<V>(&self, key: &K) -> Option<V>
+    where
+        V: 'static + Clone,
+    {
+        self.get(key).ok()
+    }
+
+    /// Retrieves a clone of the stored key together with a clone of its value
+    ///
+    /// This mirrors `HashMap::get_key_value`. It is useful when `K`'s equality is
+    /// looser than byte identity (for example case-insensitive or normalized keys),
+    /// so the canonical key that was stored may differ from the lookup key.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("answer".to_string(), 42i32)?;
+    ///
+    /// let (key, value) = store.get_key_value::<i32>(&"answer".to_string())?;
+    /// assert_eq!(key, "answer");
+    /// assert_eq!(value, 42);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_key_value<V>(&self, key: &K) -> Result<(K, V), MapError>
+    where
+        V: 'static + Clone,
+    {
+        let mut guard = self.lock_items()?;
+        let value = guard
+            .get_mut(key)
+            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
+
+        if !value.is_type::<V>() {
+            return Err(value.mismatch::<V>());
+        }
+        self.record_read(value);
+
+        // This is safe because we've checked the type
+        let value = value.downcast_ref::<V>().unwrap().clone();
+        let stored_key = guard.get_key_value(key).map(|(k, _)| k.clone()).unwrap();
+        Ok((stored_key, value))
+    }
+
+    /// Gets a value by executing a closure with read access
+    ///
+    /// This method allows for arbitrary operations on the stored value without
+    /// requiring the value to implement `Clone`. It's useful for inspecting values,
+    /// computing derived values, or performing operations that don't require ownership.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `V` - The type of the value to access. Must match the type that was stored.
+    /// * `F` - A closure that takes a reference to the value and returns a result.
+    /// * `R` - The return type of the closure.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("users".to_string(), vec!["Alice", "Bob", "Charlie"])?;
+    ///
+    /// // Read and compute something from the value
+    /// let user_count = store.with(&"users".to_string(), |users: &Vec<&str>| users.len())?;
+    /// println!("Number of users: {}", user_count);
+    ///
+    /// // Checking if a specific value exists
+    /// let has_alice = store.with(&"users".to_string(), |users: &Vec<&str>| {
+    ///     users.contains(&"Alice")
+    /// })?;
+    ///
+    /// // Handle potential errors with pattern matching
+    /// match store.with(&"settings".to_string(), |settings: &std::collections::HashMap<String, String>| {
+    ///     settings.get("theme").cloned()
+    /// }) {
+    ///     Ok(Some(theme)) => println!("Current theme: {}", theme),
+    ///     Ok(None) => println!("Theme setting not found"),
+    ///     Err(MapError::KeyNotFound(_)) => println!("Settings not initialized"),
+    ///     Err(MapError::TypeMismatch { .. }) => println!("Settings has unexpected type"),
+    ///     Err(e) => println!("Error: {}", e),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        let mut guard = self.lock_items()?;
+        self.read_locked(&mut guard, key, f)
+    }
+
+    /// Accesses a value with a read-only closure; an alias of [`with`](Self::with)
+    ///
+    /// Use it where spelling out read-only intent makes the call site clearer
+    /// next to [`with_write`](Self::with_write).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`with`](Self::with).
+    pub fn with_read<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        self.with(key, f)
+    }
+
+    /// Gets a value with read access, falling back to a borrowed default if the key is absent
+    ///
+    /// The closure runs against the stored value when the key exists, or against
+    /// `default` when it doesn't, replacing the usual `Err(KeyNotFound)` fallback match.
+    /// A key holding a different type is still reported as a mismatch rather than
+    /// quietly using the default.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::TypeMismatch` if the key holds a value of a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// let default_timeout = 30u64;
+    ///
+    /// let timeout = store.with_or(&"timeout".to_string(), &default_timeout, |t| *t)?;
+    /// assert_eq!(timeout, 30);
+    ///
+    /// store.set("timeout".to_string(), 5u64)?;
+    /// let timeout = store.with_or(&"timeout".to_string(), &default_timeout, |t| *t)?;
+    /// assert_eq!(timeout, 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_or<V: 'static, F, R>(&self, key: &K, default: &V, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        let mut guard = self.lock_items()?;
+        if !guard.contains_key(key) {
+            return Ok(f(default));
+        }
+        self.read_locked(&mut guard, key, f)
+    }
+
+    /// Gets a value with read access, along with a handle for reaching other keys
+    ///
+    /// Calling back into the map from inside a `with` closure deadlocks, because the
+    /// lock is already held (debug builds panic instead of hanging). `with_ctx` instead passes the closure a [`MapCtx`] that
+    /// operates on the already-locked map, so it can read, set and remove other keys
+    /// as part of the same critical section.
+    ///
+    /// The value under `key` is on loan to the closure, so any `MapCtx` operation on
+    /// that same key returns `MapError::BorrowConflict`. If the closure panics, the
+    /// lent value is put back before the panic continues, so the map stays complete
+    /// (the lock is poisoned, as with any panicking closure).
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("active_user".to_string(), "user:42".to_string())?;
+    /// store.set("user:42".to_string(), "Ada".to_string())?;
+    ///
+    /// // Follow a reference to another key without deadlocking
+    /// let name = store.with_ctx(&"active_user".to_string(), |id: &String, ctx| {
+    ///     ctx.get::<String>(id)
+    /// })??;
+    /// assert_eq!(name, "Ada");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_ctx<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V, &MapCtx<'_, K>) -> R,
+    {
+        let mut guard = self.lock_items()?;
+
+        // Take the entry out so the closure can borrow it while the context
+        // borrows the rest of the map
+        let (held_key, mut held) = guard
+            .remove_entry(key)
+            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
+        if !held.is_type::<V>() {
+            let err = held.mismatch::<V>();
+            guard.insert(held_key, held);
+            return Err(err);
+        }
+        self.record_read(&mut held);
+
+        // Catch a panic so the held entry can be put back before unwinding resumes
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let ctx = MapCtx {
+                map: self,
+                store: RefCell::new(&mut *guard),
+                held: key,
+            };
+            // This is safe because we've checked the type
+            f(held.downcast_ref::<V>().unwrap(), &ctx)
+        }));
+
+        // Eviction while the entry was out dropped its filing in the access index
+        self.index_access(&held_key, &mut held);
+        guard.insert(held_key, held);
+        // Inserts through the context may have filled the map while the held
+        // entry was out; trim back to capacity now that it's back
+        if let Some(capacity) = self.capacity {
+            while guard.len() > capacity {
+                match self.lru_key(&mut guard) {
+                    Some(k) => self.remove_locked(&mut guard, &k),
+                    None => break,
+                };
+            }
+        }
+        result.map_err(|payload| panic::resume_unwind(payload))
+    }
+
+    /// Gets a value with read access, waiting at most `timeout` for the lock
+    ///
+    /// This behaves like `with`, but instead of blocking indefinitely on a contended
+    /// lock it gives up once `timeout` has elapsed. Use it to bound worst-case latency
+    /// for time-sensitive callers.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::Timeout` if the lock could not be acquired within `timeout`
+    /// - Returns `MapError::LockError` if the internal lock is poisoned
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("frame".to_string(), 60u32)?;
+    ///
+    /// match store.with_timeout(&"frame".to_string(), Duration::from_millis(5), |fps: &u32| *fps) {
+    ///     Ok(fps) => assert_eq!(fps, 60),
+    ///     Err(MapError::Timeout) => println!("Store busy, skipping this frame"),
+    ///     Err(e) => return Err(e),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_timeout<V: 'static, F, R>(
+        &self,
+        key: &K,
+        timeout: Duration,
+        f: F,
+    ) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        let mut guard = self.lock_within(timeout)?;
+        self.read_locked(&mut guard, key, f)
+    }
+
+    /// Gets a value with write access, waiting at most `timeout` for the lock
+    ///
+    /// This is the read-write counterpart to [`with_timeout`](Self::with_timeout).
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::Timeout` if the lock could not be acquired within `timeout`
+    /// - Returns `MapError::LockError` if the internal lock is poisoned
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    pub fn with_timeout_mut<V: 'static, F, R>(
+        &self,
+        key: &K,
+        timeout: Duration,
+        f: F,
+    ) -> Result<R, MapError>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        let mut guard = self.lock_within(timeout)?;
+        self.write_locked(&mut guard, key, f)
+    }
+
+    /// Gets a value by executing a closure with write access, catching a panic in the closure
+    ///
+    /// Behaves like [`with_mut`](Self::with_mut), except that a panic inside `f` is
+    /// caught before it can unwind out of this call. The lock is released normally,
+    /// so the map is not poisoned and stays usable. The panic hook still runs, and
+    /// the value keeps whatever changes `f` made before panicking, which counts as
+    /// a write.
+    ///
+    /// This is meant for trust boundaries such as FFI callbacks or request
+    /// handlers, where unwinding must not escape.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    /// - Returns `MapError::ClosurePanicked` with the panic message if `f` panicked
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("queue".to_string(), vec![1u32, 2])?;
+    ///
+    /// let result = store.with_mut_catch(&"queue".to_string(), |q: &mut Vec<u32>| {
+    ///     q[5] += 1; // out of bounds
+    /// });
+    /// assert!(matches!(result, Err(MapError::ClosurePanicked(_))));
+    ///
+    /// // The map is not poisoned
+    /// assert_eq!(store.get::<Vec<u32>>(&"queue".to_string())?, vec![1, 2]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_mut_catch<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        let mut guard = self.lock_items()?;
+        self.write_locked(&mut guard, key, |value: &mut V| {
+            panic::catch_unwind(AssertUnwindSafe(|| f(value)))
+        })?
+        .map_err(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Box<dyn Any>".to_string());
+            MapError::ClosurePanicked(message)
+        })
+    }
+
+    /// Returns the current version of an entry
+    ///
+    /// Every write through the map — `set` and its variants, `with_mut` and its
+    /// variants, and bulk mutations — gives the entry a new version greater than
+    /// any it had before, even across removal and re-insertion. Pass the result to
+    /// [`wait_for_change`](Self::wait_for_change) to wait for the next write.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    pub fn version(&self, key: &K) -> Result<u64, MapError> {
+        let store = self.lock_items()?;
+        store
+            .get(key)
+            .map(|entry| entry.version)
+            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))
+    }
+
+    /// Blocks until the entry's version exceeds `last_seen_version`, returning the new version
+    ///
+    /// Returns immediately if the entry has already changed since `last_seen_version`
+    /// was read. A missing key is waited on until it is set, so pass `0` to wait for
+    /// a key that doesn't exist yet. Mutating closures count as writes whether or not
+    /// they changed the value. Writes made through [`raw`](Self::raw) don't wake waiters.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if an internal lock cannot be acquired
+    /// - Returns `MapError::Timeout` if the entry hasn't changed within `timeout`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # use std::time::Duration;
+    /// # fn main() -> Result<(), MapError> {
+    /// let state: TypeMap<String> = TypeMap::new();
+    /// state.set("phase".to_string(), "loading")?;
+    /// let seen = state.version(&"phase".to_string())?;
+    ///
+    /// let worker = state.clone();
+    /// let handle = std::thread::spawn(move || {
+    ///     worker.set("phase".to_string(), "ready").unwrap();
+    /// });
+    ///
+    /// state.wait_for_change(&"phase".to_string(), seen, Duration::from_secs(5))?;
+    /// assert_eq!(state.get::<&str>(&"phase".to_string())?, "ready");
+    /// # handle.join().unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wait_for_change(
+        &self,
+        key: &K,
+        last_seen_version: u64,
+        timeout: Duration,
+    ) -> Result<u64, MapError> {
+        let deadline = Instant::now() + timeout;
+        let signal = &*self.change_signal;
+        let (generation, changed) = (&signal.generation, &signal.changed);
+        let _waiting = Waiting::enter(&signal.waiters);
+        loop {
+            // Read the generation before checking the entry, so a write that lands
+            // between the check and the wait still wakes us
+            let seen = *generation.lock().map_err(|_| MapError::LockError)?;
+            {
+                let store = self.lock_items()?;
+                if let Some(version) = store
+                    .get(key)
+                    .map(|entry| entry.version)
+                    .filter(|&version| version > last_seen_version)
+                {
+                    return Ok(version);
+                }
+            }
+
+            let mut current = generation.lock().map_err(|_| MapError::LockError)?;
+            while *current == seen {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Err(MapError::Timeout);
+                }
+                current = changed
+                    .wait_timeout(current, deadline - now)
+                    .map_err(|_| MapError::LockError)?
+                    .0;
+            }
+        }
+    }
+
+    /// Returns the read and write counts for an entry, or `None` if the key is absent
+    ///
+    /// Reads are keyed reads such as `get`, `with`, `with_any` and their variants;
+    /// bulk traversals like `for_each_value` are not counted. Writes are `set` and
+    /// its variants, `with_mut` and its variants, and bulk mutations. The counts and
+    /// `created_at` survive overwrites and start over once the key is removed.
+    /// Counters are updated under the lock each operation already holds. Changes
+    /// made through [`raw`](Self::raw) are not counted.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let cache: TypeMap<String> = TypeMap::new();
+    /// let key = "user:42".to_string();
+    /// cache.set(key.clone(), "Ada".to_string())?;
+    /// cache.get::<String>(&key)?;
+    /// cache.get::<String>(&key)?;
+    ///
+    /// let stats = cache.stats(&key)?.unwrap();
+    /// assert_eq!((stats.reads, stats.writes), (2, 1));
+    /// assert!(cache.stats(&"user:7".to_string())?.is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stats(&self, key: &K) -> Result<Option<EntryStats>, MapError> {
+        let store = self.lock_items()?;
+        Ok(store.get(key).map(|entry| EntryStats {
+            reads: entry.reads,
+            writes: entry.writes,
+            created_at: entry.created_at,
+        }))
+    }
+
+    /// Gets a value by executing a closure with type-erased read access
+    ///
+    /// The closure receives the stored value as `&dyn Any`, so it can try several
+    /// downcasts within a single lock acquisition. This handles the "value is A or B"
+    /// case without re-locking for each attempt or treating `TypeMismatch` as control flow.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("port".to_string(), 8080u16)?;
+    ///
+    /// let port = store.with_any(&"port".to_string(), |value| {
+    ///     if let Some(port) = value.downcast_ref::<u16>() {
+    ///         Some(*port)
+    ///     } else if let Some(port) = value.downcast_ref::<String>() {
+    ///         port.parse().ok()
+    ///     } else {
+    ///         None
+    ///     }
+    /// })?;
+    /// assert_eq!(port, Some(8080));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_any<F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&dyn Any) -> R,
+    {
+        let mut guard = self.lock_items()?;
+        let value = guard
+            .get_mut(key)
+            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
+
+        self.record_read(value);
+        Ok(f(&*value.value))
+    }
+
+    /// Gets a value's stored `TypeId` and type-erased contents under one lock
+    ///
+    /// This is the primitive beneath `with`, `with_any` and friends: the closure can
+    /// inspect which type is stored and decide whether (and as what) to downcast,
+    /// all while the lock is held. Prefer the `TypeId` passed in over calling
+    /// `type_id()` on the `&dyn Any`, which is easy to get wrong through a reference.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # use std::any::TypeId;
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("ttl".to_string(), 30u64)?;
+    ///
+    /// let stale = store.with_raw(&"ttl".to_string(), |type_id, value| {
+    ///     type_id != TypeId::of::<u64>() || value.downcast_ref::<u64>() == Some(&0)
+    /// })?;
+    /// assert!(!stale);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_raw<F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(TypeId, &dyn Any) -> R,
+    {
+        let mut guard = self.lock_items()?;
+        let value = guard
+            .get_mut(key)
+            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
+
+        self.record_read(value);
+        Ok(f(value.type_id, &*value.value))
+    }
+
+    /// Gets a value by executing a closure with write access
+    ///
+    /// This method allows for modifying the stored value in place without
+    /// replacing it entirely. It's useful for updating collections or
+    /// complex structures.
+    ///
+    /// # Type Parameters
+    ///
+    /// * `V` - The type of the value to access. Must match the type that was stored.
+    /// * `F` - A closure that takes a mutable reference to the value and returns a result.
+    /// * `R` - The return type of the closure.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    ///
+    /// // Initialize a vector
+    /// store.set("numbers".to_string(), vec![1, 2, 3])?;
+    ///
+    /// // Modify the vector in place
+    /// store.with_mut(&"numbers".to_string(), |numbers: &mut Vec<i32>| {
+    ///     numbers.push(4);
+    ///     numbers.push(5);
+    /// })?;
+    ///
+    /// // Verify the modification
+    /// let count = store.with(&"numbers".to_string(), |numbers: &Vec<i32>| {
+    ///     assert_eq!(numbers, &[1, 2, 3, 4, 5]);
+    ///     numbers.len()
+    /// })?;
+    /// println!("Vector now has {} elements", count);
+    ///
+    /// // Example with a HashMap
+    /// store.set("counters".to_string(), std::collections::HashMap::<String, i32>::new())?;
+    ///
+    /// // Update counter values
+    /// let result = store.with_mut(&"counters".to_string(), |counters: &mut std::collections::HashMap<String, i32>| {
+    ///     *counters.entry("visits".to_string()).or_insert(0) += 1;
+    ///     counters.get("visits").copied() // Return current count
+    /// })?;
+    /// println!("Visit count: {}", result.unwrap_or(0));
+    ///
+    /// // Error handling example
+    /// match store.with_mut(&"config".to_string(), |config: &mut std::collections::HashMap<String, String>| {
+    ///     config.insert("theme".to_string(), "dark".to_string())
+    /// }) {
+    ///     Ok(old_theme) => println!("Previous theme: {:?}", old_theme),
+    ///     Err(MapError::KeyNotFound(_)) => println!("Config not found"),
+    ///     Err(MapError::TypeMismatch { .. }) => println!("Config has wrong type"),
+    ///     Err(e) => println!("Error: {}", e),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_mut<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        let mut guard = self.lock_items()?;
+        self.write_locked(&mut guard, key, f)
+    }
+
+    /// Accesses a value with a read-write closure; an alias of [`with_mut`](Self::with_mut)
+    ///
+    /// # Errors
+    ///
+    /// Same as [`with_mut`](Self::with_mut).
+    pub fn with_write<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        self.with_mut(key, f)
+    }
+
+    /// Gets a value with write access, removing it afterwards if the closure returns `false`
+    ///
+    /// The mutation and the removal happen under one lock, so no other thread can
+    /// observe or act on the value between deciding it should go and removing it.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// struct Connection { failures: u32 }
+    ///
+    /// let pool: TypeMap<String> = TypeMap::new();
+    /// pool.set("db-1".to_string(), Connection { failures: 2 })?;
+    ///
+    /// // Record a failure and drop the connection once it has failed three times
+    /// pool.with_mut_or_remove(&"db-1".to_string(), |conn: &mut Connection| {
+    ///     conn.failures += 1;
+    ///     conn.failures < 3
+    /// })?;
+    /// assert!(!pool.contains_key(&"db-1".to_string())?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_mut_or_remove<V: 'static, F>(&self, key: &K, f: F) -> Result<(), MapError>
+    where
+        F: FnOnce(&mut V) -> bool,
+    {
+        let mut guard = self.lock_items()?;
+        if !self.write_locked(&mut guard, key, f)? {
+            self.remove_locked(&mut guard, key);
+        }
+        Ok(())
+    }
+
+    /// Modifies a value with write access and returns it as it was before and after
+    ///
+    /// The value is cloned before the closure runs and again afterwards, and both
+    /// copies are returned alongside the closure's result as `(result, before, after)`.
+    /// This lets reactive code compute a diff for change notifications without a
+    /// separate read. It costs two clones of `V` per call, so use plain `with_mut`
+    /// when the diff isn't needed.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("volume".to_string(), 5u8)?;
+    ///
+    /// let (changed, before, after) = store.with_mut_diff(&"volume".to_string(), |v: &mut u8| {
+    ///     *v = (*v + 3).min(10);
+    ///     true
+    /// })?;
+    /// assert!(changed);
+    /// assert_eq!((before, after), (5, 8));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_mut_diff<V, F, R>(&self, key: &K, f: F) -> Result<(R, V, V), MapError>
+    where
+        V: 'static + Clone,
+        F: FnOnce(&mut V) -> R,
+    {
+        let mut guard = self.lock_items()?;
+        self.write_locked(&mut guard, key, |value: &mut V| {
+            let before = value.clone();
+            let result = f(value);
+            (result, before, value.clone())
+        })
+    }
+
+    /// Removes the items matching `pred` from a `Vec`-valued entry and returns them
+    ///
+    /// The entry must hold a `Vec<Item>`. Matching items are moved out by value, in
+    /// their original order, and the rest stay behind in order, all under one lock.
+    /// This is the in-place "take the finished jobs off the queue" operation that
+    /// would otherwise need a clone of the whole collection. If `pred` panics, the
+    /// items it hasn't drained yet stay in the entry.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    /// - Returns `MapError::TypeMismatch` if the value is not a `Vec<Item>`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// #[derive(Debug, PartialEq)]
+    /// struct Job { id: u32, done: bool }
+    ///
+    /// let queues: TypeMap<String> = TypeMap::new();
+    /// queues.set("render".to_string(), vec![
+    ///     Job { id: 1, done: true },
+    ///     Job { id: 2, done: false },
+    ///     Job { id: 3, done: true },
+    /// ])?;
+    ///
+    /// let finished = queues.drain_from(&"render".to_string(), |job: &Job| job.done)?;
+    /// assert_eq!(finished.iter().map(|j| j.id).collect::<Vec<_>>(), vec![1, 3]);
+    ///
+    /// queues.with(&"render".to_string(), |jobs: &Vec<Job>| {
+    ///     assert_eq!(jobs, &vec![Job { id: 2, done: false }]);
+    /// })?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn drain_from<Item, F>(&self, key: &K, mut pred: F) -> Result<Vec<Item>, MapError>
+    where
+        Item: 'static,
+        F: FnMut(&Item) -> bool,
+    {
+        let mut guard = self.lock_items()?;
+        self.write_locked(&mut guard, key, |items: &mut Vec<Item>| {
+            // Remove matches one at a time so a panicking `pred` leaves every item
+            // not yet drained in the stored `Vec`
+            let mut drained = Vec::new();
+            let mut i = 0;
+            while i < items.len() {
+                if pred(&items[i]) {
+                    drained.push(items.remove(i));
+                } else {
+                    i += 1;
+                }
+            }
+            drained
+        })
+    }
+
+    /// Mutates a value, inserting `V::default()` first if the key is absent
+    ///
+    /// The check, insert and mutation all happen under one lock, so this is the
+    /// atomic form of "increment the counter for this key, starting at zero".
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::TypeMismatch` if the key holds a value of a different type
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let counters: TypeMap<String> = TypeMap::new();
+    ///
+    /// for path in ["/", "/about", "/"] {
+    ///     counters.modify_or_default(path.to_string(), |hits: &mut u64| *hits += 1)?;
+    /// }
+    ///
+    /// assert_eq!(counters.get::<u64>(&"/".to_string())?, 2);
+    /// assert_eq!(counters.get::<u64>(&"/about".to_string())?, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn modify_or_default<V, F, R>(&self, key: K, f: F) -> Result<R, MapError>
+    where
+        V: 'static + Any + Send + Sync + Default,
+        F: FnOnce(&mut V) -> R,
+    {
+        let mut store = self.lock_items()?;
+        if !store.contains_key(&key) {
+            self.insert_locked(&mut store, key.clone(), AnyValue::new(V::default()))?;
+        }
+        self.write_locked(&mut store, &key, f)
+    }
+
+    /// Mutates the values stored under several distinct keys under a single lock
+    ///
+    /// Every key is validated before any closure runs: the keys must be distinct and
+    /// each must hold a value of type `V`. If validation fails nothing is modified.
+    /// The closure is then called once per key, in the order given.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::DuplicateKey` if a key appears more than once
+    /// - Returns `MapError::KeyNotFound` if any key doesn't exist in the store
+    /// - Returns `MapError::Locked` if any key has been locked with `lock_key`
+    /// - Returns `MapError::TypeMismatch` if any key holds a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// let workers = ["w1".to_string(), "w2".to_string(), "w3".to_string()];
+    /// for worker in &workers {
+    ///     store.set(worker.clone(), 0u32)?;
+    /// }
+    ///
+    /// // Hand out 10 units round-robin across all workers atomically
+    /// let mut remaining = 10u32;
+    /// while remaining > 0 {
+    ///     store.with_each_mut(&workers, |_, units: &mut u32| {
+    ///         if remaining > 0 {
+    ///             *units += 1;
+    ///             remaining -= 1;
+    ///         }
+    ///     })?;
+    /// }
+    /// assert_eq!(store.get::<u32>(&"w1".to_string())?, 4);
+    /// assert_eq!(store.get::<u32>(&"w3".to_string())?, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_each_mut<V, F>(&self, keys: &[K], mut f: F) -> Result<(), MapError>
+    where
+        V: 'static,
+        F: FnMut(&K, &mut V),
+    {
+        let mut store = self.lock_items()?;
+
+        let mut seen = HashSet::with_capacity(keys.len());
+        for key in keys {
+            if !seen.insert(key) {
+                return Err(MapError::DuplicateKey(format!("{:?}", key)));
+            }
+            let value = store
+                .get(key)
+                .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
+            if value.locked {
+                return Err(MapError::Locked(format!("{:?}", key)));
+            }
+            if !value.is_type::<V>() {
+                return Err(value.mismatch::<V>());
+            }
+        }
+
+        for key in keys {
+            self.write_locked(&mut store, key, |value: &mut V| f(key, value))?;
+        }
+        Ok(())
+    }
+
+    /// Removes a value from the store
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(true)` if the key was present and the value was removed.
+    /// Returns `Ok(false)` if the key was not present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("temp".to_string(), "This is temporary".to_string())?;
+    ///
+    /// // Remove the value
+    /// let was_removed = store.remove(&"temp".to_string())?;
+    /// assert!(was_removed);
+    ///
+    /// // Check that it's gone
+    /// match store.get::<String>(&"temp".to_string()) {
+    ///     Err(MapError::KeyNotFound(key)) => println!("Key `{}` was successfully removed", key),
+    ///     Ok(_) => println!("Key still exists"),
+    ///     Err(e) => println!("Error: {}", e),
+    /// }
+    ///
+    /// // Removing a non-existent key
+    /// let was_removed = store.remove(&"nonexistent".to_string())?;
+    /// assert!(!was_removed);
+    ///
+    /// // Using pattern matching for error handling
+    /// match store.remove(&"another_key".to_string()) {
+    ///     Ok(true) => println!("Key was found and removed"),
+    ///     Ok(false) => println!("Key did not exist"),
+    ///     Err(MapError::LockError) => println!("Failed to acquire lock"),
+    ///     Err(e) => println!("Unexpected error: {}", e),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove(&self, key: &K) -> Result<bool, MapError> {
+        let mut store = self.lock_items()?;
+        Self::check_unlocked(&store, key)?;
+        Ok(self.remove_locked(&mut store, key).is_some())
+    }
+
+    /// Removes and returns a value only if it satisfies a predicate
+    ///
+    /// The type check, predicate and removal all happen under one lock, so no other
+    /// thread can change the value between the check and the removal. If the
+    /// predicate returns `false` the value is left in place and `Ok(None)` is returned.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// struct Connection { closed: bool }
+    ///
+    /// let pool: TypeMap<u32> = TypeMap::new();
+    /// pool.set(1, Connection { closed: false })?;
+    /// pool.set(2, Connection { closed: true })?;
+    ///
+    /// assert!(pool.take_if(&1, |c: &Connection| c.closed)?.is_none());
+    /// assert!(pool.take_if(&2, |c: &Connection| c.closed)?.is_some());
+    /// assert_eq!(pool.keys()?, vec![1]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn take_if<V, F>(&self, key: &K, predicate: F) -> Result<Option<V>, MapError>
+    where
+        V: 'static,
+        F: FnOnce(&V) -> bool,
+    {
+        let mut store = self.lock_items()?;
+        let value = store
+            .get(key)
+            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
+
+        if value.locked {
+            return Err(MapError::Locked(format!("{:?}", key)));
+        }
+        let reference = value
+            .downcast_ref::<V>()
+            .ok_or_else(|| value.mismatch::<V>())?;
+        if !predicate(reference) {
+            return Ok(None);
+        }
+
+        // The key is present and its type was checked above
+        let (_, entry) = self.remove_locked(&mut store, key).unwrap();
+        Ok(Some(*entry.value.downcast::<V>().unwrap()))
+    }
+
+    /// Removes and returns every entry of type `V` that matches `predicate`, under one lock
+    ///
+    /// Non-matching entries, and entries holding other types, stay in place. This
+    /// is the partition-and-remove step of a work queue: take the ready items out
+    /// for processing and leave the pending ones. `predicate` runs once per entry
+    /// of type `V`, in arbitrary order, and the removed entries are returned in
+    /// that order.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if any matching entry has been locked with
+    ///   `lock_key`; nothing is removed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// struct Task { ready: bool }
+    ///
+    /// let queue: TypeMap<String> = TypeMap::new();
+    /// queue.set("build".to_string(), Task { ready: true })?;
+    /// queue.set("deploy".to_string(), Task { ready: false })?;
+    ///
+    /// let ready = queue.extract_if(|_key, task: &Task| task.ready)?;
+    /// assert_eq!(ready.len(), 1);
+    /// assert_eq!(ready[0].0, "build");
+    /// assert_eq!(queue.keys()?, vec!["deploy".to_string()]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract_if<V, F>(&self, mut predicate: F) -> Result<Vec<(K, V)>, MapError>
+    where
+        V: 'static,
+        F: FnMut(&K, &V) -> bool,
+    {
+        let mut store = self.lock_items()?;
+        let mut matched = Vec::new();
+        for (key, entry) in store.iter() {
+            if let Some(value) = entry.downcast_ref::<V>() {
+                if predicate(key, value) {
+                    if entry.locked {
+                        return Err(MapError::Locked(format!("{:?}", key)));
+                    }
+                    matched.push(key.clone());
+                }
+            }
+        }
+
+        Ok(matched
+            .iter()
+            .map(|key| {
+                // Every matched key is present and holds a V, checked above
+                let (key, entry) = self.remove_locked(&mut store, key).unwrap();
+                (key, *entry.value.downcast::<V>().unwrap())
+            })
+            .collect())
+    }
+
+    /// Removes several keys under a single lock acquisition
+    ///
+    /// All removals happen atomically with respect to other operations on the map,
+    /// so no observer sees a half-removed state. Keys that aren't present are ignored.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if any present key has been locked with `lock_key`; nothing is removed
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of keys that were present and removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("beta.ui".to_string(), true)?;
+    /// store.set("beta.api".to_string(), true)?;
+    /// store.set("stable".to_string(), true)?;
+    ///
+    /// let removed = store.remove_many(vec![
+    ///     "beta.ui".to_string(),
+    ///     "beta.api".to_string(),
+    ///     "beta.never_set".to_string(),
+    /// ])?;
+    /// assert_eq!(removed, 2);
+    /// assert_eq!(store.len()?, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_many<I>(&self, keys: I) -> Result<usize, MapError>
+    where
+        I: IntoIterator<Item = K>,
+    {
+        let mut store = self.lock_items()?;
+        let keys: Vec<K> = keys.into_iter().collect();
+        for key in &keys {
+            Self::check_unlocked(&store, key)?;
+        }
+        Ok(keys
+            .iter()
+            .filter(|key| self.remove_locked(&mut store, key).is_some())
+            .count())
+    }
+
+    /// Removes every entry whose stored type is one of `types`, whatever its key
+    ///
+    /// The scan and removal happen under a single lock acquisition. This is the
+    /// type-based counterpart to `remove_many`, for purging all values of some
+    /// types without tracking which keys hold them.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if any matching key has been locked with `lock_key`; nothing is removed
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of entries removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # use std::any::TypeId;
+    /// # fn main() -> Result<(), MapError> {
+    /// struct BetaBanner(String);
+    /// struct BetaLimits(u32);
+    ///
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("home.banner".to_string(), BetaBanner("Try it!".to_string()))?;
+    /// store.set("api.limits".to_string(), BetaLimits(10))?;
+    /// store.set("api.version".to_string(), 3u32)?;
+    ///
+    /// let removed = store.remove_types(&[TypeId::of::<BetaBanner>(), TypeId::of::<BetaLimits>()])?;
+    /// assert_eq!(removed, 2);
+    /// assert_eq!(store.keys()?, vec!["api.version".to_string()]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_types(&self, types: &[TypeId]) -> Result<usize, MapError> {
+        let mut store = self.lock_items()?;
+        if let Some((key, _)) = store
+            .iter()
+            .find(|(_, entry)| entry.locked && types.contains(&entry.type_id))
+        {
+            return Err(MapError::Locked(format!("{:?}", key)));
+        }
+        let doomed: Vec<K> = store
+            .iter()
+            .filter(|(_, entry)| types.contains(&entry.type_id))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &doomed {
+            self.remove_locked(&mut store, key);
+        }
+        Ok(doomed.len())
+    }
+
+    /// Checks if a key exists in the store
+    ///
+    /// This method only checks for the existence of the key and does not validate
+    /// the type of the stored value.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("config".to_string(), std::collections::HashMap::<String, String>::new())?;
+    ///
+    /// // Check if a key exists
+    /// let has_config = store.contains_key(&"config".to_string())?;
+    /// assert!(has_config);
+    ///
+    /// let has_users = store.contains_key(&"users".to_string())?;
+    /// assert!(!has_users);
+    ///
+    /// // Use in conditional logic
+    /// if !store.contains_key(&"initialized".to_string())? {
+    ///     store.set("initialized".to_string(), true)?;
+    ///     println!("Store initialized for the first time");
+    /// }
+    ///
+    /// // Error handling
+    /// match store.contains_key(&"settings".to_string()) {
+    ///     Ok(true) => println!("Settings exist"),
+    ///     Ok(false) => println!("Settings do not exist"),
+    ///     Err(e) => println!("Error checking settings: {}", e),
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn contains_key(&self, key: &K) -> Result<bool, MapError> {
+        let store = self.lock_items()?;
+        Ok(store.contains_key(key))
+    }
+
+    /// Checks whether every key in `keys` exists, under a single lock acquisition
+    ///
+    /// Like `contains_key`, this only checks presence, not the stored types.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn contains_all(&self, keys: &[K]) -> Result<bool, MapError> {
+        let store = self.lock_items()?;
+        Ok(keys.iter().all(|key| store.contains_key(key)))
+    }
+
+    /// Gets the keys in `keys` that don't exist, under a single lock acquisition
+    ///
+    /// Missing keys are returned in the order they were given. Use this for a
+    /// single fail-fast check that all required keys are configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let config: TypeMap<String> = TypeMap::new();
+    /// config.set("db.url".to_string(), "postgres://localhost".to_string())?;
+    ///
+    /// let required = ["db.url".to_string(), "db.pool".to_string(), "api.key".to_string()];
+    /// assert!(!config.contains_all(&required)?);
+    /// assert_eq!(config.missing_keys(&required)?, vec!["db.pool", "api.key"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn missing_keys(&self, keys: &[K]) -> Result<Vec<K>, MapError> {
+        let store = self.lock_items()?;
+        Ok(keys
+            .iter()
+            .filter(|key| !store.contains_key(key))
+            .cloned()
+            .collect())
+    }
+
+    /// Makes a key immutable
+    ///
+    /// Once locked, any attempt to overwrite, mutate or remove the key fails with
+    /// `MapError::Locked`, while reads keep working and other keys are unaffected.
+    /// Locks are permanent for the lifetime of the entry; there is no unlock. This
+    /// gives per-key write protection, for example so plugins can't replace a core
+    /// service. Locking an already-locked key is a no-op.
+    ///
+    /// Access through [`raw`](Self::raw) is not checked.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let services: TypeMap<String> = TypeMap::new();
+    /// services.set("auth".to_string(), "core-auth".to_string())?;
+    /// services.lock_key(&"auth".to_string())?;
+    ///
+    /// // A plugin trying to swap out the core service is rejected
+    /// let result = services.set("auth".to_string(), "evil-auth".to_string());
+    /// assert!(matches!(result, Err(MapError::Locked(_))));
+    ///
+    /// // Reads and other keys are unaffected
+    /// assert_eq!(services.get::<String>(&"auth".to_string())?, "core-auth");
+    /// services.set("theme".to_string(), "dark".to_string())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lock_key(&self, key: &K) -> Result<(), MapError> {
+        let mut store = self.lock_items()?;
+        let entry = store
+            .get_mut(key)
+            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
+        entry.locked = true;
+        Ok(())
+    }
+
+    /// Checks whether a key has been locked with `lock_key`
+    ///
+    /// Returns `Ok(false)` for keys that don't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn is_key_locked(&self, key: &K) -> Result<bool, MapError> {
+        let store = self.lock_items()?;
+        Ok(store.get(key).is_some_and(|entry| entry.locked))
+    }
+
+    /// Gets all keys in the store
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("user".to_string(), "Alice".to_string())?;
+    /// store.set("count".to_string(), 42i32)?;
+    /// store.set("active".to_string(), true)?;
+    ///
+    /// // Get all keys
+    /// let keys = store.keys()?;
+    ///
+    /// // Keys are returned in arbitrary order, so sort for stable testing
+    /// let mut sorted_keys = keys.clone();
+    /// sorted_keys.sort();
+    ///
+    /// assert_eq!(sorted_keys, vec!["active".to_string(), "count".to_string(), "user".to_string()]);
+    /// println!("Store contains {} keys", keys.len());
+    ///
+    /// // Use keys to iterate over stored values
+    /// for key in keys {
+    ///     // We need to handle different types differently
+    ///     if let Ok(value) = store.get::<String>(&key) {
+    ///         println!("{}: String = {}", key, value);
+    ///     } else if let Ok(value) = store.get::<i32>(&key) {
+    ///         println!("{}: i32 = {}", key, value);
+    ///     } else if let Ok(value) = store.get::<bool>(&key) {
+    ///         println!("{}: bool = {}", key, value);
+    ///     } else {
+    ///         println!("{}: unknown type", key);
+    ///     }
+    /// }
+    ///
+    /// // Error handling
     /// match store.keys() {
     ///     Ok(keys) => println!("Found {} keys", keys.len()),
     ///     Err(MapError::LockError) => println!("Failed to acquire lock"),
@@ -503,19 +3148,442 @@ where
     /// # Ok(())
     /// # }
     /// ```
-    pub fn keys(&self) -> Result<Vec<K>, MapError>
+    pub fn keys(&self) -> Result<Vec<K>, MapError>
+    where
+        K: Clone,
+    {
+        let store = self.lock_items()?;
+        Ok(store.keys().cloned().collect())
+    }
+
+    /// Gets all keys in the store, sorted
+    ///
+    /// `keys()` returns keys in hash order; this sorts them for deterministic output
+    /// in logs and golden tests.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("b".to_string(), 2i32)?;
+    /// store.set("c".to_string(), 3i32)?;
+    /// store.set("a".to_string(), 1i32)?;
+    ///
+    /// assert_eq!(store.keys_ordered()?, vec!["a", "b", "c"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn keys_ordered(&self) -> Result<Vec<K>, MapError>
+    where
+        K: Ord,
+    {
+        let mut keys = self.keys()?;
+        keys.sort();
+        Ok(keys)
+    }
+
+    /// Lists every key together with the type name of its stored value
+    ///
+    /// Type names come from `std::any::type_name`, captured when the value was
+    /// stored; values stored with `set_boxed` are reported as `"<unknown>"`. This is
+    /// meant for diagnostics such as a debug endpoint, and the exact name format is
+    /// not guaranteed to be stable across compiler versions. Entries are returned
+    /// in arbitrary order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("port".to_string(), 8080u16)?;
+    /// store.set("debug".to_string(), true)?;
+    ///
+    /// let mut manifest = store.manifest()?;
+    /// manifest.sort();
+    /// assert_eq!(manifest, vec![("debug".to_string(), "bool"), ("port".to_string(), "u16")]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn manifest(&self) -> Result<Vec<(K, &'static str)>, MapError> {
+        let store = self.lock_items()?;
+        Ok(store
+            .iter()
+            .map(|(key, value)| (key.clone(), value.type_name))
+            .collect())
+    }
+
+    /// Returns the number of entries holding a value of type `V`
+    ///
+    /// The map keeps an atomic count per type, updated on every insert, overwrite
+    /// and removal, so this is a constant-time read that doesn't scan the entries or
+    /// wait for the main lock. That makes it cheap enough to poll, e.g. for an
+    /// autoscaler watching the number of live sessions.
+    ///
+    /// Entries changed through an `Arc` from [`raw`](Self::raw) or
+    /// [`into_parts`](Self::into_parts) bypass the counts. While such an `Arc` is
+    /// alive, this locks the map and scans every entry instead. The first call after
+    /// the last one is dropped recounts the entries once, and later calls are
+    /// constant-time again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// struct Session;
+    ///
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("s1".to_string(), Session)?;
+    /// store.set("s2".to_string(), Session)?;
+    /// store.set("limit".to_string(), 100u32)?;
+    /// assert_eq!(store.count_of_type::<Session>()?, 2);
+    ///
+    /// // Overwriting with another type moves the entry between counts
+    /// store.set("s2".to_string(), 0u32)?;
+    /// assert_eq!(store.count_of_type::<Session>()?, 1);
+    /// assert_eq!(store.count_of_type::<u32>()?, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn count_of_type<V: 'static>(&self) -> Result<usize, MapError> {
+        if self.counts.is_trusted() {
+            return Ok(self.counts.get(TypeId::of::<V>()));
+        }
+        let store = self.lock_items()?;
+        Ok(self.resync_counts::<V>(&store))
+    }
+
+    /// Like `count_of_type`, but never blocks, for `Debug` impls
+    pub(crate) fn peek_count_of_type<V: 'static>(&self) -> Option<usize> {
+        if self.counts.is_trusted() {
+            return Some(self.counts.get(TypeId::of::<V>()));
+        }
+        let store = lock::peek(&self.items).ok()?;
+        Some(self.resync_counts::<V>(&store))
+    }
+
+    /// Counts `V` for a map whose storage has been handed out, rebuilding the
+    /// counters if only map handles still hold it.
+    ///
+    /// Must be called with the items lock held.
+    fn resync_counts<V: 'static>(&self, store: &HashMap<K, AnyValue>) -> usize {
+        let exposed = self.counts.exposed.load(Ordering::SeqCst);
+        // Every map handle holds both `Arc`s, so any extra holder of `items` is
+        // storage handed out through `raw` or `into_parts`
+        if Arc::strong_count(&self.items) != Arc::strong_count(&self.counts) {
+            return store.values().filter(|value| value.is_type::<V>()).count();
+        }
+        self.counts.rebuild(store);
+        self.counts.synced.store(exposed, Ordering::SeqCst);
+        self.counts.get(TypeId::of::<V>())
+    }
+
+    /// Counts the keys that satisfy a predicate
+    ///
+    /// The scan runs under a single lock and doesn't allocate, unlike filtering the
+    /// result of `keys()`. Stored values are not inspected.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("session:a1".to_string(), 1u64)?;
+    /// store.set("session:b2".to_string(), 2u64)?;
+    /// store.set("config".to_string(), true)?;
+    ///
+    /// let sessions = store.count_keys(|key| key.starts_with("session:"))?;
+    /// assert_eq!(sessions, 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn count_keys<F>(&self, mut predicate: F) -> Result<usize, MapError>
+    where
+        F: FnMut(&K) -> bool,
+    {
+        let store = self.lock_items()?;
+        Ok(store.keys().filter(|key| predicate(key)).count())
+    }
+
+    /// Visits every stored value as `&dyn Any`
+    ///
+    /// This is the value-only counterpart to `keys()` for type-erased traversal: the
+    /// closure can `downcast_ref` each value against the types it knows about. Values
+    /// are visited in arbitrary order under a single lock, so the closure must not
+    /// call back into this map.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("a".to_string(), 1i32)?;
+    /// store.set("b".to_string(), 2i32)?;
+    /// store.set("c".to_string(), "text".to_string())?;
+    ///
+    /// let mut sum = 0;
+    /// store.for_each_value(|value| {
+    ///     if let Some(n) = value.downcast_ref::<i32>() {
+    ///         sum += n;
+    ///     }
+    /// })?;
+    /// assert_eq!(sum, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn for_each_value<F>(&self, mut f: F) -> Result<(), MapError>
+    where
+        F: FnMut(&dyn Any),
+    {
+        let store = self.lock_items()?;
+        for value in store.values() {
+            f(&*value.value);
+        }
+        Ok(())
+    }
+
+    /// Walks every entry under one lock, handing each to `visitor`
+    ///
+    /// This is the stateful counterpart to [`for_each_value`](Self::for_each_value):
+    /// the [`Visitor`] receives each key and type-erased value and downcasts against
+    /// the types it knows about. Entries are visited in arbitrary order, and the
+    /// visitor must not call back into this map.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::any::Any;
+    /// # use sovran_typemap::{TypeMap, MapError, Visitor};
+    /// # fn main() -> Result<(), MapError> {
+    /// struct Count(usize);
+    ///
+    /// impl Visitor<String> for Count {
+    ///     fn visit_any(&mut self, _key: &String, value: &dyn Any) {
+    ///         if value.is::<u32>() {
+    ///             self.0 += 1;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("a".to_string(), 1u32)?;
+    /// store.set("b".to_string(), "text".to_string())?;
+    ///
+    /// let mut count = Count(0);
+    /// store.accept(&mut count)?;
+    /// assert_eq!(count.0, 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn accept<V: Visitor<K>>(&self, visitor: &mut V) -> Result<(), MapError> {
+        let store = self.lock_items()?;
+        for (key, value) in store.iter() {
+            visitor.visit_any(key, &*value.value);
+        }
+        Ok(())
+    }
+
+    /// Runs a read-only closure on every value of type `V`, under one lock
+    ///
+    /// This is the non-cloning counterpart to [`snapshot`](Self::snapshot): each
+    /// matching entry is handed to the closure by reference, so large values are
+    /// never copied. Entries holding other types are skipped. Entries are visited
+    /// in arbitrary order, and the closure must not call back into this map.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let buffers: TypeMap<String> = TypeMap::new();
+    /// buffers.set("frame".to_string(), vec![0u8; 4096])?;
+    /// buffers.set("audio".to_string(), vec![0u8; 1024])?;
+    /// buffers.set("label".to_string(), "not a buffer".to_string())?;
+    ///
+    /// let mut total = 0;
+    /// buffers.for_each_of_type(|_key, buf: &Vec<u8>| total += buf.len())?;
+    /// assert_eq!(total, 5120);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn for_each_of_type<V: 'static, F>(&self, mut f: F) -> Result<(), MapError>
+    where
+        F: FnMut(&K, &V),
+    {
+        let store = self.lock_items()?;
+        for (key, value) in store.iter() {
+            if let Some(reference) = value.downcast_ref::<V>() {
+                f(key, reference);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a clone of the first entry of type `V` that matches `predicate`
+    ///
+    /// Entries holding other types are skipped. Entries are searched in arbitrary
+    /// order under one lock, so "first" means whichever match is reached first, and
+    /// the search stops there. Only the matching entry is cloned.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// enum Conn { Idle, Busy }
+    ///
+    /// let pool: TypeMap<String> = TypeMap::new();
+    /// pool.set("a".to_string(), Conn::Busy)?;
+    /// pool.set("b".to_string(), Conn::Idle)?;
+    ///
+    /// let idle = pool.find(|_key, conn: &Conn| *conn == Conn::Idle)?;
+    /// assert_eq!(idle, Some(("b".to_string(), Conn::Idle)));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find<V, F>(&self, mut predicate: F) -> Result<Option<(K, V)>, MapError>
+    where
+        V: 'static + Clone,
+        F: FnMut(&K, &V) -> bool,
+    {
+        let store = self.lock_items()?;
+        Ok(store.iter().find_map(|(key, value)| {
+            let value = value.downcast_ref::<V>()?;
+            predicate(key, value).then(|| (key.clone(), value.clone()))
+        }))
+    }
+
+    /// Checks whether any entry of type `V` matches `predicate`, under one lock
+    ///
+    /// The existence-only counterpart to [`find`](Self::find): nothing is cloned,
+    /// and the search stops at the first match. Entries holding other types are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let limits: TypeMap<String> = TypeMap::new();
+    /// limits.set("cpu".to_string(), 80u8)?;
+    /// limits.set("disk".to_string(), 95u8)?;
+    ///
+    /// assert!(limits.any(|_key, pct: &u8| *pct > 90)?);
+    /// assert!(!limits.any(|key, _: &u8| key == "memory")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn any<V: 'static, F>(&self, mut predicate: F) -> Result<bool, MapError>
+    where
+        F: FnMut(&K, &V) -> bool,
+    {
+        let store = self.lock_items()?;
+        Ok(store.iter().any(|(key, value)| {
+            value
+                .downcast_ref::<V>()
+                .is_some_and(|value| predicate(key, value))
+        }))
+    }
+
+    /// Runs a closure with write access to every value of type `V`, under one lock
+    ///
+    /// Entries holding other types are skipped. Entries are visited in arbitrary
+    /// order, and the closure must not call back into this map.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if any `V` entry has been locked with `lock_key`; nothing is modified
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// struct Body { y: f32, vy: f32 }
+    ///
+    /// let world: TypeMap<String> = TypeMap::new();
+    /// world.set("ball".to_string(), Body { y: 10.0, vy: 0.0 })?;
+    /// world.set("crate".to_string(), Body { y: 5.0, vy: 0.0 })?;
+    /// world.set("gravity".to_string(), -9.8f32)?;
+    ///
+    /// let g = world.get::<f32>(&"gravity".to_string())?;
+    /// world.for_each_mut_of_type(|_key, body: &mut Body| {
+    ///     body.vy += g;
+    ///     body.y += body.vy;
+    /// })?;
+    /// assert!(world.with(&"ball".to_string(), |b: &Body| b.y < 10.0)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn for_each_mut_of_type<V: 'static, F>(&self, mut f: F) -> Result<(), MapError>
     where
-        K: Clone,
+        F: FnMut(&K, &mut V),
     {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
-        Ok(store.keys().cloned().collect())
+        let mut store = self.lock_items()?;
+        if let Some((key, _)) = store
+            .iter()
+            .find(|(_, entry)| entry.locked && entry.is_type::<V>())
+        {
+            return Err(MapError::Locked(format!("{:?}", key)));
+        }
+        for (key, value) in store.iter_mut() {
+            if let Some(reference) = value.value.downcast_mut::<V>() {
+                f(key, reference);
+                self.touch(value);
+                self.record_write(value);
+            }
+        }
+        Ok(())
     }
 
     pub fn values<V>(&self) -> Result<Vec<V>, MapError>
     where
         V: 'static + Clone,
     {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let store = self.lock_items()?;
         let mut result = Vec::new();
 
         for value in store.values() {
@@ -530,6 +3598,44 @@ where
         Ok(result)
     }
 
+    /// Takes a point-in-time snapshot of every entry holding a value of type `V`
+    ///
+    /// The lock is held only while the matching keys and values are cloned into the
+    /// returned vector. Iterating the snapshot afterwards doesn't block writers, which
+    /// suits slow consumers like serialization or reporting. Entries of other types
+    /// are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("requests".to_string(), 120u64)?;
+    /// store.set("errors".to_string(), 3u64)?;
+    /// store.set("version".to_string(), "1.2.0".to_string())?;
+    ///
+    /// let mut stats = store.snapshot::<u64>()?;
+    /// stats.sort();
+    /// assert_eq!(stats, vec![("errors".to_string(), 3), ("requests".to_string(), 120)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn snapshot<V>(&self) -> Result<Vec<(K, V)>, MapError>
+    where
+        V: 'static + Clone,
+    {
+        let store = self.lock_items()?;
+        Ok(store
+            .iter()
+            .filter_map(|(key, value)| value.downcast_ref::<V>().map(|v| (key.clone(), v.clone())))
+            .collect())
+    }
+
     /// Gets the number of items in the store
     ///
     /// # Errors
@@ -570,7 +3676,7 @@ where
     /// # }
     /// ```
     pub fn len(&self) -> Result<usize, MapError> {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let store = self.lock_items()?;
         Ok(store.len())
     }
 
@@ -614,9 +3720,417 @@ where
     /// # }
     /// ```
     pub fn is_empty(&self) -> Result<bool, MapError> {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let store = self.lock_items()?;
         Ok(store.is_empty())
     }
+
+    /// Gets the number of items in the store, recovering from a poisoned lock
+    ///
+    /// A poisoned lock only means another thread panicked while holding it; the map
+    /// itself is still readable. This variant reads through the poison instead of
+    /// reporting it, which is convenient for assertions and log lines. Use
+    /// [`len`](Self::len) when poisoning should be surfaced as an error.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("one".to_string(), 1)?;
+    /// assert_eq!(store.len_or_zero(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn len_or_zero(&self) -> usize {
+        self.lock_items_lenient().len()
+    }
+
+    /// Checks if the store is empty, recovering from a poisoned lock
+    ///
+    /// See [`len_or_zero`](Self::len_or_zero) for how poisoning is handled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::TypeMap;
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// assert!(store.is_empty_lenient());
+    /// ```
+    pub fn is_empty_lenient(&self) -> bool {
+        self.lock_items_lenient().is_empty()
+    }
+}
+
+/// Access to the rest of a locked [`TypeMap`] from inside a [`TypeMap::with_ctx`] closure
+///
+/// Every operation works on the map whose lock is already held, so nothing here
+/// can deadlock. Operations on the key currently lent to the closure, or calls
+/// made from inside another `MapCtx::with` closure, fail with
+/// `MapError::BorrowConflict`.
+pub struct MapCtx<'a, K> {
+    map: &'a TypeMap<K>,
+    store: RefCell<&'a mut HashMap<K, AnyValue>>,
+    held: &'a K,
+}
+
+impl<'a, K> MapCtx<'a, K>
+where
+    K: Clone + Eq + Hash + Debug,
+{
+    /// Borrows the locked map for one operation on `key`.
+    fn store_for(&self, key: &K) -> Result<RefMut<'_, &'a mut HashMap<K, AnyValue>>, MapError> {
+        if key == self.held {
+            return Err(MapError::BorrowConflict);
+        }
+        self.store
+            .try_borrow_mut()
+            .map_err(|_| MapError::BorrowConflict)
+    }
+
+    /// Retrieves a clone of a value, like [`TypeMap::get`]
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::BorrowConflict` if `key` is the key lent to the closure
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    pub fn get<V>(&self, key: &K) -> Result<V, MapError>
+    where
+        V: 'static + Clone,
+    {
+        self.with(key, |value: &V| value.clone())
+    }
+
+    /// Accesses a value with a read-only closure, like [`TypeMap::with`]
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::BorrowConflict` if `key` is the key lent to the closure
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    pub fn with<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        let mut store = self.store_for(key)?;
+        self.map.read_locked(&mut store, key, f)
+    }
+
+    /// Stores a value, like [`TypeMap::set`]
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::BorrowConflict` if `key` is the key lent to the closure
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    pub fn set<V>(&self, key: K, value: V) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        let mut store = self.store_for(&key)?;
+        self.map
+            .insert_locked(&mut store, key, AnyValue::new(value))
+            .map(|_| ())
+    }
+
+    /// Removes a value, like [`TypeMap::remove`]
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::BorrowConflict` if `key` is the key lent to the closure
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    pub fn remove(&self, key: &K) -> Result<bool, MapError> {
+        let mut store = self.store_for(key)?;
+        TypeMap::check_unlocked(&store, key)?;
+        Ok(self.map.remove_locked(&mut store, key).is_some())
+    }
+
+    /// Checks if a key exists, like [`TypeMap::contains_key`]
+    ///
+    /// The key lent to the closure is reported as present.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::BorrowConflict` if called from inside a `MapCtx::with` closure.
+    pub fn contains_key(&self, key: &K) -> Result<bool, MapError> {
+        if key == self.held {
+            return Ok(true);
+        }
+        let store = self
+            .store
+            .try_borrow()
+            .map_err(|_| MapError::BorrowConflict)?;
+        Ok(store.contains_key(key))
+    }
+}
+
+/// Namespace-style operations for maps with string-like keys such as `config.db.host`
+impl<K> TypeMap<K>
+where
+    K: Clone + Eq + Hash + Debug + AsRef<str>,
+{
+    /// Gets all keys that start with `prefix`
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("config.db.host".to_string(), "localhost".to_string())?;
+    /// store.set("config.db.port".to_string(), 5432u16)?;
+    /// store.set("config.cache.ttl".to_string(), 60u32)?;
+    ///
+    /// let mut keys = store.keys_with_prefix("config.db.")?;
+    /// keys.sort();
+    /// assert_eq!(keys, vec!["config.db.host", "config.db.port"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn keys_with_prefix(&self, prefix: &str) -> Result<Vec<K>, MapError> {
+        let store = self.lock_items()?;
+        Ok(store
+            .keys()
+            .filter(|key| key.as_ref().starts_with(prefix))
+            .cloned()
+            .collect())
+    }
+
+    /// Removes all keys that start with `prefix` under a single lock acquisition
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if any matching key has been locked with `lock_key`; nothing is removed
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of entries removed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.set("session.a".to_string(), 1u64)?;
+    /// store.set("session.b".to_string(), 2u64)?;
+    /// store.set("user.a".to_string(), 3u64)?;
+    ///
+    /// assert_eq!(store.remove_with_prefix("session.")?, 2);
+    /// assert_eq!(store.keys()?, vec!["user.a".to_string()]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove_with_prefix(&self, prefix: &str) -> Result<usize, MapError> {
+        let mut store = self.lock_items()?;
+        if let Some((key, _)) = store
+            .iter()
+            .find(|(key, entry)| entry.locked && key.as_ref().starts_with(prefix))
+        {
+            return Err(MapError::Locked(format!("{:?}", key)));
+        }
+        let doomed: Vec<K> = store
+            .keys()
+            .filter(|key| key.as_ref().starts_with(prefix))
+            .cloned()
+            .collect();
+        for key in &doomed {
+            self.remove_locked(&mut store, key);
+        }
+        Ok(doomed.len())
+    }
+
+    /// Retrieves a clone of a nested value by dotted path, e.g. `"config.db.port"`
+    ///
+    /// See [`with_path`](Self::with_path) for how the path is resolved.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if an internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if any segment of the path can't be resolved
+    /// - Returns `MapError::TypeMismatch` if the value at the path has a different type
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::collections::HashMap;
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<String> = TypeMap::new();
+    /// store.register_descent::<HashMap<String, HashMap<String, u16>>>()?;
+    /// store.register_descent::<HashMap<String, u16>>()?;
+    ///
+    /// let db = HashMap::from([("port".to_string(), 5432u16)]);
+    /// store.set("config".to_string(), HashMap::from([("db".to_string(), db)]))?;
+    ///
+    /// assert_eq!(store.get_path::<u16>("config.db.port")?, 5432);
+    /// assert!(store.get_path::<u16>("config.db.user").is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_path<V>(&self, path: &str) -> Result<V, MapError>
+    where
+        K: Borrow<str>,
+        V: 'static + Clone,
+    {
+        self.with_path(path, |value: &V| value.clone())
+    }
+
+    /// Accesses a nested value by dotted path with a read-only closure
+    ///
+    /// The first segment of `path` is looked up as a key in the map. Each further
+    /// segment is resolved against the value reached so far, whose type must have
+    /// been registered with [`register_descent`](TypeMap::register_descent). That
+    /// includes `HashMap<String, V>`, which has to be registered once for every `V`
+    /// a path passes through. The whole walk happens under one lock.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if an internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if any segment of the path can't be resolved;
+    ///   if an intermediate value's type was never registered, the message says so
+    /// - Returns `MapError::TypeMismatch` if the value at the path has a different type
+    pub fn with_path<V: 'static, F, R>(&self, path: &str, f: F) -> Result<R, MapError>
+    where
+        K: Borrow<str>,
+        F: FnOnce(&V) -> R,
+    {
+        let (first, rest) = match path.split_once('.') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (path, None),
+        };
+        let mut store = self.lock_items()?;
+        let Some(rest) = rest else {
+            return self.read_locked(&mut store, first, f);
+        };
+
+        let entry = store
+            .get_mut(first)
+            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", first)))?;
+        self.record_read(entry);
+
+        let descenders = self.descenders.lock().map_err(|_| MapError::LockError)?;
+        let mut current: &dyn Any = &*entry.value;
+        let mut resolved = first.len();
+        for segment in rest.split('.') {
+            let parent = &path[..resolved];
+            resolved += 1 + segment.len();
+            let Some(descend) = descenders.get(&(*current).type_id()) else {
+                return Err(MapError::KeyNotFound(format!(
+                    "{:?} (no descent registered for the type at {:?})",
+                    &path[..resolved],
+                    parent
+                )));
+            };
+            current = descend(current, segment)
+                .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", &path[..resolved])))?;
+        }
+
+        match current.downcast_ref::<V>() {
+            Some(value) => Ok(f(value)),
+            None => Err(MapError::TypeMismatch {
+                expected: std::any::type_name::<V>(),
+                found: UNKNOWN_TYPE_NAME,
+            }),
+        }
+    }
+}
+
+/// Helpers for maps keyed by `Arc<str>`
+///
+/// `Arc<str>` keys are cheap to clone and can be shared between maps, which cuts
+/// memory for large string-keyed maps. These helpers take plain `&str` keys: reads
+/// look the key up without allocating, `set_str` reuses the stored key's
+/// allocation when overwriting, and `set_interned` draws keys from a shared
+/// [`KeyInterner`] pool.
+impl TypeMap<Arc<str>> {
+    /// Stores a value under a `&str` key
+    ///
+    /// If the key already exists its `Arc<str>` is reused, so overwriting a value
+    /// doesn't allocate a new key.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use sovran_typemap::{TypeMap, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store: TypeMap<Arc<str>> = TypeMap::new();
+    /// store.set_str("region", "eu-west".to_string())?;
+    /// assert_eq!(store.get_str::<String>("region")?, "eu-west");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_str<V>(&self, key: &str, value: V) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        let mut store = self.lock_items()?;
+        let key = match store.get_key_value(key) {
+            Some((existing, _)) => Arc::clone(existing),
+            None => Arc::from(key),
+        };
+        self.insert_locked(&mut store, key, AnyValue::new(value))?;
+        Ok(())
+    }
+
+    /// Stores a value under a key taken from `interner`'s shared pool
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if either internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    pub fn set_interned<V>(
+        &self,
+        interner: &KeyInterner,
+        key: &str,
+        value: V,
+    ) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        let key = interner.intern(key)?;
+        self.set(key, value)
+    }
+
+    /// Retrieves a clone of a value by `&str` key
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    pub fn get_str<V>(&self, key: &str) -> Result<V, MapError>
+    where
+        V: 'static + Clone,
+    {
+        self.with_str(key, |value: &V| value.clone())
+    }
+
+    /// Accesses a value by `&str` key with a read-only closure
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the value exists but has a different type
+    pub fn with_str<V: 'static, F, R>(&self, key: &str, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        let mut guard = self.lock_items()?;
+        self.read_locked(&mut guard, key, f)
+    }
 }
 
 impl<K> Default for TypeMap<K>
@@ -627,3 +4141,44 @@ where
         Self::new()
     }
 }
+
+/// Formats a one-line summary such as `TypeMap(3 entries)`, for log lines where
+/// the `Debug` output is too verbose.
+///
+/// Formatting never blocks or panics: if the internal lock is held, including by
+/// a closure on this thread that is formatting the map, this prints
+/// `TypeMap(<busy>)`, and if it is poisoned, `TypeMap(<poisoned>)`.
+impl<K> fmt::Display for TypeMap<K>
+where
+    K: Clone + Eq + Hash + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match lock::peek(&self.items) {
+            Ok(store) => match store.len() {
+                1 => f.write_str("TypeMap(1 entry)"),
+                n => write!(f, "TypeMap({} entries)", n),
+            },
+            Err(placeholder) => write!(f, "TypeMap({})", placeholder),
+        }
+    }
+}
+
+/// Bulk-inserts entries under a single lock acquisition.
+///
+/// `Extend` cannot report errors, so this never fails: it waits for the lock even
+/// if a lock timeout is configured, reads through a poisoned lock, and silently
+/// skips keys that have been locked with `lock_key`. Callers that need to know
+/// about any of those should use [`TypeMap::set_all`] instead.
+impl<K, V> Extend<(K, V)> for TypeMap<K>
+where
+    K: Clone + Eq + Hash + Debug,
+    V: 'static + Any + Send + Sync,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let mut store = self.lock_items_lenient();
+        for (key, value) in iter {
+            // Only a locked key can make the insert fail, and those are skipped
+            let _ = self.insert_locked(&mut store, key, AnyValue::new(value));
+        }
+    }
+}
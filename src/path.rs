@@ -0,0 +1,52 @@
+// src/path.rs
+use std::any::Any;
+use std::collections::HashMap;
+
+/// A value that can be descended into by one segment of a dotted path.
+///
+/// [`TypeMap::get_path`](crate::TypeMap::get_path) resolves `"config.db.port"` by
+/// looking up `config` in the map and then asking each value along the way for its
+/// next segment. Implement this trait for nested types that should be traversable,
+/// then register them with [`TypeMap::register_descent`](crate::TypeMap::register_descent).
+///
+/// Every type a path passes through must be registered, with no exceptions.
+/// `HashMap<String, V>` implements this trait, but still has to be registered once
+/// for each `V` it is used with.
+///
+/// # Examples
+///
+/// ```
+/// use std::any::Any;
+/// use sovran_typemap::{Descend, MapError, TypeMap};
+///
+/// struct Database { host: String, port: u16 }
+///
+/// impl Descend for Database {
+///     fn descend(&self, segment: &str) -> Option<&dyn Any> {
+///         match segment {
+///             "host" => Some(&self.host),
+///             "port" => Some(&self.port),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// fn main() -> Result<(), MapError> {
+///     let config: TypeMap<String> = TypeMap::new();
+///     config.register_descent::<Database>()?;
+///     config.set("db".to_string(), Database { host: "localhost".to_string(), port: 5432 })?;
+///
+///     assert_eq!(config.get_path::<u16>("db.port")?, 5432);
+///     Ok(())
+/// }
+/// ```
+pub trait Descend: Any {
+    /// Returns the child value named by `segment`, or `None` if there is none.
+    fn descend(&self, segment: &str) -> Option<&dyn Any>;
+}
+
+impl<V: Any> Descend for HashMap<String, V> {
+    fn descend(&self, segment: &str) -> Option<&dyn Any> {
+        self.get(segment).map(|value| value as &dyn Any)
+    }
+}
@@ -0,0 +1,81 @@
+use std::any::Any;
+
+use crate::error::MapError;
+use crate::store::TypeStore;
+
+/// A type that can be looked up in a [`TypeStore`] through its own name.
+///
+/// Implementing `ServiceKey` moves the turbofish from every call site onto the
+/// type itself: `DatabaseConfig::from_store(&store)` instead of
+/// `store.get::<DatabaseConfig>()`. All methods have default implementations,
+/// so an empty `impl` is enough. With the `derive` feature enabled,
+/// `#[derive(ServiceKey)]` writes that impl for you.
+///
+/// # Examples
+///
+/// ```
+/// use sovran_typemap::{MapError, ServiceKey, TypeStore};
+///
+/// #[derive(Clone, Debug)]
+/// struct DatabaseConfig { host: String, port: u16 }
+///
+/// impl ServiceKey for DatabaseConfig {}
+///
+/// fn main() -> Result<(), MapError> {
+///     let store = TypeStore::new();
+///     DatabaseConfig { host: "localhost".to_string(), port: 5432 }.store_in(&store)?;
+///
+///     let port = DatabaseConfig::with_in(&store, |config| config.port)?;
+///     assert_eq!(port, 5432);
+///     Ok(())
+/// }
+/// ```
+pub trait ServiceKey: 'static + Any + Send + Sync + Sized {
+    /// Retrieves a clone of this type's value from the store.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if no value of this type exists
+    fn from_store(store: &TypeStore) -> Result<Self, MapError>
+    where
+        Self: Clone,
+    {
+        store.get::<Self>()
+    }
+
+    /// Accesses this type's value in the store with a read-only closure.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if no value of this type exists
+    fn with_in<F, R>(store: &TypeStore, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&Self) -> R,
+    {
+        store.with(f)
+    }
+
+    /// Accesses this type's value in the store with a read-write closure.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if no value of this type exists
+    fn with_mut_in<F, R>(store: &TypeStore, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        store.with_mut(f)
+    }
+
+    /// Stores this value, replacing any existing value of the same type.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    fn store_in(self, store: &TypeStore) -> Result<(), MapError> {
+        store.set(self)
+    }
+}
@@ -1,10 +1,135 @@
 // src/store.rs
 use std::any::{type_name, Any, TypeId};
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::{Arc, Mutex};
 
-use crate::any_value::AnyValue;
+use crate::any_value::{short_type_name, AnyValue};
+use crate::batcher::Batcher;
 use crate::error::MapError;
+use crate::lock::{self, TrackedGuard};
+use crate::store_value::{CloneAny, TypeStoreValue};
+
+/// Explains how to avoid the nested call in the debug-build reentrancy panic.
+const REENTRY_HINT: &str = "finish the closure before using the store again";
+
+/// A marker type naming a callable slot in a [`TypeStore`].
+///
+/// Implement this on a zero-sized marker to register a function with
+/// [`TypeStore::set_fn`] and invoke it with [`TypeStore::call_fn`]. The marker
+/// is the key, and the associated types fix the signature, so call sites need
+/// no turbofish beyond the marker itself. Use a tuple for `Args` when the
+/// function takes several arguments.
+///
+/// # Examples
+///
+/// ```
+/// use sovran_typemap::{FnKey, TypeStore, MapError};
+///
+/// struct Double;
+///
+/// impl FnKey for Double {
+///     type Args = i32;
+///     type Output = i32;
+/// }
+///
+/// fn main() -> Result<(), MapError> {
+///     let store = TypeStore::new();
+///     store.set_fn::<Double, _>(|x| x * 2)?;
+///     assert_eq!(store.call_fn::<Double>(21)?, 42);
+///     Ok(())
+/// }
+/// ```
+pub trait FnKey: 'static {
+    /// The argument passed to the function.
+    type Args;
+    /// The value returned by the function.
+    type Output;
+}
+
+/// A type that must be present in a [`TypeStore`], for use with
+/// [`TypeStore::require`] and [`TypeStore::missing_types`].
+///
+/// # Examples
+///
+/// ```
+/// use sovran_typemap::RequiredType;
+///
+/// let required = [RequiredType::of::<u16>(), RequiredType::of::<String>()];
+/// assert_eq!(required[0].type_name(), "u16");
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RequiredType {
+    type_id: TypeId,
+    type_name: &'static str,
+}
+
+impl RequiredType {
+    /// Describes the requirement that a value of type `T` is stored.
+    pub fn of<T: 'static>() -> Self {
+        Self {
+            type_id: TypeId::of::<T>(),
+            type_name: type_name::<T>(),
+        }
+    }
+
+    /// The `TypeId` of the required type.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
+
+    /// The name of the required type, from `std::any::type_name`.
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// A tuple of values that can be stored in a [`TypeStore`] in one call with
+/// [`TypeStore::register_all`].
+///
+/// Implemented for tuples of 1 to 12 elements where every element is
+/// `'static + Send + Sync`. This trait is sealed and can't be implemented
+/// outside this crate.
+pub trait StoreTuple: sealed::Sealed {
+    #[doc(hidden)]
+    fn insert_into(self, items: &mut HashMap<TypeId, AnyValue>);
+}
+
+macro_rules! impl_store_tuple {
+    ($($name:ident),+) => {
+        impl<$($name: 'static + Any + Send + Sync),+> sealed::Sealed for ($($name,)+) {}
+
+        impl<$($name: 'static + Any + Send + Sync),+> StoreTuple for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn insert_into(self, items: &mut HashMap<TypeId, AnyValue>) {
+                let ($($name,)+) = self;
+                $(items.insert(TypeId::of::<$name>(), AnyValue::new($name));)+
+            }
+        }
+    };
+}
+
+impl_store_tuple!(A);
+impl_store_tuple!(A, B);
+impl_store_tuple!(A, B, C);
+impl_store_tuple!(A, B, C, D);
+impl_store_tuple!(A, B, C, D, E);
+impl_store_tuple!(A, B, C, D, E, F);
+impl_store_tuple!(A, B, C, D, E, F, G);
+impl_store_tuple!(A, B, C, D, E, F, G, H);
+impl_store_tuple!(A, B, C, D, E, F, G, H, I);
+impl_store_tuple!(A, B, C, D, E, F, G, H, I, J);
+impl_store_tuple!(A, B, C, D, E, F, G, H, I, J, K);
+impl_store_tuple!(A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Storage slot for a function registered under the marker `M`.
+struct FnSlot<M: FnKey>(Arc<dyn Fn(M::Args) -> M::Output + Send + Sync>);
 
 /// A thread-safe container that stores exactly one value per type.
 ///
@@ -60,7 +185,92 @@ use crate::error::MapError;
 /// ```
 #[derive(Clone, Debug)]
 pub struct TypeStore {
-    items: Arc<Mutex<HashMap<TypeId, AnyValue>>>,
+    pub(crate) items: Arc<Mutex<HashMap<TypeId, AnyValue>>>,
+    // Lock order: `items` is always acquired before `defaults`, `cloners` and `sizers`
+    defaults: Arc<Mutex<HashMap<TypeId, DefaultFn>>>,
+    cloners: Arc<Mutex<HashMap<TypeId, CloneFn>>>,
+    sizers: Arc<Mutex<HashMap<TypeId, Sizer>>>,
+    // Never held while calling a provider or while `items` is locked
+    fallbacks: Arc<Mutex<HashMap<TypeId, Fallback>>>,
+}
+
+/// Constructor for a registered default value.
+type DefaultFn = fn() -> AnyValue;
+
+/// Builds the registered default for `T`, type-erased for the defaults table.
+fn default_value<T: 'static + Default + Any + Send + Sync>() -> AnyValue {
+    AnyValue::new(T::default())
+}
+
+/// Clones a stored value into a box suitable for a `TypeStoreValue`.
+type CloneFn = fn(&AnyValue) -> Box<dyn CloneAny>;
+
+/// A registered function estimating the heap size of a stored value, type-erased
+/// for the sizers table.
+struct Sizer(Box<dyn Fn(&AnyValue) -> usize + Send + Sync>);
+
+impl fmt::Debug for Sizer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Sizer")
+    }
+}
+
+/// A registered provider consulted when a type is missing, type-erased for the
+/// fallbacks table.
+#[derive(Clone)]
+struct Fallback {
+    provide: Arc<dyn Fn() -> Option<AnyValue> + Send + Sync>,
+    /// Whether a provided value is stored so later lookups don't consult the provider
+    cache: bool,
+}
+
+impl fmt::Debug for Fallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Fallback")
+            .field("cache", &self.cache)
+            .finish()
+    }
+}
+
+thread_local! {
+    /// Types whose `get_or_insert_with` initializer is running on this thread,
+    /// tagged with the address of the store they belong to.
+    static INITIALIZING: RefCell<Vec<(usize, TypeId, &'static str)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Marks a type as being initialized on this thread until dropped, so the mark
+/// is cleared even if the initializer panics.
+struct InitMark;
+
+impl InitMark {
+    /// Records that `V` is being initialized in the store at `store`, or returns
+    /// the dependency chain if it already is.
+    fn enter<V: 'static>(store: usize) -> Result<Self, MapError> {
+        INITIALIZING.with(|stack| {
+            let mut stack = stack.borrow_mut();
+            let this = (store, TypeId::of::<V>());
+            if let Some(start) = stack.iter().position(|&(s, t, _)| (s, t) == this) {
+                let mut chain: Vec<&'static str> =
+                    stack[start..].iter().map(|&(_, _, name)| name).collect();
+                chain.push(type_name::<V>());
+                return Err(MapError::CircularDependency { chain });
+            }
+            stack.push((store, TypeId::of::<V>(), type_name::<V>()));
+            Ok(InitMark)
+        })
+    }
+}
+
+impl Drop for InitMark {
+    fn drop(&mut self) {
+        let _ = INITIALIZING.try_with(|stack| stack.borrow_mut().pop());
+    }
+}
+
+/// Clones the stored `T`, type-erased for the cloners table.
+fn clone_value<T: 'static + Clone + Any + Send + Sync>(value: &AnyValue) -> Box<dyn CloneAny> {
+    // Type is guaranteed to match since the cloner is registered under T's TypeId
+    Box::new(value.downcast_ref::<T>().unwrap().clone())
 }
 
 impl TypeStore {
@@ -74,8 +284,339 @@ impl TypeStore {
     /// let store = TypeStore::new();
     /// ```
     pub fn new() -> Self {
+        Self::with_capacity(0)
+    }
+
+    /// Creates a new, empty TypeStore with room for `capacity` values.
+    ///
+    /// Since each type holds at most one value, capacity counts distinct types.
+    /// Sizing the store up front avoids rehashing while a known set of services
+    /// is registered at startup.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::TypeStore;
+    ///
+    /// let store = TypeStore::with_capacity(32);
+    /// ```
+    pub fn with_capacity(capacity: usize) -> Self {
         Self {
-            items: Arc::new(Mutex::new(HashMap::new())),
+            items: Arc::new(Mutex::new(HashMap::with_capacity(capacity))),
+            defaults: Arc::new(Mutex::new(HashMap::new())),
+            cloners: Arc::new(Mutex::new(HashMap::new())),
+            sizers: Arc::new(Mutex::new(HashMap::new())),
+            fallbacks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Declares `T::default()` as the fallback for a type that hasn't been set.
+    ///
+    /// Once registered, `get`, `with` and `with_mut` never fail with
+    /// `KeyNotFound` for `T`: if no value is stored they construct the default,
+    /// insert it, and continue as if it had been set. Values that are set
+    /// explicitly always take precedence. `contains` only reports values that
+    /// have actually been stored or materialized.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// #[derive(Clone, Debug, Default, PartialEq)]
+    /// struct RetryPolicy { attempts: u32 }
+    ///
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.register_default::<RetryPolicy>()?;
+    ///
+    /// assert!(!store.contains::<RetryPolicy>()?);
+    /// assert_eq!(store.get::<RetryPolicy>()?, RetryPolicy { attempts: 0 });
+    /// assert!(store.contains::<RetryPolicy>()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_default<V>(&self) -> Result<(), MapError>
+    where
+        V: 'static + Default + Any + Send + Sync,
+    {
+        let mut defaults = self.defaults.lock().map_err(|_| MapError::LockError)?;
+        defaults.insert(TypeId::of::<V>(), default_value::<V>);
+        Ok(())
+    }
+
+    /// Registers a provider that `get` and `with` consult when no value of type `V`
+    /// is stored.
+    ///
+    /// This builds layered lookups on top of the store: the provider might read the
+    /// environment, or ask a parent store. It may itself miss by returning `None`,
+    /// in which case the lookup fails with `KeyNotFound` as usual. The provided
+    /// value is not stored, so the provider runs again on every miss; use
+    /// [`set_fallback_cached`](Self::set_fallback_cached) to keep the first value
+    /// it provides.
+    ///
+    /// The provider runs without the store's lock held, so it may use this store.
+    /// A registered default takes precedence, since it means `V` is never missing.
+    /// `with_mut` does not consult fallbacks. Registering again replaces the
+    /// previous provider.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct LogLevel(String);
+    ///
+    /// # fn main() -> Result<(), MapError> {
+    /// let defaults = TypeStore::new();
+    /// defaults.set(LogLevel("info".to_string()))?;
+    ///
+    /// // Environment first, then the parent store
+    /// let config = TypeStore::new();
+    /// let parent = defaults.clone();
+    /// config.set_fallback(move || {
+    ///     std::env::var("MYAPP_LOG_LEVEL")
+    ///         .ok()
+    ///         .map(LogLevel)
+    ///         .or_else(|| parent.get::<LogLevel>().ok())
+    /// })?;
+    ///
+    /// # std::env::remove_var("MYAPP_LOG_LEVEL");
+    /// assert_eq!(config.get::<LogLevel>()?, LogLevel("info".to_string()));
+    /// assert!(!config.contains::<LogLevel>()?);
+    ///
+    /// // A stored value always wins
+    /// config.set(LogLevel("debug".to_string()))?;
+    /// assert_eq!(config.get::<LogLevel>()?, LogLevel("debug".to_string()));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_fallback<V, F>(&self, f: F) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        F: Fn() -> Option<V> + Send + Sync + 'static,
+    {
+        self.insert_fallback(f, false)
+    }
+
+    /// Registers a provider like [`set_fallback`](Self::set_fallback), but stores
+    /// the first value it provides.
+    ///
+    /// After the provider succeeds once, `V` is present in the store and the
+    /// provider is no longer consulted. If two threads miss at the same time, both
+    /// may run the provider; the first value stored wins and both callers see it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn set_fallback_cached<V, F>(&self, f: F) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        F: Fn() -> Option<V> + Send + Sync + 'static,
+    {
+        self.insert_fallback(f, true)
+    }
+
+    fn insert_fallback<V, F>(&self, f: F, cache: bool) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        F: Fn() -> Option<V> + Send + Sync + 'static,
+    {
+        let mut fallbacks = self.fallbacks.lock().map_err(|_| MapError::LockError)?;
+        let fallback = Fallback {
+            provide: Arc::new(move || f().map(AnyValue::new)),
+            cache,
+        };
+        fallbacks.insert(TypeId::of::<V>(), fallback);
+        Ok(())
+    }
+
+    /// Resolves a missing `V` through its registered fallback, if any, and runs
+    /// `f` on the provided value.
+    ///
+    /// Must be called without the items lock held.
+    fn with_fallback<V: 'static, F, R>(&self, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        let not_found = || MapError::KeyNotFound(type_name::<V>().to_string());
+        let fallback = {
+            let fallbacks = self.fallbacks.lock().map_err(|_| MapError::LockError)?;
+            fallbacks.get(&TypeId::of::<V>()).cloned()
+        };
+        let fallback = fallback.ok_or_else(not_found)?;
+
+        let value = {
+            let _mark = InitMark::enter::<V>(Arc::as_ptr(&self.items) as usize)?;
+            (fallback.provide)().ok_or_else(not_found)?
+        };
+        if !fallback.cache {
+            // Type is guaranteed to match since the fallback is registered under V's TypeId
+            return Ok(f(value.downcast_ref::<V>().unwrap()));
+        }
+
+        let mut store = self.lock_items()?;
+        let stored = store.entry(TypeId::of::<V>()).or_insert(value);
+        // Type is guaranteed to match since TypeId is the key
+        Ok(f(stored.downcast_ref::<V>().unwrap()))
+    }
+
+    /// Marks a type as cloneable for [`to_store_value`](Self::to_store_value).
+    ///
+    /// Stored values are type-erased, so the store can't tell on its own which of
+    /// them implement `Clone`. Registering a type records how to clone it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn register_clone<V>(&self) -> Result<(), MapError>
+    where
+        V: 'static + Clone + Any + Send + Sync,
+    {
+        let mut cloners = self.cloners.lock().map_err(|_| MapError::LockError)?;
+        cloners.insert(TypeId::of::<V>(), clone_value::<V>);
+        Ok(())
+    }
+
+    /// Registers a function that estimates how many bytes a value of type `V` uses.
+    ///
+    /// Stored values are type-erased, so the store can't measure them on its own.
+    /// The sizer should return the value's total footprint, e.g.
+    /// `size_of::<V>()` plus any heap allocations it owns. Registering a type
+    /// again replaces its sizer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn register_sizer<V, F>(&self, sizer: F) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        F: Fn(&V) -> usize + Send + Sync + 'static,
+    {
+        let mut sizers = self.sizers.lock().map_err(|_| MapError::LockError)?;
+        let sizer = Sizer(Box::new(move |value: &AnyValue| {
+            // Type is guaranteed to match since the sizer is registered under V's TypeId
+            sizer(value.downcast_ref::<V>().unwrap())
+        }));
+        sizers.insert(TypeId::of::<V>(), sizer);
+        Ok(())
+    }
+
+    /// Estimates the memory used by stored values whose type has a registered sizer.
+    ///
+    /// Values of types without a sizer are not counted. Sizers run under the
+    /// store's lock, so they must not call back into this store.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # use std::mem::size_of;
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.register_sizer::<Vec<u64>, _>(|v| size_of::<Vec<u64>>() + v.capacity() * 8)?;
+    ///
+    /// store.set(Vec::<u64>::with_capacity(1000))?;
+    /// store.set(42u32)?; // no sizer, not counted
+    ///
+    /// assert!(store.approx_memory()? >= 8000);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn approx_memory(&self) -> Result<usize, MapError> {
+        let store = self.lock_items()?;
+        let sizers = self.sizers.lock().map_err(|_| MapError::LockError)?;
+        Ok(store
+            .iter()
+            .filter_map(|(type_id, value)| sizers.get(type_id).map(|sizer| (sizer.0)(value)))
+            .sum())
+    }
+
+    /// Copies every stored value whose type was registered with `register_clone`
+    /// into a new `TypeStoreValue`.
+    ///
+    /// Values of unregistered types are skipped. The snapshot is taken under a
+    /// single lock, so it is consistent, and is independent of the store afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # use std::sync::Mutex;
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Config { workers: usize }
+    ///
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.register_clone::<Config>()?;
+    ///
+    /// store.set(Config { workers: 4 })?;
+    /// store.set(Mutex::new(0u64))?; // not Clone, never registered
+    ///
+    /// let snapshot = store.to_store_value()?;
+    /// assert_eq!(snapshot.get::<Config>(), Some(Config { workers: 4 }));
+    /// assert_eq!(snapshot.len(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_store_value(&self) -> Result<TypeStoreValue, MapError> {
+        let store = self.lock_items()?;
+        let cloners = self.cloners.lock().map_err(|_| MapError::LockError)?;
+
+        let mut snapshot = TypeStoreValue::new();
+        for (type_id, value) in store.iter() {
+            if let Some(clone) = cloners.get(type_id) {
+                snapshot.insert_boxed(*type_id, clone(value));
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// Acquires the items lock.
+    ///
+    /// In debug builds this panics if the current thread already holds the lock,
+    /// which would otherwise deadlock silently.
+    pub(crate) fn lock_items(
+        &self,
+    ) -> Result<TrackedGuard<'_, HashMap<TypeId, AnyValue>>, MapError> {
+        lock::lock(&self.items, "TypeStore", REENTRY_HINT)
+    }
+
+    /// Acquires the items lock, recovering the data if the lock is poisoned.
+    fn lock_items_lenient(&self) -> TrackedGuard<'_, HashMap<TypeId, AnyValue>> {
+        lock::lock_lenient(&self.items, "TypeStore", REENTRY_HINT)
+    }
+
+    /// Looks up the entry for `V` in the already-locked map, materializing its
+    /// registered default if it is absent.
+    pub(crate) fn entry_or_default<'a, V: 'static>(
+        &self,
+        store: &'a mut HashMap<TypeId, AnyValue>,
+    ) -> Result<&'a mut AnyValue, MapError> {
+        match store.entry(TypeId::of::<V>()) {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => {
+                let defaults = self.defaults.lock().map_err(|_| MapError::LockError)?;
+                let make = defaults
+                    .get(&TypeId::of::<V>())
+                    .ok_or_else(|| MapError::KeyNotFound(type_name::<V>().to_string()))?;
+                Ok(entry.insert(make()))
+            }
         }
     }
 
@@ -108,7 +649,7 @@ impl TypeStore {
     where
         V: 'static + Any + Send + Sync,
     {
-        let mut store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let mut store = self.lock_items()?;
         store.insert(TypeId::of::<V>(), AnyValue::new(value));
         Ok(())
     }
@@ -149,12 +690,54 @@ impl TypeStore {
         self.set(value)
     }
 
+    /// Stores each element of a tuple under its own type, under a single lock.
+    ///
+    /// This is a batched form of calling `set` once per element, handy for
+    /// startup registration. Tuples of up to 12 elements are supported. If two
+    /// elements share a type, the later one wins.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// struct Config { debug: bool }
+    /// struct Logger { prefix: String }
+    ///
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.register_all((
+    ///     Config { debug: true },
+    ///     Logger { prefix: "app".to_string() },
+    ///     42u16,
+    /// ))?;
+    ///
+    /// assert_eq!(store.len()?, 3);
+    /// assert!(store.with(|c: &Config| c.debug)?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn register_all<T: StoreTuple>(&self, values: T) -> Result<(), MapError> {
+        let mut store = self.lock_items()?;
+        values.insert_into(&mut store);
+        Ok(())
+    }
+
     /// Retrieves a clone of a value by its type.
     ///
+    /// This always clones the stored value. When a borrow is enough, prefer
+    /// `with`, which runs a closure against the stored value without cloning;
+    /// the difference is significant for large values.
+    ///
     /// # Errors
     ///
     /// - Returns `MapError::LockError` if the internal lock cannot be acquired
-    /// - Returns `MapError::KeyNotFound` if no value of this type exists
+    /// - Returns `MapError::KeyNotFound` if no value of this type exists (and no default or
+    ///   fallback provides one)
+    /// - Returns `MapError::CircularDependency` if `V`'s fallback depends on itself
     ///
     /// # Examples
     ///
@@ -184,22 +767,144 @@ impl TypeStore {
         self.with(|val: &V| val.clone())
     }
 
-    /// Accesses a value by type with a read-only closure.
+    /// Retrieves a clone of the value of type `V`, initializing it with `f` first
+    /// if it is absent.
+    ///
+    /// Unlike `set_with`, `f` only runs when no value of type `V` is stored (and no
+    /// default is registered). It runs *without* the store's lock held, so it may
+    /// resolve other services from this store. If two threads race to initialize
+    /// the same type, the first value stored wins and both callers receive it.
+    ///
+    /// If an initializer ends up needing the very type it is initializing,
+    /// directly or through other lazily initialized types, the nested call fails
+    /// with `CircularDependency` rather than recursing forever.
     ///
     /// # Errors
     ///
     /// - Returns `MapError::LockError` if the internal lock cannot be acquired
-    /// - Returns `MapError::KeyNotFound` if no value of this type exists
+    /// - Returns `MapError::CircularDependency` if `V`'s initialization depends on itself
     ///
     /// # Examples
     ///
     /// ```
     /// # use sovran_typemap::{TypeStore, MapError};
     /// # fn main() -> Result<(), MapError> {
+    /// #[derive(Clone, Debug)]
+    /// struct Config { url: String }
+    ///
+    /// #[derive(Clone, Debug)]
+    /// struct Database { url: String }
+    ///
     /// let store = TypeStore::new();
-    /// store.set(vec![1, 2, 3, 4, 5])?;
+    /// store.set(Config { url: "postgres://db".to_string() })?;
     ///
-    /// let sum = store.with::<Vec<i32>, _, _>(|numbers| {
+    /// // The initializer can resolve its own dependencies
+    /// let db = store.get_or_insert_with(|| Database {
+    ///     url: store.get::<Config>().unwrap().url,
+    /// })?;
+    /// assert_eq!(db.url, "postgres://db");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_or_insert_with<V, F>(&self, f: F) -> Result<V, MapError>
+    where
+        V: 'static + Any + Send + Sync + Clone,
+        F: FnOnce() -> V,
+    {
+        match self.get::<V>() {
+            Err(MapError::KeyNotFound(_)) => {}
+            result => return result,
+        }
+
+        let value = {
+            let _mark = InitMark::enter::<V>(Arc::as_ptr(&self.items) as usize)?;
+            f()
+        };
+
+        let mut store = self.lock_items()?;
+        let stored = store
+            .entry(TypeId::of::<V>())
+            .or_insert_with(|| AnyValue::new(value));
+        // Type is guaranteed to match since TypeId is the key
+        Ok(stored.downcast_ref::<V>().unwrap().clone())
+    }
+
+    /// Stores a function under the marker type `M`.
+    ///
+    /// This is a convenience for command registries and hook tables: the
+    /// function is boxed behind the scenes and the marker's [`FnKey`]
+    /// associated types fix its signature. Registering again under the same
+    /// marker replaces the previous function.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{FnKey, TypeStore, MapError};
+    /// struct Greet;
+    ///
+    /// impl FnKey for Greet {
+    ///     type Args = (String, u32);
+    ///     type Output = String;
+    /// }
+    ///
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.set_fn::<Greet, _>(|(name, times)| format!("Hi {}! ", name).repeat(times as usize))?;
+    ///
+    /// let greeting = store.call_fn::<Greet>(("Ann".to_string(), 2))?;
+    /// assert_eq!(greeting, "Hi Ann! Hi Ann! ");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_fn<M, F>(&self, f: F) -> Result<(), MapError>
+    where
+        M: FnKey,
+        F: Fn(M::Args) -> M::Output + Send + Sync + 'static,
+    {
+        self.set(FnSlot::<M>(Arc::new(f)))
+    }
+
+    /// Invokes the function stored under the marker type `M`.
+    ///
+    /// The function is called after the internal lock is released, so it may
+    /// itself use the store.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if no function is registered under `M`
+    pub fn call_fn<M: FnKey>(&self, args: M::Args) -> Result<M::Output, MapError> {
+        let f = self
+            .with(|slot: &FnSlot<M>| Arc::clone(&slot.0))
+            .map_err(|e| match e {
+                MapError::KeyNotFound(_) => MapError::KeyNotFound(type_name::<M>().to_string()),
+                e => e,
+            })?;
+        Ok(f(args))
+    }
+
+    /// Accesses a value by type with a read-only closure.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if no value of this type exists (and no default or
+    ///   fallback provides one)
+    /// - Returns `MapError::CircularDependency` if `V`'s fallback depends on itself
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.set(vec![1, 2, 3, 4, 5])?;
+    ///
+    /// let sum = store.with::<Vec<i32>, _, _>(|numbers| {
     ///     numbers.iter().sum::<i32>()
     /// })?;
     /// assert_eq!(sum, 15);
@@ -210,14 +915,175 @@ impl TypeStore {
     where
         F: FnOnce(&V) -> R,
     {
-        let guard = self.items.lock().map_err(|_| MapError::LockError)?;
-        let value = guard
-            .get(&TypeId::of::<V>())
-            .ok_or_else(|| MapError::KeyNotFound(type_name::<V>().to_string()))?;
+        {
+            let mut guard = self.lock_items()?;
+            match self.entry_or_default::<V>(&mut guard) {
+                Ok(value) => {
+                    // Type is guaranteed to match since TypeId is the key
+                    let reference = value.downcast_ref::<V>().unwrap();
+                    return Ok(f(reference));
+                }
+                Err(MapError::KeyNotFound(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        self.with_fallback(f)
+    }
 
-        // Type is guaranteed to match since TypeId is the key
-        let reference = value.downcast_ref::<V>().unwrap();
-        Ok(f(reference))
+    /// Accesses a value by type with a read-only closure; an alias of [`with`](Self::with).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`with`](Self::with).
+    pub fn with_read<V: 'static, F, R>(&self, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        self.with(f)
+    }
+
+    /// Accesses a value by its type name with a type-erased, read-only closure.
+    ///
+    /// This bridges the static type keys to dynamic, name-based access for
+    /// scripting hosts. The name of each value is recorded from
+    /// `std::any::type_name` when it is stored, and `name` may be either that
+    /// full path or the bare type name (e.g. `"DatabaseConfig"`). The closure
+    /// receives the value as `&dyn Any` to downcast as it sees fit.
+    ///
+    /// Lookup is a linear scan over the stored types. If a bare name matches
+    /// more than one stored type, use the full path instead.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if no stored type matches `name`, or
+    ///   if a bare name is ambiguous
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// #[derive(Debug)]
+    /// struct DatabaseConfig { port: u16 }
+    ///
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.set(DatabaseConfig { port: 5432 })?;
+    ///
+    /// let port = store.with_by_name("DatabaseConfig", |value| {
+    ///     value.downcast_ref::<DatabaseConfig>().map(|cfg| cfg.port)
+    /// })?;
+    /// assert_eq!(port, Some(5432));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_by_name<F, R>(&self, name: &str, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&dyn Any) -> R,
+    {
+        let guard = self.lock_items()?;
+
+        let value = match guard.values().find(|value| value.type_name == name) {
+            Some(value) => value,
+            None => {
+                let mut matches = guard.values().filter(|value| value.matches_name(name));
+                match (matches.next(), matches.next()) {
+                    (Some(value), None) => value,
+                    _ => return Err(MapError::KeyNotFound(name.to_string())),
+                }
+            }
+        };
+
+        Ok(f(&*value.value))
+    }
+
+    /// Gets the type names of all stored values.
+    ///
+    /// The names come from `std::any::type_name` and are meant for diagnostics
+    /// and name-based lookup with `with_by_name`; their exact format is not
+    /// guaranteed to be stable across compiler versions.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn type_names(&self) -> Result<Vec<&'static str>, MapError> {
+        let store = self.lock_items()?;
+        Ok(store.values().map(|value| value.type_name).collect())
+    }
+
+    /// Gets the type names of all stored values, sorted.
+    ///
+    /// Like `type_names`, but in a deterministic order for logs and golden tests.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.set(1u32)?;
+    /// store.set(true)?;
+    /// store.set(2i8)?;
+    ///
+    /// assert_eq!(store.type_names_ordered()?, vec!["bool", "i8", "u32"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn type_names_ordered(&self) -> Result<Vec<&'static str>, MapError> {
+        let mut names = self.type_names()?;
+        names.sort_unstable();
+        Ok(names)
+    }
+
+    /// Visits every stored value as a `TypeId` and `&dyn Any`.
+    ///
+    /// This is the generic traversal primitive for export and debug tooling: the
+    /// callback can `downcast_ref` each value against the types it knows about and
+    /// skip the rest. Values are visited in no particular order, under the lock, so
+    /// the callback must not call back into this store. Types registered with
+    /// `register_default` that haven't been accessed yet are not visited.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.set(42i32)?;
+    /// store.set("hello".to_string())?;
+    /// store.set(1.5f64)?;
+    ///
+    /// let mut exported = Vec::new();
+    /// store.inspect(|_, value| {
+    ///     if let Some(n) = value.downcast_ref::<i32>() {
+    ///         exported.push(n.to_string());
+    ///     } else if let Some(s) = value.downcast_ref::<String>() {
+    ///         exported.push(s.clone());
+    ///     }
+    /// })?;
+    ///
+    /// exported.sort();
+    /// assert_eq!(exported, vec!["42", "hello"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn inspect<F>(&self, mut f: F) -> Result<(), MapError>
+    where
+        F: FnMut(TypeId, &dyn Any),
+    {
+        let store = self.lock_items()?;
+        for (type_id, value) in store.iter() {
+            f(*type_id, &*value.value);
+        }
+        Ok(())
     }
 
     /// Accesses a value by type with a read-write closure.
@@ -249,46 +1115,332 @@ impl TypeStore {
     where
         F: FnOnce(&mut V) -> R,
     {
-        let mut guard = self.items.lock().map_err(|_| MapError::LockError)?;
+        let mut guard = self.lock_items()?;
+        let value = self.entry_or_default::<V>(&mut guard)?;
+
+        // Type is guaranteed to match since TypeId is the key
+        let reference = value.downcast_mut::<V>().unwrap();
+        Ok(f(reference))
+    }
+
+    /// Accesses a value by type with a read-write closure; an alias of
+    /// [`with_mut`](Self::with_mut).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`with_mut`](Self::with_mut).
+    pub fn with_write<V: 'static, F, R>(&self, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        self.with_mut(f)
+    }
+
+    /// Accesses a value by type with a read-write closure, inserting one from
+    /// `init` first if the type is absent. Runs under a single lock acquisition.
+    pub(crate) fn with_mut_or_insert_with<V, I, F, R>(&self, init: I, f: F) -> Result<R, MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        I: FnOnce() -> V,
+        F: FnOnce(&mut V) -> R,
+    {
+        let mut guard = self.lock_items()?;
         let value = guard
-            .get_mut(&TypeId::of::<V>())
-            .ok_or_else(|| MapError::KeyNotFound(type_name::<V>().to_string()))?;
+            .entry(TypeId::of::<V>())
+            .or_insert_with(|| AnyValue::new(init()));
 
         // Type is guaranteed to match since TypeId is the key
         let reference = value.downcast_mut::<V>().unwrap();
         Ok(f(reference))
     }
 
+    /// Temporarily replaces the stored `V`, restoring the original when the
+    /// returned guard is dropped.
+    ///
+    /// The previous value (if any) is moved aside and `value` installed in its
+    /// place. When the [`OverrideGuard`] goes out of scope, including during a
+    /// panic unwind, the original is put back, or the override is removed if there
+    /// was no original. This is the usual test seam for injecting a mock service.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Clock { now: u64 }
+    ///
+    /// let store = TypeStore::new();
+    /// store.set(Clock { now: 1_700_000_000 })?;
+    ///
+    /// {
+    ///     let _frozen = store.override_scoped(Clock { now: 0 })?;
+    ///     assert_eq!(store.get::<Clock>()?.now, 0);
+    /// }
+    ///
+    /// // The real clock is back
+    /// assert_eq!(store.get::<Clock>()?.now, 1_700_000_000);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn override_scoped<V>(&self, value: V) -> Result<OverrideGuard<'_>, MapError>
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        let mut store = self.lock_items()?;
+        let previous = store.insert(TypeId::of::<V>(), AnyValue::new(value));
+        Ok(OverrideGuard {
+            store: self,
+            type_id: TypeId::of::<V>(),
+            previous,
+        })
+    }
+
+    /// Creates a [`Batcher`] that queues mutations to the stored `V` and applies
+    /// them `flush_every` at a time under a single lock acquisition.
+    ///
+    /// Use it for high-frequency updates to one value, where taking the lock for
+    /// every change dominates. A `flush_every` of zero is treated as one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.set(0u64)?;
+    ///
+    /// {
+    ///     let mut bytes_sent = store.batcher::<u64>(64);
+    ///     for size in [512, 1024, 256] {
+    ///         bytes_sent.push(move |total| *total += size)?;
+    ///     }
+    /// } // dropping the batcher flushes what's left
+    ///
+    /// assert_eq!(store.get::<u64>()?, 1792);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn batcher<V>(&self, flush_every: usize) -> Batcher<V>
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        Batcher::new(self.clone(), flush_every)
+    }
+
+    /// Replaces a value by passing ownership of the current one to a closure.
+    ///
+    /// Under a single lock, the existing value (if any) is moved out and handed to
+    /// `f` as `Some(value)`, or `None` if no value of this type is stored; whatever
+    /// `f` returns is stored in its place. This covers both transform and initialize
+    /// in one atomic step without requiring `Clone` or `Default`. A type registered
+    /// with `register_default` but not yet accessed counts as absent.
+    ///
+    /// If `f` panics the old value has already been taken and is dropped, leaving
+    /// no value of this type in the store.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// #[derive(Debug)]
+    /// struct Config { retries: u32 }
+    ///
+    /// let store = TypeStore::new();
+    ///
+    /// // Initializes when absent...
+    /// store.replace_with(|old: Option<Config>| old.unwrap_or(Config { retries: 1 }))?;
+    ///
+    /// // ...and transforms when present
+    /// store.replace_with(|old: Option<Config>| {
+    ///     let old = old.expect("set above");
+    ///     Config { retries: old.retries * 3 }
+    /// })?;
+    ///
+    /// assert_eq!(store.with(|c: &Config| c.retries)?, 3);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn replace_with<V, F>(&self, f: F) -> Result<(), MapError>
+    where
+        V: 'static + Any + Send + Sync,
+        F: FnOnce(Option<V>) -> V,
+    {
+        let mut store = self.lock_items()?;
+        let current = store
+            .remove(&TypeId::of::<V>())
+            // Type is guaranteed to match since TypeId is the key
+            .map(|value| *value.value.downcast::<V>().unwrap());
+        store.insert(TypeId::of::<V>(), AnyValue::new(f(current)));
+        Ok(())
+    }
+
+    /// Stores a value and returns the one it replaced, in a single locked step.
+    ///
+    /// No other thread can observe the slot empty between the old value leaving
+    /// and the new one arriving, which makes this suitable for double-buffering.
+    /// Because values are keyed by their type, the previous occupant is always a
+    /// `V`. A type registered with `register_default` but not yet accessed counts
+    /// as absent.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(previous))` if a value was stored, `Ok(None)` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// #[derive(Debug, PartialEq)]
+    /// struct Frame(Vec<u8>);
+    ///
+    /// let store = TypeStore::new();
+    /// assert_eq!(store.exchange(Frame(vec![1]))?, None);
+    ///
+    /// let front = store.exchange(Frame(vec![2]))?;
+    /// assert_eq!(front, Some(Frame(vec![1])));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn exchange<V>(&self, value: V) -> Result<Option<V>, MapError>
+    where
+        V: 'static + Any + Send + Sync,
+    {
+        let mut store = self.lock_items()?;
+        Ok(store
+            .insert(TypeId::of::<V>(), AnyValue::new(value))
+            // Type is guaranteed to match since TypeId is the key
+            .map(|previous| *previous.value.downcast::<V>().unwrap()))
+    }
+
     /// Removes a value by its type.
     ///
     /// # Errors
     ///
-    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(true)` if a value was removed, `Ok(false)` if no value
+    /// of that type existed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.set(42i32)?;
+    ///
+    /// assert!(store.remove::<i32>()?);
+    /// assert!(!store.remove::<i32>()?); // Already removed
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remove<V: 'static>(&self) -> Result<bool, MapError> {
+        let mut store = self.lock_items()?;
+        Ok(store.remove(&TypeId::of::<V>()).is_some())
+    }
+
+    /// Checks if a value of the given type exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    ///
+    /// assert!(!store.contains::<i32>()?);
+    /// store.set(42i32)?;
+    /// assert!(store.contains::<i32>()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn contains<V: 'static>(&self) -> Result<bool, MapError> {
+        let store = self.lock_items()?;
+        Ok(store.contains_key(&TypeId::of::<V>()))
+    }
+
+    /// Returns the names of the required types that are not stored.
+    ///
+    /// All checks run under a single lock acquisition. The result is empty when
+    /// every required type is present.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn missing_types(&self, required: &[RequiredType]) -> Result<Vec<&'static str>, MapError> {
+        let store = self.lock_items()?;
+        Ok(required
+            .iter()
+            .filter(|required| !store.contains_key(&required.type_id))
+            .map(|required| required.type_name)
+            .collect())
+    }
+
+    /// Verifies that every required type is stored.
+    ///
+    /// Use this at startup to fail fast with the full list of missing
+    /// dependencies instead of discovering them lazily at first use.
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// Returns `Ok(true)` if a value was removed, `Ok(false)` if no value
-    /// of that type existed.
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::MissingTypes` listing every required type that is absent
     ///
     /// # Examples
     ///
     /// ```
-    /// # use sovran_typemap::{TypeStore, MapError};
-    /// # fn main() -> Result<(), MapError> {
+    /// # use sovran_typemap::{RequiredType, TypeStore, MapError};
+    /// struct Database;
+    /// struct Logger;
+    ///
     /// let store = TypeStore::new();
-    /// store.set(42i32)?;
+    /// store.set(Logger).unwrap();
     ///
-    /// assert!(store.remove::<i32>()?);
-    /// assert!(!store.remove::<i32>()?); // Already removed
-    /// # Ok(())
-    /// # }
+    /// let required = [RequiredType::of::<Database>(), RequiredType::of::<Logger>()];
+    /// match store.require(&required) {
+    ///     Ok(()) => println!("All services registered"),
+    ///     Err(MapError::MissingTypes(missing)) => {
+    ///         assert_eq!(missing.len(), 1);
+    ///         assert!(missing[0].ends_with("Database"));
+    ///     }
+    ///     Err(e) => panic!("Unexpected error: {}", e),
+    /// }
     /// ```
-    pub fn remove<V: 'static>(&self) -> Result<bool, MapError> {
-        let mut store = self.items.lock().map_err(|_| MapError::LockError)?;
-        Ok(store.remove(&TypeId::of::<V>()).is_some())
+    pub fn require(&self, required: &[RequiredType]) -> Result<(), MapError> {
+        let missing = self.missing_types(required)?;
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(MapError::MissingTypes(missing))
+        }
     }
 
-    /// Checks if a value of the given type exists.
+    /// Reserves room for at least `additional` more values.
+    ///
+    /// Since each type holds at most one value, this counts distinct types. Like
+    /// [`with_capacity`](Self::with_capacity), it avoids rehashing during a burst
+    /// of registrations, but works on a store that already exists.
     ///
     /// # Errors
     ///
@@ -300,16 +1452,15 @@ impl TypeStore {
     /// # use sovran_typemap::{TypeStore, MapError};
     /// # fn main() -> Result<(), MapError> {
     /// let store = TypeStore::new();
-    ///
-    /// assert!(!store.contains::<i32>()?);
+    /// store.reserve(16)?;
     /// store.set(42i32)?;
-    /// assert!(store.contains::<i32>()?);
     /// # Ok(())
     /// # }
     /// ```
-    pub fn contains<V: 'static>(&self) -> Result<bool, MapError> {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
-        Ok(store.contains_key(&TypeId::of::<V>()))
+    pub fn reserve(&self, additional: usize) -> Result<(), MapError> {
+        let mut store = self.lock_items()?;
+        store.reserve(additional);
+        Ok(())
     }
 
     /// Gets the number of values in the store.
@@ -333,7 +1484,7 @@ impl TypeStore {
     /// # }
     /// ```
     pub fn len(&self) -> Result<usize, MapError> {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let store = self.lock_items()?;
         Ok(store.len())
     }
 
@@ -357,9 +1508,80 @@ impl TypeStore {
     /// # }
     /// ```
     pub fn is_empty(&self) -> Result<bool, MapError> {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let store = self.lock_items()?;
         Ok(store.is_empty())
     }
+
+    /// Gets the number of values in the store, recovering from a poisoned lock.
+    ///
+    /// Unlike [`len`](Self::len), a lock poisoned by a panicking thread is read
+    /// through rather than reported, so this never fails.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use sovran_typemap::{TypeStore, MapError};
+    /// # fn main() -> Result<(), MapError> {
+    /// let store = TypeStore::new();
+    /// store.set(42i32)?;
+    /// assert_eq!(store.len_or_zero(), 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn len_or_zero(&self) -> usize {
+        self.lock_items_lenient().len()
+    }
+
+    /// Checks if the store is empty, recovering from a poisoned lock.
+    ///
+    /// See [`len_or_zero`](Self::len_or_zero) for how poisoning is handled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::TypeStore;
+    ///
+    /// let store = TypeStore::new();
+    /// assert!(store.is_empty_lenient());
+    /// ```
+    pub fn is_empty_lenient(&self) -> bool {
+        self.lock_items_lenient().is_empty()
+    }
+}
+
+/// Restores a value replaced by [`TypeStore::override_scoped`] when dropped.
+///
+/// If no value of the type existed before the override, dropping the guard
+/// removes the override instead.
+#[must_use = "the override is undone as soon as the guard is dropped"]
+pub struct OverrideGuard<'a> {
+    store: &'a TypeStore,
+    type_id: TypeId,
+    previous: Option<AnyValue>,
+}
+
+impl fmt::Debug for OverrideGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OverrideGuard")
+            .field("type_id", &self.type_id)
+            .field("had_previous", &self.previous.is_some())
+            .finish()
+    }
+}
+
+impl Drop for OverrideGuard<'_> {
+    fn drop(&mut self) {
+        // Restore even if another thread panicked while holding the lock
+        let mut store = self.store.lock_items_lenient();
+        match self.previous.take() {
+            Some(previous) => {
+                store.insert(self.type_id, previous);
+            }
+            None => {
+                store.remove(&self.type_id);
+            }
+        }
+    }
 }
 
 impl Default for TypeStore {
@@ -368,6 +1590,25 @@ impl Default for TypeStore {
     }
 }
 
+/// Formats a one-line summary listing the stored types by their short names,
+/// such as `TypeStore(Config, Logger)`, sorted for stable output.
+///
+/// Formatting never blocks or panics: if the internal lock is held this prints
+/// `TypeStore(<busy>)`, and if it is poisoned, `TypeStore(<poisoned>)`.
+impl fmt::Display for TypeStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<String> = match lock::peek(&self.items) {
+            Ok(store) => store
+                .values()
+                .map(|value| short_type_name(value.type_name))
+                .collect(),
+            Err(placeholder) => return write!(f, "TypeStore({})", placeholder),
+        };
+        names.sort_unstable();
+        write!(f, "TypeStore({})", names.join(", "))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -565,4 +1806,464 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_set_fn_and_call_fn() -> Result<(), MapError> {
+        struct Add;
+        impl FnKey for Add {
+            type Args = (i32, i32);
+            type Output = i32;
+        }
+
+        struct Negate;
+        impl FnKey for Negate {
+            type Args = i32;
+            type Output = i32;
+        }
+
+        let store = TypeStore::new();
+        store.set_fn::<Add, _>(|(a, b)| a + b)?;
+        store.set_fn::<Negate, _>(|x| -x)?;
+
+        assert_eq!(store.call_fn::<Add>((2, 3))?, 5);
+        assert_eq!(store.call_fn::<Negate>(4)?, -4);
+
+        // Re-registering replaces the function
+        store.set_fn::<Negate, _>(|x| x)?;
+        assert_eq!(store.call_fn::<Negate>(4)?, 4);
+        assert_eq!(store.len()?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_call_fn_missing() {
+        struct Missing;
+        impl FnKey for Missing {
+            type Args = ();
+            type Output = ();
+        }
+
+        let store = TypeStore::new();
+        match store.call_fn::<Missing>(()) {
+            Err(MapError::KeyNotFound(name)) => assert!(name.contains("Missing")),
+            _ => panic!("Expected KeyNotFound error"),
+        }
+    }
+
+    #[test]
+    fn test_with_by_name() -> Result<(), MapError> {
+        let store = TypeStore::new();
+        store.set(TestConfig {
+            name: "scripted".to_string(),
+            value: 7,
+        })?;
+        store.set(AnotherConfig { enabled: true })?;
+
+        let value = store.with_by_name("TestConfig", |any| {
+            any.downcast_ref::<TestConfig>().map(|cfg| cfg.value)
+        })?;
+        assert_eq!(value, Some(7));
+
+        let full_name = std::any::type_name::<AnotherConfig>();
+        let enabled = store.with_by_name(full_name, |any| {
+            any.downcast_ref::<AnotherConfig>().map(|cfg| cfg.enabled)
+        })?;
+        assert_eq!(enabled, Some(true));
+
+        match store.with_by_name("MissingConfig", |_| ()) {
+            Err(MapError::KeyNotFound(name)) => assert_eq!(name, "MissingConfig"),
+            _ => panic!("Expected KeyNotFound error"),
+        }
+
+        let mut names = store.type_names()?;
+        names.sort();
+        assert_eq!(names.len(), 2);
+        assert!(names.iter().any(|name| name.ends_with("TestConfig")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_require_reports_missing_types() -> Result<(), MapError> {
+        let store = TypeStore::new();
+        store.set(AnotherConfig { enabled: true })?;
+
+        let required = [
+            RequiredType::of::<TestConfig>(),
+            RequiredType::of::<AnotherConfig>(),
+            RequiredType::of::<u64>(),
+        ];
+
+        let missing = store.missing_types(&required)?;
+        assert_eq!(missing.len(), 2);
+        assert!(missing[0].ends_with("TestConfig"));
+        assert_eq!(missing[1], "u64");
+
+        match store.require(&required) {
+            Err(MapError::MissingTypes(names)) => assert_eq!(names, missing),
+            _ => panic!("Expected MissingTypes error"),
+        }
+
+        store.set(TestConfig {
+            name: "test".to_string(),
+            value: 1,
+        })?;
+        store.set(0u64)?;
+        store.require(&required)?;
+        assert!(store.missing_types(&required)?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_default() -> Result<(), MapError> {
+        #[derive(Clone, Debug, Default, PartialEq)]
+        struct Counter {
+            hits: u32,
+        }
+
+        let store = TypeStore::new();
+        assert!(store.get::<Counter>().is_err());
+
+        store.register_default::<Counter>()?;
+        assert!(!store.contains::<Counter>()?);
+
+        // with_mut materializes the default before mutating
+        store.with_mut::<Counter, _, _>(|c| c.hits += 1)?;
+        assert_eq!(store.get::<Counter>()?, Counter { hits: 1 });
+
+        // Explicitly set values take precedence, and removal falls back again
+        store.set(Counter { hits: 10 })?;
+        assert_eq!(store.get::<Counter>()?.hits, 10);
+        store.remove::<Counter>()?;
+        assert_eq!(store.get::<Counter>()?.hits, 0);
+
+        // Unregistered types still report KeyNotFound
+        assert!(matches!(
+            store.get::<TestConfig>(),
+            Err(MapError::KeyNotFound(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lenient_len() -> Result<(), MapError> {
+        let store = TypeStore::new();
+        assert!(store.is_empty_lenient());
+
+        store.set(42i32)?;
+        store.set("hello".to_string())?;
+        assert_eq!(store.len_or_zero(), 2);
+        assert!(!store.is_empty_lenient());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_visits_every_value() -> Result<(), MapError> {
+        let store = TypeStore::new();
+        store.set(42i32)?;
+        store.set(AnotherConfig { enabled: true })?;
+
+        let mut seen = Vec::new();
+        store.inspect(|type_id, value| {
+            if let Some(config) = value.downcast_ref::<AnotherConfig>() {
+                assert_eq!(type_id, TypeId::of::<AnotherConfig>());
+                assert!(config.enabled);
+            }
+            seen.push(type_id);
+        })?;
+
+        assert_eq!(seen.len(), 2);
+        assert!(seen.contains(&TypeId::of::<i32>()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_with_moves_value() -> Result<(), MapError> {
+        // Not Clone or Default, so only an ownership-based update can change it
+        struct Token(String);
+
+        let store = TypeStore::new();
+        store.replace_with(|old: Option<Token>| {
+            assert!(old.is_none());
+            Token("a".to_string())
+        })?;
+        store.replace_with(|old: Option<Token>| {
+            let Token(mut s) = old.unwrap();
+            s.push('b');
+            Token(s)
+        })?;
+
+        assert_eq!(store.with(|t: &Token| t.0.clone())?, "ab");
+        assert_eq!(store.len()?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_store_value_clones_registered_types() -> Result<(), MapError> {
+        let store = TypeStore::new();
+        store.register_clone::<TestConfig>()?;
+        store.register_clone::<AnotherConfig>()?;
+
+        store.set(TestConfig {
+            name: "test".to_string(),
+            value: 1,
+        })?;
+        store.set(42i32)?;
+
+        let mut snapshot = store.to_store_value()?;
+        assert_eq!(snapshot.len(), 1);
+        assert!(!snapshot.contains::<i32>());
+        assert!(!snapshot.contains::<AnotherConfig>());
+
+        // The snapshot is detached from the store
+        snapshot.with_mut(|c: &mut TestConfig| c.value = 2);
+        assert_eq!(store.get::<TestConfig>()?.value, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_register_all_tuples() -> Result<(), MapError> {
+        let store = TypeStore::new();
+        store.register_all((1u8,))?;
+        store.register_all((
+            TestConfig {
+                name: "t".to_string(),
+                value: 3,
+            },
+            AnotherConfig { enabled: true },
+            "s".to_string(),
+        ))?;
+        assert_eq!(store.len()?, 4);
+        assert_eq!(store.get::<TestConfig>()?.value, 3);
+
+        // Largest supported arity; duplicate types keep the last element
+        store.register_all((
+            1u16, 2u32, 3u64, 4u128, 5i8, 6i16, 7i32, 8i64, 9i128, 10usize, 11isize, 12u8,
+        ))?;
+        assert_eq!(store.get::<u8>()?, 12);
+        assert_eq!(store.len()?, 15);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "reentrant lock on TypeStore")]
+    fn test_reentrant_access_panics() {
+        let store = TypeStore::new();
+        store.set(1u8).unwrap();
+        store.set(2u16).unwrap();
+
+        // Without the check this would deadlock
+        let _ = store.with(|_: &u8| store.get::<u16>());
+    }
+
+    #[test]
+    fn test_separate_stores_nest() -> Result<(), MapError> {
+        let outer = TypeStore::new();
+        let inner = TypeStore::new();
+        outer.set(1u8)?;
+        inner.set(2u8)?;
+
+        let sum = outer.with(|a: &u8| inner.with(|b: &u8| a + b))??;
+        assert_eq!(sum, 3);
+
+        // The lock is released once the closure returns
+        assert_eq!(outer.get::<u8>()?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_override_scoped_restores_on_panic() -> Result<(), MapError> {
+        let store = TypeStore::new();
+        store.set(TestConfig {
+            name: "real".to_string(),
+            value: 1,
+        })?;
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            let _mock = store
+                .override_scoped(TestConfig {
+                    name: "mock".to_string(),
+                    value: 2,
+                })
+                .unwrap();
+            assert_eq!(store.get::<TestConfig>().unwrap().name, "mock");
+            panic!("test failure");
+        }));
+        assert!(result.is_err());
+        assert_eq!(store.get::<TestConfig>()?.name, "real");
+
+        // Without an original, the override is simply removed
+        {
+            let _flag = store.override_scoped(AnotherConfig { enabled: true })?;
+            assert!(store.contains::<AnotherConfig>()?);
+        }
+        assert!(!store.contains::<AnotherConfig>()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_approx_memory_counts_sized_types() -> Result<(), MapError> {
+        let store = TypeStore::new();
+        assert_eq!(store.approx_memory()?, 0);
+
+        store.register_sizer::<String, _>(|s| s.len())?;
+        store.register_sizer::<u64, _>(|_| 8)?;
+        store.set("hello".to_string())?;
+        store.set(1u64)?;
+        store.set(AnotherConfig { enabled: true })?;
+        assert_eq!(store.approx_memory()?, 13);
+
+        // Re-registering replaces the sizer
+        store.register_sizer::<u64, _>(|_| 100)?;
+        assert_eq!(store.approx_memory()?, 105);
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_or_insert_with_detects_cycles() -> Result<(), MapError> {
+        #[derive(Clone, Debug, PartialEq)]
+        struct A(u32);
+        #[derive(Clone, Debug, PartialEq)]
+        struct B(u32);
+
+        let store = TypeStore::new();
+        let inner_error = RefCell::new(None);
+
+        // A's initializer needs B, whose initializer needs A again
+        let a = store.get_or_insert_with(|| {
+            let b = store.get_or_insert_with(|| match store.get_or_insert_with(|| A(0)) {
+                Ok(a) => B(a.0),
+                Err(e) => {
+                    *inner_error.borrow_mut() = Some(e);
+                    B(2)
+                }
+            });
+            A(b.unwrap().0 + 1)
+        })?;
+        assert_eq!(a, A(3));
+
+        match inner_error.into_inner() {
+            Some(MapError::CircularDependency { chain }) => {
+                assert_eq!(chain.len(), 3);
+                assert!(chain[0].ends_with("::A") && chain[2].ends_with("::A"));
+                assert!(chain[1].ends_with("::B"));
+            }
+            other => panic!("expected a circular dependency, got {:?}", other),
+        }
+
+        // Once initialized, the stored value wins and f doesn't run
+        assert_eq!(store.get_or_insert_with(|| -> A { unreachable!() })?, A(3));
+
+        // The same type can be initialized in another store while this one is
+        // still initializing it
+        #[derive(Clone, Debug, PartialEq)]
+        struct C(u32);
+
+        let outer = TypeStore::new();
+        let other = TypeStore::new();
+        let nested =
+            outer.get_or_insert_with(|| C(other.get_or_insert_with(|| C(7)).unwrap().0 + 1))?;
+        assert_eq!(nested, C(8));
+        assert_eq!(other.get::<C>()?, C(7));
+        Ok(())
+    }
+
+    #[test]
+    fn test_fallback_chain() -> Result<(), MapError> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        #[derive(Clone, Debug, PartialEq)]
+        struct Port(u16);
+
+        let parent = TypeStore::new();
+        let store = TypeStore::new();
+        let calls = Arc::new(AtomicUsize::new(0));
+
+        let (upstream, counter) = (parent.clone(), Arc::clone(&calls));
+        store.set_fallback(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            upstream.get::<Port>().ok()
+        })?;
+
+        // The provider may miss too
+        assert!(matches!(store.get::<Port>(), Err(MapError::KeyNotFound(_))));
+        parent.set(Port(80))?;
+        assert_eq!(store.get::<Port>()?, Port(80));
+        assert_eq!(store.with(|p: &Port| p.0)?, 80);
+        assert!(!store.contains::<Port>()?);
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        assert!(matches!(
+            store.with_mut(|_: &mut Port| ()),
+            Err(MapError::KeyNotFound(_))
+        ));
+
+        // Cached: the first provided value is kept
+        let (upstream, counter) = (parent.clone(), Arc::clone(&calls));
+        store.set_fallback_cached(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            upstream.get::<Port>().ok()
+        })?;
+        assert_eq!(store.get::<Port>()?, Port(80));
+        parent.set(Port(8080))?;
+        assert_eq!(store.get::<Port>()?, Port(80));
+        assert!(store.contains::<Port>()?);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // A provider that reads its own type from the same store is a cycle
+        let this = store.clone();
+        store.set_fallback(move || match this.get::<u8>() {
+            Err(MapError::CircularDependency { .. }) => Some(1u8),
+            other => other.ok(),
+        })?;
+        assert_eq!(store.get::<u8>()?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_display_lists_short_type_names() -> Result<(), MapError> {
+        #[derive(Debug)]
+        struct Logger;
+
+        let store = TypeStore::new();
+        assert_eq!(store.to_string(), "TypeStore()");
+
+        store.set(Logger)?;
+        store.set(vec![String::new()])?;
+        store.set(HashMap::<String, Option<u8>>::new())?;
+        assert_eq!(
+            store.to_string(),
+            "TypeStore(HashMap<String, Option<u8>>, Logger, Vec<String>)"
+        );
+
+        // Formatting from inside a closure reports the held lock instead of panicking
+        let inside = store.with(|_: &Logger| store.to_string())?;
+        assert_eq!(inside, "TypeStore(<busy>)");
+
+        let poisoner = store.clone();
+        let result = std::thread::spawn(move || {
+            let _ = poisoner.with_mut(|_: &mut Logger| panic!("poison"));
+        })
+        .join();
+        assert!(result.is_err());
+        assert_eq!(store.to_string(), "TypeStore(<poisoned>)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_capacity_and_reserve() -> Result<(), MapError> {
+        let store = TypeStore::with_capacity(8);
+        assert!(store.items.lock().unwrap().capacity() >= 8);
+        assert!(store.is_empty()?);
+
+        store.set(1u8)?;
+        store.reserve(32)?;
+        assert!(store.items.lock().unwrap().capacity() >= 33);
+        assert_eq!(store.get::<u8>()?, 1);
+        Ok(())
+    }
 }
@@ -10,6 +10,10 @@ pub trait CloneAny: Any + Send + Sync {
     fn as_any(&self) -> &dyn Any;
     /// Get a mutable reference to the underlying Any.
     fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// Get the `std::any::type_name` of the concrete type.
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
 }
 
 impl<T: Clone + Any + Send + Sync> CloneAny for T {
@@ -26,6 +30,15 @@ impl<T: Clone + Any + Send + Sync> CloneAny for T {
     }
 }
 
+/// Compares two stored values of the same type, for `changed_types`.
+type EqFn = fn(&dyn Any, &dyn Any) -> bool;
+
+/// Compares two `T`s, type-erased for the comparators table.
+fn eq_entry<T: PartialEq + 'static>(a: &dyn Any, b: &dyn Any) -> bool {
+    // Types are guaranteed to match since the comparator is registered under T's TypeId
+    a.downcast_ref::<T>().unwrap() == b.downcast_ref::<T>().unwrap()
+}
+
 // Implement Clone for Box<dyn CloneAny> - this is the key trick
 // NOTE: Must use (**self) to call the inner type's clone_any, not the Box's
 impl Clone for Box<dyn CloneAny> {
@@ -71,6 +84,7 @@ impl Clone for Box<dyn CloneAny> {
 #[derive(Default, Clone)]
 pub struct TypeStoreValue {
     items: HashMap<TypeId, Box<dyn CloneAny>>,
+    comparators: HashMap<TypeId, EqFn>,
 }
 
 impl std::fmt::Debug for TypeStoreValue {
@@ -95,6 +109,7 @@ impl TypeStoreValue {
     pub fn new() -> Self {
         Self {
             items: HashMap::new(),
+            comparators: HashMap::new(),
         }
     }
 
@@ -123,6 +138,11 @@ impl TypeStoreValue {
         self.items.insert(TypeId::of::<V>(), Box::new(value));
     }
 
+    /// Stores an already-boxed value under the given type id.
+    pub(crate) fn insert_boxed(&mut self, type_id: TypeId, value: Box<dyn CloneAny>) {
+        self.items.insert(type_id, value);
+    }
+
     /// Stores a value generated by a closure.
     ///
     /// # Examples
@@ -148,6 +168,9 @@ impl TypeStoreValue {
     ///
     /// Returns `None` if no value of this type exists.
     ///
+    /// This always clones the stored value. When a borrow is enough, prefer
+    /// `with`, which runs a closure against the stored value without cloning.
+    ///
     /// # Examples
     ///
     /// ```
@@ -294,6 +317,155 @@ impl TypeStoreValue {
     pub fn is_empty(&self) -> bool {
         self.items.is_empty()
     }
+
+    /// Clones only the values of the listed types into a new store.
+    ///
+    /// This is a cheap partial snapshot when only a few types need checkpointing.
+    /// Ids with no value in the store are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::any::TypeId;
+    /// use sovran_typemap::TypeStoreValue;
+    ///
+    /// let mut store = TypeStoreValue::new();
+    /// store.set(42i32);
+    /// store.set("world".to_string());
+    ///
+    /// let checkpoint = store.clone_types(&[TypeId::of::<i32>()]);
+    /// assert_eq!(checkpoint.get::<i32>(), Some(42));
+    /// assert!(!checkpoint.contains::<String>());
+    /// ```
+    pub fn clone_types(&self, types: &[TypeId]) -> TypeStoreValue {
+        let items = types
+            .iter()
+            .filter_map(|id| self.items.get(id).map(|value| (*id, value.clone())))
+            .collect();
+        Self {
+            items,
+            comparators: self.comparators.clone(),
+        }
+    }
+
+    /// Registers `PartialEq` as the comparator for values of type `V`.
+    ///
+    /// `changed_types` uses it to report values of type `V` that differ between two
+    /// snapshots. Registrations are carried along by `clone` and `clone_types`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::TypeStoreValue;
+    ///
+    /// let mut store = TypeStoreValue::new();
+    /// store.register_eq::<u32>();
+    /// ```
+    pub fn register_eq<V>(&mut self)
+    where
+        V: 'static + PartialEq,
+    {
+        self.comparators.insert(TypeId::of::<V>(), eq_entry::<V>);
+    }
+
+    /// Lists the types that differ between this store and `other`.
+    ///
+    /// A type is reported if it is present in only one of the two stores, or if it
+    /// is present in both, has a comparator registered with `register_eq` on
+    /// either store, and the two values compare unequal. Types without a
+    /// comparator are only checked for presence. Ids are returned in arbitrary
+    /// order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::any::TypeId;
+    /// use sovran_typemap::TypeStoreValue;
+    ///
+    /// let mut store = TypeStoreValue::new();
+    /// store.register_eq::<u32>();
+    /// store.set(10u32);
+    /// let before = store.clone();
+    ///
+    /// store.set(20u32);
+    /// store.set(true);
+    ///
+    /// let mut changed = before.changed_types(&store);
+    /// changed.sort();
+    /// let mut expected = vec![TypeId::of::<u32>(), TypeId::of::<bool>()];
+    /// expected.sort();
+    /// assert_eq!(changed, expected);
+    /// ```
+    pub fn changed_types(&self, other: &TypeStoreValue) -> Vec<TypeId> {
+        let mut changed: Vec<TypeId> = other
+            .items
+            .keys()
+            .filter(|id| !self.items.contains_key(id))
+            .copied()
+            .collect();
+        for (id, value) in &self.items {
+            let Some(other_value) = other.items.get(id) else {
+                changed.push(*id);
+                continue;
+            };
+            let eq = self
+                .comparators
+                .get(id)
+                .or_else(|| other.comparators.get(id));
+            if let Some(eq) = eq {
+                if !eq((**value).as_any(), (**other_value).as_any()) {
+                    changed.push(*id);
+                }
+            }
+        }
+        changed
+    }
+
+    /// Returns the `TypeId` of every value in the store.
+    ///
+    /// Ids are returned in arbitrary order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::any::TypeId;
+    /// use sovran_typemap::TypeStoreValue;
+    ///
+    /// let mut store = TypeStoreValue::new();
+    /// store.set(42i32);
+    ///
+    /// assert_eq!(store.type_ids(), vec![TypeId::of::<i32>()]);
+    /// ```
+    pub fn type_ids(&self) -> Vec<TypeId> {
+        self.items.keys().copied().collect()
+    }
+
+    /// Returns the type name of every value in the store, sorted.
+    ///
+    /// Names come from `std::any::type_name`, so no registration is needed. This is
+    /// meant for diagnostics such as logging what a snapshot contains, and the
+    /// exact name format is not guaranteed to be stable across compiler versions.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::TypeStoreValue;
+    ///
+    /// let mut store = TypeStoreValue::new();
+    /// store.set(42i32);
+    /// store.set(true);
+    ///
+    /// assert_eq!(store.type_names(), vec!["bool", "i32"]);
+    /// ```
+    pub fn type_names(&self) -> Vec<&'static str> {
+        let mut names: Vec<&'static str> = self
+            .items
+            .values()
+            .map(|value| (**value).type_name())
+            .collect();
+        names.sort_unstable();
+        names
+    }
 }
 
 #[cfg(test)]
@@ -484,4 +656,83 @@ mod tests {
         assert!(debug_str.contains("len"));
         assert!(debug_str.contains("2"));
     }
+
+    #[test]
+    fn test_type_ids_and_names() {
+        let mut store = TypeStoreValue::new();
+        assert!(store.type_ids().is_empty());
+        assert!(store.type_names().is_empty());
+
+        store.set(TestConfig {
+            name: "test".to_string(),
+            value: 1,
+        });
+        store.set(AnotherConfig { enabled: true });
+
+        let ids = store.type_ids();
+        assert_eq!(ids.len(), 2);
+        assert!(ids.contains(&TypeId::of::<TestConfig>()));
+        assert!(ids.contains(&TypeId::of::<AnotherConfig>()));
+
+        let names = store.type_names();
+        assert!(names[0].ends_with("AnotherConfig"));
+        assert!(names[1].ends_with("TestConfig"));
+
+        // A clone reports the same types
+        assert_eq!(store.clone().type_names(), names);
+    }
+
+    #[test]
+    fn test_clone_types() {
+        let mut store = TypeStoreValue::new();
+        store.set(TestConfig {
+            name: "test".to_string(),
+            value: 1,
+        });
+        store.set(AnotherConfig { enabled: true });
+        store.set(42i32);
+
+        let partial = store.clone_types(&[TypeId::of::<TestConfig>(), TypeId::of::<String>()]);
+        assert_eq!(partial.len(), 1);
+        assert_eq!(partial.get::<TestConfig>().unwrap().value, 1);
+        assert!(!partial.contains::<AnotherConfig>());
+        assert!(!partial.contains::<i32>());
+        assert!(!partial.contains::<String>());
+
+        // The snapshot is independent of the original
+        store.with_mut::<TestConfig, _, _>(|cfg| cfg.value = 2);
+        assert_eq!(partial.get::<TestConfig>().unwrap().value, 1);
+    }
+
+    #[test]
+    fn test_changed_types() {
+        let mut store = TypeStoreValue::new();
+        store.set(TestConfig {
+            name: "test".to_string(),
+            value: 1,
+        });
+        store.set(AnotherConfig { enabled: true });
+        store.set(42i32);
+        let before = store.clone();
+        assert!(before.changed_types(&store).is_empty());
+
+        // Without a comparator, only presence is compared
+        store.with_mut::<TestConfig, _, _>(|cfg| cfg.value = 2);
+        store.remove::<i32>();
+        store.set(1.5f64);
+        let mut changed = before.changed_types(&store);
+        changed.sort();
+        let mut expected = vec![TypeId::of::<i32>(), TypeId::of::<f64>()];
+        expected.sort();
+        assert_eq!(changed, expected);
+
+        // A comparator on either side picks up the value change
+        store.register_eq::<TestConfig>();
+        store.register_eq::<AnotherConfig>();
+        for diff in [before.changed_types(&store), store.changed_types(&before)] {
+            assert_eq!(diff.len(), 3);
+            assert!(diff.contains(&TypeId::of::<TestConfig>()));
+            assert!(!diff.contains(&TypeId::of::<AnotherConfig>()));
+        }
+    }
 }
@@ -1,18 +1,114 @@
 // src/traits.rs
+use crate::lock::{self, TrackedGuard};
 use crate::MapError;
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 use std::hash::Hash;
 use std::sync::{Arc, Mutex};
 
+/// Explains how to avoid the nested call in the debug-build reentrancy panic.
+const REENTRY_HINT: &str = "finish the closure before using the map again";
+
 pub(crate) struct TypeMapValue {
     concrete_type_id: TypeId,
+    concrete_type_name: &'static str,
     trait_type_id: TypeId,
+    trait_type_name: &'static str,
     concrete_value: Box<dyn Any + Send + Sync>,
     trait_object: Box<dyn Any + Send + Sync>,
 }
 
+impl TypeMapValue {
+    /// Builds an entry holding `value` both as `U` and as the trait object `Box<T>`.
+    fn new<T, U>(value: U) -> Self
+    where
+        T: ?Sized + Any + Send + Sync + 'static,
+        U: 'static + Into<Box<T>> + Send + Sync + Clone,
+    {
+        let concrete_value = Box::new(value.clone());
+        let trait_object: Box<T> = value.into();
+        debug_assert!(
+            std::mem::size_of_val::<T>(&*trait_object) == std::mem::size_of::<U>()
+                && std::mem::align_of_val::<T>(&*trait_object) == std::mem::align_of::<U>(),
+            "Into<Box<{}>> for {} did not box the value itself",
+            std::any::type_name::<T>(),
+            std::any::type_name::<U>(),
+        );
+
+        Self {
+            concrete_type_id: TypeId::of::<U>(),
+            concrete_type_name: std::any::type_name::<U>(),
+            trait_type_id: TypeId::of::<T>(),
+            trait_type_name: std::any::type_name::<T>(),
+            concrete_value,
+            trait_object: Box::new(trait_object),
+        }
+    }
+
+    /// Returns the trait object if this entry was stored under `T`.
+    fn trait_ref<T: ?Sized + 'static>(&self) -> Option<&T> {
+        if self.trait_type_id != TypeId::of::<T>() {
+            return None;
+        }
+        // The trait object is always stored as Box<T> for its trait_type_id
+        let boxed_trait = self.trait_object.downcast_ref::<Box<T>>().unwrap();
+        Some(&**boxed_trait)
+    }
+}
+
+/// An entry taken out of a [`TraitTypeMap`] by [`TraitTypeMap::remove_returning`].
+///
+/// The map stores each value twice, once as its concrete type and once as a
+/// trait object, so the removed entry can be turned back into either one. Both
+/// conversions hand the entry back unchanged if the requested type doesn't match.
+pub struct RemovedEntry {
+    value: TypeMapValue,
+}
+
+impl RemovedEntry {
+    /// Takes ownership of the value as its concrete type `V`, dropping the
+    /// trait-object copy.
+    ///
+    /// Returns `Err(self)` if `V` is not the concrete type that was stored.
+    pub fn into_concrete<V: 'static>(self) -> Result<V, Self> {
+        if self.value.concrete_type_id != TypeId::of::<V>() {
+            return Err(self);
+        }
+        // Type is guaranteed to match since we've checked the TypeId
+        Ok(*self.value.concrete_value.downcast::<V>().unwrap())
+    }
+
+    /// Takes ownership of the value as the trait object `Box<T>`, dropping the
+    /// concrete copy.
+    ///
+    /// Returns `Err(self)` if `T` is not the trait the value was stored under.
+    pub fn into_trait<T: ?Sized + 'static>(self) -> Result<Box<T>, Self> {
+        if self.value.trait_type_id != TypeId::of::<T>() || !self.value.trait_object.is::<Box<T>>()
+        {
+            return Err(self);
+        }
+        // Type is guaranteed to match since we've checked it above
+        Ok(*self.value.trait_object.downcast::<Box<T>>().unwrap())
+    }
+}
+
+/// How [`TypeMap::absorb`](crate::TypeMap::absorb) and [`TraitTypeMap::absorb`]
+/// resolve a key present in both maps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OnConflict {
+    /// The incoming entry replaces the existing one.
+    Overwrite,
+    /// The existing entry is kept and the incoming one is dropped.
+    Keep,
+}
+
+impl Debug for RemovedEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemovedEntry").finish_non_exhaustive()
+    }
+}
+
 /// A thread-safe heterogeneous container that supports trait object access.
 ///
 /// `TraitTypeMap` extends the concept of `TypeMap` to support storing values
@@ -64,6 +160,14 @@ where
         }
     }
 
+    /// Acquires the items lock.
+    ///
+    /// In debug builds this panics if the current thread already holds the lock,
+    /// which would otherwise deadlock silently.
+    fn lock_items(&self) -> Result<TrackedGuard<'_, HashMap<K, TypeMapValue>>, MapError> {
+        lock::lock(&self.items, "TraitTypeMap", REENTRY_HINT)
+    }
+
     /// Stores a value with its associated trait type.
     ///
     /// The value can later be accessed either by its concrete type or through
@@ -74,6 +178,17 @@ where
     /// * `T` - The trait type (e.g., `dyn MyTrait`)
     /// * `U` - The concrete type that implements the trait
     ///
+    /// # Invariant
+    ///
+    /// The `Into<Box<T>>` impl for `U` must box the value itself, e.g.
+    /// `Box::new(self)`. The map keeps a clone of `U` for concrete access and the
+    /// converted box for trait access; if the conversion produced a box of some
+    /// other type, `with::<U>` and `with_trait::<T>` would silently see different
+    /// values. The concrete type behind a trait object can't be recovered in
+    /// general, so in debug builds this is checked as far as possible by comparing
+    /// the size and alignment of the boxed value against `U`, panicking on a
+    /// mismatch.
+    ///
     /// # Errors
     ///
     /// Returns `MapError::LockError` if the internal lock cannot be acquired.
@@ -82,20 +197,80 @@ where
         T: ?Sized + Any + Send + Sync + 'static,
         U: 'static + Into<Box<T>> + Send + Sync + Clone,
     {
-        let type_map_value = TypeMapValue {
-            concrete_type_id: TypeId::of::<U>(),
-            trait_type_id: TypeId::of::<T>(),
-            concrete_value: Box::new(value.clone()),
-            trait_object: Box::new(value.into()),
-        };
+        let type_map_value = TypeMapValue::new::<T, U>(value);
 
-        let mut store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let mut store = self.lock_items()?;
         store.insert(key, type_map_value);
         Ok(())
     }
 
+    /// Stores a value under a trait, returning the trait object it replaced.
+    ///
+    /// This is [`set_trait`](Self::set_trait) for hot-swapping: the previous
+    /// implementation comes back as a `Box<T>` so it can be shut down cleanly
+    /// instead of being dropped. The same `Into<Box<T>>` invariant applies.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::WrongTrait` if the existing entry was stored under a
+    ///   different trait; the existing entry is left in place and `value` is dropped
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(old))` if the key held an entry, `Ok(None)` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::TraitTypeMap;
+    ///
+    /// trait Handler: Send + Sync {
+    ///     fn close(&self) -> String;
+    /// }
+    ///
+    /// #[derive(Clone)]
+    /// struct Versioned(u32);
+    ///
+    /// impl Handler for Versioned {
+    ///     fn close(&self) -> String { format!("v{} closed", self.0) }
+    /// }
+    ///
+    /// impl From<Versioned> for Box<dyn Handler> {
+    ///     fn from(value: Versioned) -> Self { Box::new(value) }
+    /// }
+    ///
+    /// let handlers = TraitTypeMap::<String>::new();
+    /// let key = "request".to_string();
+    /// assert!(handlers.replace_trait::<dyn Handler, _>(key.clone(), Versioned(1)).unwrap().is_none());
+    ///
+    /// let old = handlers.replace_trait::<dyn Handler, _>(key.clone(), Versioned(2)).unwrap().unwrap();
+    /// assert_eq!(old.close(), "v1 closed");
+    /// ```
+    pub fn replace_trait<T, U>(&self, key: K, value: U) -> Result<Option<Box<T>>, MapError>
+    where
+        T: ?Sized + Any + Send + Sync + 'static,
+        U: 'static + Into<Box<T>> + Send + Sync + Clone,
+    {
+        let mut store = self.lock_items()?;
+        if let Some(existing) = store.get(&key) {
+            if existing.trait_type_id != TypeId::of::<T>() {
+                return Err(MapError::WrongTrait {
+                    expected: std::any::type_name::<T>(),
+                    found: existing.trait_type_name,
+                });
+            }
+        }
+
+        let old = store.insert(key, TypeMapValue::new::<T, U>(value));
+        // The trait object is always stored as Box<T> for its trait_type_id
+        Ok(old.map(|old| *old.trait_object.downcast::<Box<T>>().unwrap()))
+    }
+
     /// Accesses a value by its concrete type with a read-only closure.
     ///
+    /// The closure borrows the stored value, so nothing is cloned.
+    ///
     /// # Errors
     ///
     /// - Returns `MapError::LockError` if the internal lock cannot be acquired
@@ -105,7 +280,7 @@ where
     where
         F: FnOnce(&V) -> R,
     {
-        let guard = self.items.lock().map_err(|_| MapError::LockError)?;
+        let guard = self.lock_items()?;
         let value = guard
             .get(key)
             .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
@@ -116,7 +291,23 @@ where
             }
         }
 
-        Err(MapError::TypeMismatch)
+        Err(MapError::TypeMismatch {
+            expected: std::any::type_name::<V>(),
+            found: value.concrete_type_name,
+        })
+    }
+
+    /// Accesses a value by its concrete type with a read-only closure; an alias of
+    /// [`with`](Self::with).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`with`](Self::with).
+    pub fn with_read<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&V) -> R,
+    {
+        self.with(key, f)
     }
 
     /// Accesses a value by its concrete type with a read-write closure.
@@ -130,7 +321,7 @@ where
     where
         F: FnOnce(&mut V) -> R,
     {
-        let mut guard = self.items.lock().map_err(|_| MapError::LockError)?;
+        let mut guard = self.lock_items()?;
         let value = guard
             .get_mut(key)
             .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
@@ -141,7 +332,23 @@ where
             }
         }
 
-        Err(MapError::TypeMismatch)
+        Err(MapError::TypeMismatch {
+            expected: std::any::type_name::<V>(),
+            found: value.concrete_type_name,
+        })
+    }
+
+    /// Accesses a value by its concrete type with a read-write closure; an alias of
+    /// [`with_mut`](Self::with_mut).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`with_mut`](Self::with_mut).
+    pub fn with_write<V: 'static, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&mut V) -> R,
+    {
+        self.with_mut(key, f)
     }
 
     /// Accesses a value through its trait interface with a read-only closure.
@@ -153,24 +360,93 @@ where
     ///
     /// - Returns `MapError::LockError` if the internal lock cannot be acquired
     /// - Returns `MapError::KeyNotFound` if the key doesn't exist
-    /// - Returns `MapError::TypeMismatch` if the trait type doesn't match
+    /// - Returns `MapError::WrongTrait` if the entry was stored under a different trait
     pub fn with_trait<T, F, R>(&self, key: &K, f: F) -> Result<R, MapError>
     where
         T: ?Sized + Any + Send + Sync + 'static,
         F: FnOnce(&T) -> R,
     {
-        let guard = self.items.lock().map_err(|_| MapError::LockError)?;
+        let guard = self.lock_items()?;
         let value = guard
             .get(key)
             .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
 
-        if value.trait_type_id == TypeId::of::<T>() {
-            if let Some(boxed_trait) = value.trait_object.downcast_ref::<Box<T>>() {
-                return Ok(f(&**boxed_trait));
-            }
-        }
+        let object = value.trait_ref::<T>().ok_or(MapError::WrongTrait {
+            expected: std::any::type_name::<T>(),
+            found: value.trait_type_name,
+        })?;
+        Ok(f(object))
+    }
+
+    /// Accesses a value through the first of two traits it was stored under.
+    ///
+    /// If the entry was stored under `T1`, `f1` runs; otherwise, if it was stored
+    /// under `T2`, `f2` runs. Both checks happen under a single lock, so this
+    /// dispatches on "supports one of several capabilities" without retrying
+    /// `with_trait` on `WrongTrait`.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist
+    /// - Returns `MapError::WrongTrait` if the entry was stored under neither trait;
+    ///   `expected` names `T1`
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::TraitTypeMap;
+    ///
+    /// trait Serializer: Send + Sync {
+    ///     fn serialize(&self) -> String;
+    /// }
+    /// trait Renderer: Send + Sync {
+    ///     fn render(&self) -> String;
+    /// }
+    ///
+    /// #[derive(Clone)]
+    /// struct Chart;
+    ///
+    /// impl Renderer for Chart {
+    ///     fn render(&self) -> String { "<svg/>".to_string() }
+    /// }
+    ///
+    /// impl From<Chart> for Box<dyn Renderer> {
+    ///     fn from(value: Chart) -> Self { Box::new(value) }
+    /// }
+    ///
+    /// let plugins = TraitTypeMap::<String>::new();
+    /// plugins.set_trait::<dyn Renderer, _>("chart".to_string(), Chart).unwrap();
+    ///
+    /// let output = plugins
+    ///     .with_trait_or::<dyn Serializer, dyn Renderer, _, _, _>(
+    ///         &"chart".to_string(),
+    ///         |s| s.serialize(),
+    ///         |r| r.render(),
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(output, "<svg/>");
+    /// ```
+    pub fn with_trait_or<T1, T2, R, F1, F2>(&self, key: &K, f1: F1, f2: F2) -> Result<R, MapError>
+    where
+        T1: ?Sized + Any + Send + Sync + 'static,
+        T2: ?Sized + Any + Send + Sync + 'static,
+        F1: FnOnce(&T1) -> R,
+        F2: FnOnce(&T2) -> R,
+    {
+        let guard = self.lock_items()?;
+        let value = guard
+            .get(key)
+            .ok_or_else(|| MapError::KeyNotFound(format!("{:?}", key)))?;
 
-        Err(MapError::TypeMismatch)
+        if let Some(object) = value.trait_ref::<T1>() {
+            return Ok(f1(object));
+        }
+        let object = value.trait_ref::<T2>().ok_or(MapError::WrongTrait {
+            expected: std::any::type_name::<T1>(),
+            found: value.trait_type_name,
+        })?;
+        Ok(f2(object))
     }
 
     /// Removes a value from the store.
@@ -183,20 +459,163 @@ where
     ///
     /// Returns `Ok(true)` if the key was present and removed, `Ok(false)` otherwise.
     pub fn remove(&self, key: &K) -> Result<bool, MapError> {
-        let mut store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let mut store = self.lock_items()?;
         Ok(store.remove(key).is_some())
     }
 
+    /// Removes a value from the store and returns it, so ownership can be reclaimed.
+    ///
+    /// Use [`RemovedEntry::into_concrete`] or [`RemovedEntry::into_trait`] to get
+    /// the value back by its concrete type or as a trait object.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    ///
+    /// # Returns
+    ///
+    /// Returns `Ok(Some(entry))` if the key was present, `Ok(None)` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::TraitTypeMap;
+    ///
+    /// trait Plugin: Send + Sync {
+    ///     fn shutdown(&self) -> String;
+    /// }
+    ///
+    /// #[derive(Clone)]
+    /// struct Metrics;
+    ///
+    /// impl Plugin for Metrics {
+    ///     fn shutdown(&self) -> String { "metrics flushed".to_string() }
+    /// }
+    ///
+    /// impl From<Metrics> for Box<dyn Plugin> {
+    ///     fn from(value: Metrics) -> Self { Box::new(value) }
+    /// }
+    ///
+    /// let plugins = TraitTypeMap::<String>::new();
+    /// plugins.set_trait::<dyn Plugin, _>("metrics".to_string(), Metrics).unwrap();
+    ///
+    /// let entry = plugins.remove_returning(&"metrics".to_string()).unwrap().unwrap();
+    /// let plugin: Box<dyn Plugin> = entry.into_trait::<dyn Plugin>().unwrap();
+    /// assert_eq!(plugin.shutdown(), "metrics flushed");
+    /// ```
+    pub fn remove_returning(&self, key: &K) -> Result<Option<RemovedEntry>, MapError> {
+        let mut store = self.lock_items()?;
+        Ok(store.remove(key).map(|value| RemovedEntry { value }))
+    }
+
+    /// Moves every entry out of `other` and into this map.
+    ///
+    /// Entries hold non-`Clone` trait objects, so merging moves them rather than
+    /// copying, and `other` is consumed.
+    ///
+    /// When a key exists in both maps, `on_conflict` decides which entry survives:
+    /// [`OnConflict::Overwrite`] takes the incoming one, [`OnConflict::Keep`] keeps
+    /// the existing one and drops the incoming one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if either internal lock cannot be acquired.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sovran_typemap::{OnConflict, TraitTypeMap};
+    ///
+    /// trait Plugin: Send + Sync {
+    ///     fn name(&self) -> String;
+    /// }
+    ///
+    /// #[derive(Clone)]
+    /// struct Named(&'static str);
+    ///
+    /// impl Plugin for Named {
+    ///     fn name(&self) -> String { self.0.to_string() }
+    /// }
+    ///
+    /// impl From<Named> for Box<dyn Plugin> {
+    ///     fn from(value: Named) -> Self { Box::new(value) }
+    /// }
+    ///
+    /// let host = TraitTypeMap::<String>::new();
+    /// host.set_trait::<dyn Plugin, _>("auth".to_string(), Named("host-auth")).unwrap();
+    ///
+    /// let bundle = TraitTypeMap::<String>::new();
+    /// bundle.set_trait::<dyn Plugin, _>("auth".to_string(), Named("bundle-auth")).unwrap();
+    /// bundle.set_trait::<dyn Plugin, _>("cache".to_string(), Named("bundle-cache")).unwrap();
+    ///
+    /// host.absorb(bundle, OnConflict::Keep).unwrap();
+    ///
+    /// let auth = host.with_trait::<dyn Plugin, _, _>(&"auth".to_string(), |p| p.name()).unwrap();
+    /// assert_eq!(auth, "host-auth");
+    /// assert!(host.contains_key(&"cache".to_string()).unwrap());
+    /// ```
+    pub fn absorb(&self, other: TraitTypeMap<K>, on_conflict: OnConflict) -> Result<(), MapError> {
+        let incoming: Vec<(K, TypeMapValue)> = other.lock_items()?.drain().collect();
+
+        let mut store = self.lock_items()?;
+        for (key, value) in incoming {
+            match on_conflict {
+                OnConflict::Overwrite => {
+                    store.insert(key, value);
+                }
+                OnConflict::Keep => {
+                    store.entry(key).or_insert(value);
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Checks if a key exists in the store.
     ///
     /// # Errors
     ///
     /// Returns `MapError::LockError` if the internal lock cannot be acquired.
     pub fn contains_key(&self, key: &K) -> Result<bool, MapError> {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let store = self.lock_items()?;
         Ok(store.contains_key(key))
     }
 
+    /// Checks if a key exists and was stored under the trait type `T`.
+    ///
+    /// Returns `Ok(false)` if the key is missing or was stored under a
+    /// different trait, so callers can pre-check compatibility before
+    /// calling `with_trait`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn contains_trait<T>(&self, key: &K) -> Result<bool, MapError>
+    where
+        T: ?Sized + Any + Send + Sync + 'static,
+    {
+        let store = self.lock_items()?;
+        Ok(store
+            .get(key)
+            .is_some_and(|value| value.trait_type_id == TypeId::of::<T>()))
+    }
+
+    /// Checks if a key exists and holds a value of the concrete type `V`.
+    ///
+    /// Returns `Ok(false)` if the key is missing or holds a different
+    /// concrete type, so callers can pre-check compatibility before
+    /// calling `with` or `with_mut`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn contains_concrete<V: 'static>(&self, key: &K) -> Result<bool, MapError> {
+        let store = self.lock_items()?;
+        Ok(store
+            .get(key)
+            .is_some_and(|value| value.concrete_type_id == TypeId::of::<V>()))
+    }
+
     /// Gets all keys in the store.
     ///
     /// # Errors
@@ -206,17 +625,33 @@ where
     where
         K: Clone,
     {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let store = self.lock_items()?;
         Ok(store.keys().cloned().collect())
     }
 
+    /// Gets all keys in the store, sorted.
+    ///
+    /// Useful for deterministic output in logs and golden tests.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn keys_ordered(&self) -> Result<Vec<K>, MapError>
+    where
+        K: Ord,
+    {
+        let mut keys = self.keys()?;
+        keys.sort();
+        Ok(keys)
+    }
+
     /// Gets the number of items in the store.
     ///
     /// # Errors
     ///
     /// Returns `MapError::LockError` if the internal lock cannot be acquired.
     pub fn len(&self) -> Result<usize, MapError> {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let store = self.lock_items()?;
         Ok(store.len())
     }
 
@@ -226,7 +661,7 @@ where
     ///
     /// Returns `MapError::LockError` if the internal lock cannot be acquired.
     pub fn is_empty(&self) -> Result<bool, MapError> {
-        let store = self.items.lock().map_err(|_| MapError::LockError)?;
+        let store = self.lock_items()?;
         Ok(store.is_empty())
     }
 }
@@ -240,6 +675,25 @@ where
     }
 }
 
+/// Formats a one-line summary such as `TraitTypeMap(3 entries)`.
+///
+/// Formatting never blocks or panics: if the internal lock is held this prints
+/// `TraitTypeMap(<busy>)`, and if it is poisoned, `TraitTypeMap(<poisoned>)`.
+impl<K> fmt::Display for TraitTypeMap<K>
+where
+    K: Clone + Eq + Hash + Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match lock::peek(&self.items) {
+            Ok(store) => match store.len() {
+                1 => f.write_str("TraitTypeMap(1 entry)"),
+                n => write!(f, "TraitTypeMap({} entries)", n),
+            },
+            Err(placeholder) => write!(f, "TraitTypeMap({})", placeholder),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -269,9 +723,9 @@ mod tests {
         }
     }
 
-    impl Into<Box<dyn Animal>> for Dog {
-        fn into(self) -> Box<dyn Animal> {
-            Box::new(self)
+    impl From<Dog> for Box<dyn Animal> {
+        fn from(value: Dog) -> Self {
+            Box::new(value)
         }
     }
 
@@ -293,9 +747,15 @@ mod tests {
         }
     }
 
-    impl Into<Box<dyn Animal>> for Cat {
-        fn into(self) -> Box<dyn Animal> {
-            Box::new(self)
+    impl From<Cat> for Box<dyn Animal> {
+        fn from(value: Cat) -> Self {
+            Box::new(value)
+        }
+    }
+
+    impl From<Cat> for Box<dyn Debug + Send + Sync> {
+        fn from(value: Cat) -> Self {
+            Box::new(value)
         }
     }
 
@@ -405,7 +865,10 @@ mod tests {
 
         // Try to access as wrong type
         match store.with::<Cat, _, _>(&"pet".to_string(), |_| {}) {
-            Err(MapError::TypeMismatch) => (), // Expected
+            Err(MapError::TypeMismatch { expected, found }) => {
+                assert!(expected.ends_with("Cat"));
+                assert!(found.ends_with("Dog"));
+            }
             _ => panic!("Should have gotten type mismatch error"),
         }
 
@@ -414,6 +877,20 @@ mod tests {
             Err(MapError::KeyNotFound(_)) => (), // Expected
             _ => panic!("Should have gotten key not found error"),
         }
+
+        // Try to access through a trait it wasn't stored under
+        match store
+            .with_trait::<dyn std::fmt::Debug + Send + Sync, _, _>(&"pet".to_string(), |_| {})
+        {
+            Err(MapError::WrongTrait { expected, found }) => {
+                assert_eq!(
+                    expected,
+                    std::any::type_name::<dyn std::fmt::Debug + Send + Sync>()
+                );
+                assert!(found.ends_with("Animal"));
+            }
+            _ => panic!("Should have gotten wrong trait error"),
+        }
     }
 
     #[test]
@@ -482,6 +959,10 @@ mod tests {
 
         assert!(!store.is_empty()?);
         assert_eq!(store.len()?, 2);
+        assert_eq!(store.to_string(), "TraitTypeMap(2 entries)");
+        // Formatting from inside a closure reports the held lock instead of panicking
+        let inside = store.with(&"cat".to_string(), |_: &Cat| store.to_string())?;
+        assert_eq!(inside, "TraitTypeMap(<busy>)");
 
         let mut keys = store.keys()?;
         keys.sort();
@@ -489,4 +970,224 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_contains_trait_and_concrete() -> Result<(), MapError> {
+        trait Named: Any + Send + Sync {}
+
+        let store = TraitTypeMap::<String>::new();
+
+        store.set_trait::<dyn Animal, _>(
+            "dog".to_string(),
+            Dog {
+                name: "Rover".to_string(),
+                breed: "Golden Retriever".to_string(),
+            },
+        )?;
+
+        assert!(store.contains_trait::<dyn Animal>(&"dog".to_string())?);
+        assert!(!store.contains_trait::<dyn Named>(&"dog".to_string())?);
+        assert!(!store.contains_trait::<dyn Animal>(&"missing".to_string())?);
+
+        assert!(store.contains_concrete::<Dog>(&"dog".to_string())?);
+        assert!(!store.contains_concrete::<Cat>(&"dog".to_string())?);
+        assert!(!store.contains_concrete::<Dog>(&"missing".to_string())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concrete_and_trait_access_agree() -> Result<(), MapError> {
+        let store = TraitTypeMap::<String>::new();
+        let dog = Dog {
+            name: "Rex".to_string(),
+            breed: "Collie".to_string(),
+        };
+        store.set_trait::<dyn Animal, _>("pet".to_string(), dog)?;
+
+        let via_concrete = store.with(&"pet".to_string(), |d: &Dog| d.make_sound())?;
+        let via_trait =
+            store.with_trait::<dyn Animal, _, _>(&"pet".to_string(), |a| a.make_sound())?;
+        assert_eq!(via_concrete, via_trait);
+        Ok(())
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "did not box the value itself")]
+    fn test_incoherent_into_is_caught() {
+        #[derive(Clone)]
+        struct Impostor(u8);
+
+        // A buggy conversion that boxes a different concrete type
+        impl From<Impostor> for Box<dyn Animal> {
+            fn from(value: Impostor) -> Self {
+                Box::new(Dog {
+                    name: value.0.to_string(),
+                    breed: String::new(),
+                })
+            }
+        }
+
+        let store = TraitTypeMap::<String>::new();
+        let _ = store.set_trait::<dyn Animal, _>("pet".to_string(), Impostor(1));
+    }
+
+    #[test]
+    fn test_remove_returning() -> Result<(), MapError> {
+        let store = TraitTypeMap::<String>::new();
+        let dog = Dog {
+            name: "Rex".to_string(),
+            breed: "Collie".to_string(),
+        };
+        store.set_trait::<dyn Animal, _>("dog".to_string(), dog.clone())?;
+        store.set_trait::<dyn Animal, _>("cat".to_string(), dog)?;
+
+        // A mismatched conversion hands the entry back
+        let entry = store.remove_returning(&"dog".to_string())?.unwrap();
+        let entry = entry.into_concrete::<Cat>().unwrap_err();
+        let entry = entry.into_trait::<dyn Any>().unwrap_err();
+        let dog = entry.into_concrete::<Dog>().unwrap();
+        assert_eq!(dog.breed, "Collie");
+        assert!(!store.contains_key(&"dog".to_string())?);
+
+        let animal = store
+            .remove_returning(&"cat".to_string())?
+            .unwrap()
+            .into_trait::<dyn Animal>()
+            .unwrap();
+        assert_eq!(animal.make_sound(), "Rex says: Woof!");
+
+        assert!(store.remove_returning(&"cat".to_string())?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_keys_ordered() -> Result<(), MapError> {
+        let store = TraitTypeMap::<u32>::new();
+        for key in [3, 1, 2] {
+            let dog = Dog {
+                name: key.to_string(),
+                breed: "Mutt".to_string(),
+            };
+            store.set_trait::<dyn Animal, _>(key, dog)?;
+        }
+        assert_eq!(store.keys_ordered()?, vec![1, 2, 3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_absorb() -> Result<(), MapError> {
+        let dog = |name: &str| Dog {
+            name: name.to_string(),
+            breed: "Mutt".to_string(),
+        };
+        let cat = |name: &str| Cat {
+            name: name.to_string(),
+            lives: 9,
+        };
+        let bundle = || -> Result<TraitTypeMap<String>, MapError> {
+            let other = TraitTypeMap::<String>::new();
+            other.set_trait::<dyn Animal, _>("shared".to_string(), cat("Tom"))?;
+            other.set_trait::<dyn Animal, _>("new".to_string(), cat("Felix"))?;
+            Ok(other)
+        };
+
+        let host = TraitTypeMap::<String>::new();
+        host.set_trait::<dyn Animal, _>("shared".to_string(), dog("Rex"))?;
+        host.set_trait::<dyn Animal, _>("own".to_string(), dog("Fido"))?;
+
+        // Keep leaves the overlapping key alone but takes the disjoint one
+        let other = bundle()?;
+        host.absorb(other, OnConflict::Keep)?;
+        assert_eq!(host.len()?, 3);
+        assert_eq!(
+            host.with(&"shared".to_string(), |d: &Dog| d.name.clone())?,
+            "Rex"
+        );
+        assert_eq!(host.with(&"new".to_string(), |c: &Cat| c.lives)?, 9);
+
+        // Overwrite replaces it, concrete and trait views alike
+        host.absorb(bundle()?, OnConflict::Overwrite)?;
+        assert_eq!(host.len()?, 3);
+        assert_eq!(
+            host.with(&"shared".to_string(), |c: &Cat| c.purr())?,
+            "Tom purrs contentedly"
+        );
+        let sound = host.with_trait::<dyn Animal, _, _>(&"own".to_string(), |a| a.make_sound())?;
+        assert_eq!(sound, "Fido says: Woof!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_trait() -> Result<(), MapError> {
+        let store = TraitTypeMap::<String>::new();
+        let key = "pet".to_string();
+        let dog = Dog {
+            name: "Rex".to_string(),
+            breed: "Mutt".to_string(),
+        };
+        let cat = Cat {
+            name: "Tom".to_string(),
+            lives: 9,
+        };
+
+        assert!(store
+            .replace_trait::<dyn Animal, _>(key.clone(), dog)?
+            .is_none());
+
+        let old = store.replace_trait::<dyn Animal, _>(key.clone(), cat.clone())?;
+        assert_eq!(old.unwrap().make_sound(), "Rex says: Woof!");
+        assert_eq!(store.with(&key, |c: &Cat| c.lives)?, 9);
+
+        // A different trait is refused and the existing entry stays
+        match store.replace_trait::<dyn Debug + Send + Sync, _>(key.clone(), cat) {
+            Err(MapError::WrongTrait { found, .. }) => assert!(found.ends_with("Animal")),
+            _ => panic!("Should have gotten wrong trait error"),
+        }
+        let sound = store.with_trait::<dyn Animal, _, _>(&key, |a| a.make_sound())?;
+        assert_eq!(sound, "Tom says: Meow!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_trait_or() -> Result<(), MapError> {
+        let store = TraitTypeMap::<String>::new();
+        let rex = Dog {
+            name: "Rex".to_string(),
+            breed: "Mutt".to_string(),
+        };
+        let tom = Cat {
+            name: "Tom".to_string(),
+            lives: 9,
+        };
+        store.set_trait::<dyn Animal, _>("dog".to_string(), rex)?;
+        store.set_trait::<dyn Debug + Send + Sync, _>("cat".to_string(), tom)?;
+
+        let describe = |key: &str| {
+            store.with_trait_or::<dyn Animal, dyn Debug + Send + Sync, _, _, _>(
+                &key.to_string(),
+                |a| a.make_sound(),
+                |d| format!("{:?}", d),
+            )
+        };
+        assert_eq!(describe("dog")?, "Rex says: Woof!");
+        assert!(describe("cat")?.starts_with("Cat"));
+        assert!(matches!(describe("bird"), Err(MapError::KeyNotFound(_))));
+
+        // Neither trait matches
+        match store
+            .with_trait_or::<dyn Debug + Send + Sync, dyn fmt::Display + Send + Sync, _, _, _>(
+                &"dog".to_string(),
+                |_| (),
+                |_| (),
+            ) {
+            Err(MapError::WrongTrait { expected, found }) => {
+                assert_eq!(expected, std::any::type_name::<dyn Debug + Send + Sync>());
+                assert!(found.ends_with("Animal"));
+            }
+            _ => panic!("Should have gotten wrong trait error"),
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,161 @@
+// src/typed_view.rs
+use std::any::Any;
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::marker::PhantomData;
+
+use crate::error::MapError;
+use crate::map::TypeMap;
+
+/// A handle onto a [`TypeMap`] that is statically pinned to one value type.
+///
+/// `TypedView` shares the same underlying storage as the map it was created
+/// from, but every operation is fixed to type `T`. Hand one to a component to
+/// give it access to its own values without exposing the rest of the map.
+///
+/// Created with [`TypeMap::typed_view`].
+///
+/// # Examples
+///
+/// ```
+/// use sovran_typemap::{TypeMap, MapError};
+///
+/// #[derive(Clone, Debug, PartialEq)]
+/// struct Session { user: String }
+///
+/// fn main() -> Result<(), MapError> {
+///     let store = TypeMap::<String>::new();
+///     store.set("config".to_string(), 42u32)?;
+///
+///     let sessions = store.typed_view::<Session>();
+///     sessions.set("s1".to_string(), Session { user: "ann".to_string() })?;
+///
+///     // The view reads and writes the shared map...
+///     assert_eq!(store.get::<Session>(&"s1".to_string())?.user, "ann");
+///
+///     // ...but only ever as `Session`
+///     assert!(matches!(sessions.get(&"config".to_string()), Err(MapError::TypeMismatch { .. })));
+///     Ok(())
+/// }
+/// ```
+pub struct TypedView<K, T> {
+    map: TypeMap<K>,
+    // fn() -> T keeps the view Send + Sync regardless of T's auto traits
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<K, T> TypedView<K, T>
+where
+    K: Clone + Eq + Hash + Debug,
+    T: 'static + Any + Send + Sync,
+{
+    pub(crate) fn new(map: TypeMap<K>) -> Self {
+        Self {
+            map,
+            _type: PhantomData,
+        }
+    }
+
+    /// Stores a value of the view's type.
+    ///
+    /// An existing entry is only replaced if it already holds a `T`, so a view
+    /// can't overwrite values of the types it is meant to hide.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::Locked` if the key has been locked with `lock_key`
+    /// - Returns `MapError::TypeMismatch` if the key holds a different type
+    pub fn set(&self, key: K, value: T) -> Result<(), MapError> {
+        self.map.set_same_type(key, value)
+    }
+
+    /// Retrieves a clone of a value of the view's type.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the key holds a different type
+    pub fn get(&self, key: &K) -> Result<T, MapError>
+    where
+        T: Clone,
+    {
+        self.map.get::<T>(key)
+    }
+
+    /// Accesses a value of the view's type with a read-only closure.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the key holds a different type
+    pub fn with<F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        self.map.with(key, f)
+    }
+
+    /// Accesses a value of the view's type with a read-write closure.
+    ///
+    /// # Errors
+    ///
+    /// - Returns `MapError::LockError` if the internal lock cannot be acquired
+    /// - Returns `MapError::KeyNotFound` if the key doesn't exist in the store
+    /// - Returns `MapError::TypeMismatch` if the key holds a different type
+    pub fn with_mut<F, R>(&self, key: &K, f: F) -> Result<R, MapError>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.map.with_mut(key, f)
+    }
+
+    /// Checks if a key exists and holds a value of the view's type.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn contains_key(&self, key: &K) -> Result<bool, MapError> {
+        match self.map.with(key, |_: &T| ()) {
+            Ok(()) => Ok(true),
+            Err(MapError::KeyNotFound(_)) | Err(MapError::TypeMismatch { .. }) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Gets clones of all values of the view's type.
+    ///
+    /// # Errors
+    ///
+    /// Returns `MapError::LockError` if the internal lock cannot be acquired.
+    pub fn values(&self) -> Result<Vec<T>, MapError>
+    where
+        T: Clone,
+    {
+        self.map.values::<T>()
+    }
+}
+
+impl<K: Clone, T> Clone for TypedView<K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            map: self.map.clone(),
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<K, T> Debug for TypedView<K, T>
+where
+    K: Clone + Eq + Hash + Debug,
+    T: 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TypedView")
+            .field("type", &std::any::type_name::<T>())
+            .field("count", &self.map.peek_count_of_type::<T>())
+            .finish()
+    }
+}
@@ -0,0 +1,51 @@
+// src/visitor.rs
+use std::any::Any;
+
+/// A stateful visitor over the entries of a [`TypeMap`](crate::TypeMap).
+///
+/// [`TypeMap::accept`](crate::TypeMap::accept) walks every entry under one lock
+/// and hands each key and type-erased value to `visit_any`, which downcasts
+/// against the types it knows about. Unlike a closure passed to
+/// [`for_each_value`](crate::TypeMap::for_each_value), a visitor is a named type
+/// that can carry its own state and be reused across maps.
+///
+/// # Examples
+///
+/// ```
+/// use std::any::Any;
+/// use sovran_typemap::{MapError, TypeMap, Visitor};
+///
+/// #[derive(Default)]
+/// struct Totals { ints: i64, text_len: usize, other: Vec<String> }
+///
+/// impl Visitor<String> for Totals {
+///     fn visit_any(&mut self, key: &String, value: &dyn Any) {
+///         if let Some(n) = value.downcast_ref::<i64>() {
+///             self.ints += n;
+///         } else if let Some(s) = value.downcast_ref::<String>() {
+///             self.text_len += s.len();
+///         } else {
+///             self.other.push(key.clone());
+///         }
+///     }
+/// }
+///
+/// fn main() -> Result<(), MapError> {
+///     let store: TypeMap<String> = TypeMap::new();
+///     store.set("a".to_string(), 2i64)?;
+///     store.set("b".to_string(), 3i64)?;
+///     store.set("name".to_string(), "hello".to_string())?;
+///     store.set("flag".to_string(), true)?;
+///
+///     let mut totals = Totals::default();
+///     store.accept(&mut totals)?;
+///     assert_eq!(totals.ints, 5);
+///     assert_eq!(totals.text_len, 5);
+///     assert_eq!(totals.other, vec!["flag".to_string()]);
+///     Ok(())
+/// }
+/// ```
+pub trait Visitor<K> {
+    /// Visits one entry; `value` can be downcast with `downcast_ref`.
+    fn visit_any(&mut self, key: &K, value: &dyn Any);
+}
@@ -1,4 +1,4 @@
-use sovran_typemap::{MapError, TypeMap};
+use sovran_typemap::{AnyValue, KeyInterner, MapError, TypeMap, Visitor};
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::thread;
@@ -63,7 +63,7 @@ fn test_type_safety() {
 
     // Try to get it as the wrong type
     let result = store.with(&"key".to_string(), |val: &i32| *val);
-    assert!(matches!(result, Err(MapError::TypeMismatch)));
+    assert!(matches!(result, Err(MapError::TypeMismatch { .. })));
 
     // Get it as the correct type
     let value = store
@@ -81,7 +81,7 @@ fn test_multiple_types() {
     store
         .set("string".to_string(), "hello".to_string())
         .unwrap();
-    store.set("float".to_string(), 3.14f64).unwrap();
+    store.set("float".to_string(), 2.5f64).unwrap();
 
     // Get them back
     let int_val = store.with(&"int".to_string(), |val: &i32| *val).unwrap();
@@ -93,7 +93,7 @@ fn test_multiple_types() {
     assert_eq!(string_val, "hello");
 
     let float_val = store.with(&"float".to_string(), |val: &f64| *val).unwrap();
-    assert_eq!(float_val, 3.14);
+    assert_eq!(float_val, 2.5);
 
     // Get the keys
     let keys = store.keys().unwrap();
@@ -148,7 +148,7 @@ fn test_error_handling() {
     // Store a value and try to get it with the wrong type
     store.set("key".to_string(), 42i32).unwrap();
     let result = store.with(&"key".to_string(), |val: &String| val.clone());
-    assert!(matches!(result, Err(MapError::TypeMismatch)));
+    assert!(matches!(result, Err(MapError::TypeMismatch { .. })));
 
     // Try to modify a non-existent key
     let result = store.with_mut(&"nonexistent".to_string(), |val: &mut i32| {
@@ -199,7 +199,10 @@ fn test_error_display() {
     // Test error.rs Display implementation
     let lock_error = MapError::LockError;
     let key_not_found = MapError::KeyNotFound("someKey".into());
-    let type_mismatch = MapError::TypeMismatch;
+    let type_mismatch = MapError::TypeMismatch {
+        expected: "Config",
+        found: "alloc::string::String",
+    };
 
     assert_eq!(format!("{}", lock_error), "Failed to acquire lock");
     assert_eq!(
@@ -208,7 +211,53 @@ fn test_error_display() {
     );
     assert_eq!(
         format!("{}", type_mismatch),
-        "Type mismatch for the requested key"
+        "Type mismatch: expected Config, found alloc::string::String"
+    );
+
+    assert_eq!(
+        format!("{}", MapError::Timeout),
+        "Timed out waiting to acquire lock"
+    );
+
+    assert_eq!(
+        format!("{}", MapError::MissingTypes(vec!["Config", "Logger"])),
+        "Missing required types: Config, Logger"
+    );
+
+    assert_eq!(
+        format!("{}", MapError::DuplicateKey("\"a\"".into())),
+        "Duplicate key in request: \"a\""
+    );
+
+    assert_eq!(
+        format!("{}", MapError::BorrowConflict),
+        "Value is already borrowed"
+    );
+
+    assert_eq!(
+        format!("{}", MapError::Locked("\"auth\"".into())),
+        "Key is locked against modification: \"auth\""
+    );
+
+    assert_eq!(
+        format!(
+            "{}",
+            MapError::WrongTrait {
+                expected: "dyn Plugin",
+                found: "dyn Handler"
+            }
+        ),
+        "Entry stored as dyn Handler but requested as dyn Plugin"
+    );
+
+    assert_eq!(
+        format!(
+            "{}",
+            MapError::CircularDependency {
+                chain: vec!["Auth", "Session", "Auth"]
+            }
+        ),
+        "Circular dependency: Auth -> Session -> Auth"
     );
 
     // Test Debug implementation
@@ -266,7 +315,21 @@ fn test_with_mut_type_mismatch() {
         panic!("Should not reach here");
     });
 
-    assert!(matches!(result, Err(MapError::TypeMismatch)));
+    assert!(matches!(
+        result,
+        Err(MapError::TypeMismatch { expected: "i32", found })
+            if found == std::any::type_name::<String>()
+    ));
+
+    // Type-erased values report an unknown stored type
+    store.set_boxed("boxed".to_string(), Box::new(1u8)).unwrap();
+    assert!(matches!(
+        store.get::<i32>(&"boxed".to_string()),
+        Err(MapError::TypeMismatch {
+            expected: "i32",
+            found: "<unknown>"
+        })
+    ));
 }
 
 #[test]
@@ -281,3 +344,2089 @@ fn test_default_implementation() {
     store.set("test".to_string(), 42).unwrap();
     assert_eq!(store.get::<i32>(&"test".to_string()).unwrap(), 42);
 }
+
+#[test]
+fn test_lru_capacity_evicts_least_recently_used() {
+    let store: TypeMap<String> = TypeMap::new().with_lru_capacity(3);
+    assert_eq!(store.lru_capacity(), Some(3));
+
+    store.set("a".to_string(), 1i32).unwrap();
+    store.set("b".to_string(), "two".to_string()).unwrap();
+    store.set("c".to_string(), 3.0f64).unwrap();
+
+    // Reading "a" and mutating "b" leaves "c" as the least recently used
+    assert_eq!(store.get::<i32>(&"a".to_string()).unwrap(), 1);
+    store
+        .with_mut(&"b".to_string(), |s: &mut String| s.push('!'))
+        .unwrap();
+
+    let evicted = store.set_evicting("d".to_string(), 4u8).unwrap();
+    let (key, value) = evicted.expect("an entry should have been evicted");
+    assert_eq!(key, "c");
+    assert_eq!(value.downcast_ref::<f64>(), Some(&3.0));
+
+    assert_eq!(store.len().unwrap(), 3);
+    assert!(!store.contains_key(&"c".to_string()).unwrap());
+
+    // Overwriting an existing key never evicts
+    assert!(store
+        .set_evicting("a".to_string(), 10i32)
+        .unwrap()
+        .is_none());
+    assert_eq!(store.len().unwrap(), 3);
+
+    // "b" is now the oldest entry
+    store.set("e".to_string(), 5i32).unwrap();
+    assert!(!store.contains_key(&"b".to_string()).unwrap());
+    assert!(store.contains_key(&"a".to_string()).unwrap());
+}
+
+#[test]
+fn test_lru_eviction_matches_access_order() {
+    // Replays a pseudo-random mix of writes, reads and removals against a model
+    // that evicts the key with the oldest access
+    let store: TypeMap<u32> = TypeMap::new().with_lru_capacity(50);
+    store.set(0, 0u32).unwrap();
+    store.lock_key(&0).unwrap();
+
+    let mut model: HashMap<u32, u64> = HashMap::from([(0, 0)]);
+    let mut tick = 0u64;
+    let mut seed = 0x2545_f491u32;
+    for _ in 0..5000 {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        let key = 1 + (seed >> 16) % 120;
+        tick += 1;
+        match (seed >> 8) % 4 {
+            0 | 1 => {
+                let evicted = store.set_evicting(key, key).unwrap().map(|(k, _)| k);
+                let expected = if model.len() >= 50 && !model.contains_key(&key) {
+                    let (&oldest, _) = model
+                        .iter()
+                        .filter(|(&k, _)| k != 0)
+                        .min_by_key(|(_, &t)| t)
+                        .unwrap();
+                    model.remove(&oldest);
+                    Some(oldest)
+                } else {
+                    None
+                };
+                assert_eq!(evicted, expected);
+                model.insert(key, tick);
+            }
+            2 => {
+                if store.get::<u32>(&key).is_ok() {
+                    model.insert(key, tick);
+                }
+            }
+            _ => {
+                store.remove(&key).unwrap();
+                model.remove(&key);
+            }
+        }
+    }
+
+    let mut keys = store.keys().unwrap();
+    keys.sort();
+    let mut expected: Vec<u32> = model.keys().copied().collect();
+    expected.sort();
+    assert_eq!(keys, expected);
+}
+
+#[test]
+fn test_unbounded_map_never_evicts() {
+    let store: TypeMap<i32> = TypeMap::new();
+    assert_eq!(store.lru_capacity(), None);
+
+    for i in 0..100 {
+        assert!(store.set_evicting(i, i).unwrap().is_none());
+    }
+    assert_eq!(store.len().unwrap(), 100);
+}
+
+#[test]
+fn test_get_key_value_returns_stored_key() {
+    #[derive(Clone, Debug)]
+    struct CaseInsensitive(String);
+
+    impl PartialEq for CaseInsensitive {
+        fn eq(&self, other: &Self) -> bool {
+            self.0.eq_ignore_ascii_case(&other.0)
+        }
+    }
+
+    impl Eq for CaseInsensitive {}
+
+    impl std::hash::Hash for CaseInsensitive {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.0.to_ascii_lowercase().hash(state);
+        }
+    }
+
+    let store: TypeMap<CaseInsensitive> = TypeMap::new();
+    store
+        .set(CaseInsensitive("Content-Type".to_string()), "text/plain")
+        .unwrap();
+
+    let (key, value) = store
+        .get_key_value::<&str>(&CaseInsensitive("content-type".to_string()))
+        .unwrap();
+    assert_eq!(key.0, "Content-Type");
+    assert_eq!(value, "text/plain");
+
+    let missing = store.get_key_value::<&str>(&CaseInsensitive("accept".to_string()));
+    assert!(matches!(missing, Err(MapError::KeyNotFound(_))));
+
+    let mismatch = store.get_key_value::<i32>(&CaseInsensitive("CONTENT-TYPE".to_string()));
+    assert!(matches!(mismatch, Err(MapError::TypeMismatch { .. })));
+}
+
+#[test]
+fn test_extend_bulk_insert() {
+    let mut store: TypeMap<u32> = TypeMap::new();
+
+    store.extend((0..1000u32).map(|i| (i, i * 2)));
+
+    assert_eq!(store.len().unwrap(), 1000);
+    assert_eq!(store.get::<u32>(&500).unwrap(), 1000);
+}
+
+#[test]
+fn test_extend_skips_locked_keys_and_never_panics() {
+    let mut store: TypeMap<u32> = TypeMap::new();
+    store.set(7, 0u32).unwrap();
+    store.lock_key(&7).unwrap();
+
+    store.extend((0..1000u32).map(|i| (i, i * 2)));
+
+    // Every key but the locked one was written
+    assert_eq!(store.len().unwrap(), 1000);
+    assert_eq!(store.get::<u32>(&7).unwrap(), 0);
+    assert_eq!(store.get::<u32>(&999).unwrap(), 1998);
+
+    // A poisoned lock is read through rather than panicking
+    let poisoner = store.clone();
+    let result = std::thread::spawn(move || {
+        let _ = poisoner.with_mut(&1, |_: &mut u32| panic!("poison"));
+    })
+    .join();
+    assert!(result.is_err());
+    store.extend([(1000u32, 1u32)]);
+    assert_eq!(store.len_or_zero(), 1001);
+}
+
+#[test]
+fn test_set_all_respects_lru_capacity() {
+    let store: TypeMap<u32> = TypeMap::new().with_lru_capacity(10);
+
+    store
+        .set_all((0..25u32).map(|i| (i, format!("value {}", i))))
+        .unwrap();
+
+    assert_eq!(store.len().unwrap(), 10);
+    let mut keys = store.keys().unwrap();
+    keys.sort();
+    assert_eq!(keys, (15..25).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_settings_compose() {
+    let store: TypeMap<u32> = TypeMap::new()
+        .with_lru_capacity(2)
+        .with_auto_shrink(0.25)
+        .with_lock_timeout(std::time::Duration::from_millis(10));
+
+    assert_eq!(store.lru_capacity(), Some(2));
+    assert_eq!(store.auto_shrink_threshold(), Some(0.25));
+    assert_eq!(
+        store.lock_timeout(),
+        Some(std::time::Duration::from_millis(10))
+    );
+
+    // Clones made afterwards carry every setting
+    let shared = store.clone();
+    shared.set_all((0..5u32).map(|i| (i, i))).unwrap();
+    assert_eq!(store.len().unwrap(), 2);
+    assert_eq!(shared.lock_timeout(), store.lock_timeout());
+}
+
+#[test]
+fn test_with_any_multiple_downcasts() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("a".to_string(), 7i64).unwrap();
+    store.set("b".to_string(), "11".to_string()).unwrap();
+    store.set("c".to_string(), vec![1u8]).unwrap();
+
+    let as_number = |value: &dyn std::any::Any| {
+        if let Some(n) = value.downcast_ref::<i64>() {
+            Some(*n)
+        } else {
+            value.downcast_ref::<String>().and_then(|s| s.parse().ok())
+        }
+    };
+
+    assert_eq!(
+        store.with_any(&"a".to_string(), as_number).unwrap(),
+        Some(7)
+    );
+    assert_eq!(
+        store.with_any(&"b".to_string(), as_number).unwrap(),
+        Some(11)
+    );
+    assert_eq!(store.with_any(&"c".to_string(), as_number).unwrap(), None);
+    assert!(matches!(
+        store.with_any(&"missing".to_string(), as_number),
+        Err(MapError::KeyNotFound(_))
+    ));
+}
+
+#[test]
+fn test_with_timeout() {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("value".to_string(), 5i32).unwrap();
+
+    // Uncontended access succeeds
+    let value = store
+        .with_timeout(
+            &"value".to_string(),
+            Duration::from_millis(10),
+            |v: &i32| *v,
+        )
+        .unwrap();
+    assert_eq!(value, 5);
+
+    // Hold the lock in another thread and make sure we give up
+    let holder = store.clone();
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let handle = thread::spawn(move || {
+        holder
+            .with_mut(&"value".to_string(), |_: &mut i32| {
+                locked_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+            })
+            .unwrap();
+    });
+
+    locked_rx.recv().unwrap();
+    let result = store.with_timeout(
+        &"value".to_string(),
+        Duration::from_millis(20),
+        |v: &i32| *v,
+    );
+    assert!(matches!(result, Err(MapError::Timeout)));
+
+    release_tx.send(()).unwrap();
+    handle.join().unwrap();
+
+    // Errors other than contention are reported as usual
+    let result = store.with_timeout(
+        &"missing".to_string(),
+        Duration::from_millis(10),
+        |v: &i32| *v,
+    );
+    assert!(matches!(result, Err(MapError::KeyNotFound(_))));
+}
+
+#[test]
+fn test_get_opt() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("name".to_string(), "sovran".to_string()).unwrap();
+
+    assert_eq!(
+        store.get_opt::<String>(&"name".to_string()),
+        Some("sovran".to_string())
+    );
+    assert_eq!(store.get_opt::<i32>(&"name".to_string()), None);
+    assert_eq!(store.get_opt::<String>(&"missing".to_string()), None);
+}
+
+#[test]
+fn test_set_boxed_preserves_runtime_type() {
+    let store: TypeMap<String> = TypeMap::new();
+
+    let values: Vec<(String, Box<dyn std::any::Any + Send + Sync>)> = vec![
+        ("text".to_string(), Box::new("plugin".to_string())),
+        ("count".to_string(), Box::new(3usize)),
+    ];
+    for (key, value) in values {
+        store.set_boxed(key, value).unwrap();
+    }
+
+    assert_eq!(store.get::<String>(&"text".to_string()).unwrap(), "plugin");
+    assert_eq!(store.get::<usize>(&"count".to_string()).unwrap(), 3);
+
+    // The stored type is the boxed value's type, not the Box itself
+    let result = store.with(
+        &"count".to_string(),
+        |_: &Box<dyn std::any::Any + Send + Sync>| (),
+    );
+    assert!(matches!(result, Err(MapError::TypeMismatch { .. })));
+}
+
+#[test]
+fn test_snapshot_is_detached_from_store() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("a".to_string(), 1u32).unwrap();
+    store.set("b".to_string(), 2u32).unwrap();
+    store.set("c".to_string(), "not a u32".to_string()).unwrap();
+
+    let mut snapshot = store.snapshot::<u32>().unwrap();
+    snapshot.sort();
+    assert_eq!(snapshot, vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+
+    // Writers are free to change the store while the snapshot is held
+    store.set("a".to_string(), 100u32).unwrap();
+    store.remove(&"b".to_string()).unwrap();
+    assert_eq!(snapshot, vec![("a".to_string(), 1), ("b".to_string(), 2)]);
+}
+
+#[test]
+fn test_with_mut_diff() {
+    let store: TypeMap<String> = TypeMap::new();
+    store
+        .set("tags".to_string(), vec!["a".to_string()])
+        .unwrap();
+
+    let (len, before, after) = store
+        .with_mut_diff(&"tags".to_string(), |tags: &mut Vec<String>| {
+            tags.push("b".to_string());
+            tags.len()
+        })
+        .unwrap();
+
+    assert_eq!(len, 2);
+    assert_eq!(before, vec!["a".to_string()]);
+    assert_eq!(after, vec!["a".to_string(), "b".to_string()]);
+    assert_eq!(
+        store.get::<Vec<String>>(&"tags".to_string()).unwrap(),
+        after
+    );
+
+    let result = store.with_mut_diff(&"tags".to_string(), |_: &mut String| ());
+    assert!(matches!(result, Err(MapError::TypeMismatch { .. })));
+}
+
+#[test]
+fn test_remove_many_counts_only_present_keys() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("a".to_string(), 1i32).unwrap();
+    store.set("b".to_string(), "two".to_string()).unwrap();
+    store.set("c".to_string(), 3.0f64).unwrap();
+
+    let removed = store
+        .remove_many(vec![
+            "a".to_string(),
+            "missing".to_string(),
+            "c".to_string(),
+            "a".to_string(),
+        ])
+        .unwrap();
+
+    assert_eq!(removed, 2);
+    assert_eq!(store.keys().unwrap(), vec!["b".to_string()]);
+    assert_eq!(store.remove_many(Vec::new()).unwrap(), 0);
+}
+
+#[test]
+fn test_typed_view_is_pinned_to_one_type() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("name".to_string(), "sovran".to_string()).unwrap();
+
+    let numbers = store.typed_view::<i64>();
+    numbers.set("a".to_string(), 1).unwrap();
+    numbers.set("b".to_string(), 2).unwrap();
+
+    // Writes are visible through the shared map
+    assert_eq!(store.get::<i64>(&"a".to_string()).unwrap(), 1);
+    assert_eq!(store.len().unwrap(), 3);
+
+    // The view only sees its own type
+    assert!(numbers.contains_key(&"a".to_string()).unwrap());
+    assert!(!numbers.contains_key(&"name".to_string()).unwrap());
+    assert!(!numbers.contains_key(&"missing".to_string()).unwrap());
+    assert!(matches!(
+        numbers.get(&"name".to_string()),
+        Err(MapError::TypeMismatch { .. })
+    ));
+
+    let mut values = numbers.values().unwrap();
+    values.sort();
+    assert_eq!(values, vec![1, 2]);
+
+    // Clones of the view share the same storage
+    let other = numbers.clone();
+    other.with_mut(&"b".to_string(), |b| *b *= 10).unwrap();
+    assert_eq!(numbers.with(&"b".to_string(), |b| *b).unwrap(), 20);
+}
+
+#[test]
+fn test_typed_view_set_refuses_other_types() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("config".to_string(), 42u32).unwrap();
+    store
+        .set("secret".to_string(), "hunter2".to_string())
+        .unwrap();
+
+    let numbers = store.typed_view::<i64>();
+    assert!(matches!(
+        numbers.set("config".to_string(), 7),
+        Err(MapError::TypeMismatch { .. })
+    ));
+    assert_eq!(store.get::<u32>(&"config".to_string()).unwrap(), 42);
+
+    // Entries of the view's own type are still replaced
+    numbers.set("a".to_string(), 1).unwrap();
+    numbers.set("a".to_string(), 2).unwrap();
+    assert_eq!(numbers.get(&"a".to_string()).unwrap(), 2);
+
+    // Debug shows the view's type and count, not the rest of the map
+    let debug = format!("{:?}", numbers);
+    assert!(debug.contains("i64"));
+    assert!(debug.contains("count: Some(1)"));
+    assert!(!debug.contains("hunter2"));
+}
+
+#[test]
+fn test_lenient_len_recovers_from_poisoned_lock() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("value".to_string(), 1i32).unwrap();
+
+    // Panic while the lock is held to poison it
+    let poisoner = store.clone();
+    let result = std::thread::spawn(move || {
+        let _ = poisoner.with_mut(&"value".to_string(), |_: &mut i32| panic!("poison"));
+    })
+    .join();
+    assert!(result.is_err());
+
+    assert!(matches!(store.len(), Err(MapError::LockError)));
+    assert_eq!(store.len_or_zero(), 1);
+    assert!(!store.is_empty_lenient());
+}
+
+#[test]
+fn test_with_each_mut_validates_before_mutating() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("a".to_string(), 1i32).unwrap();
+    store.set("b".to_string(), 2i32).unwrap();
+    store.set("name".to_string(), "text".to_string()).unwrap();
+
+    let mut visited = Vec::new();
+    store
+        .with_each_mut(&["b".to_string(), "a".to_string()], |key, v: &mut i32| {
+            visited.push(key.clone());
+            *v *= 10;
+        })
+        .unwrap();
+    assert_eq!(visited, vec!["b".to_string(), "a".to_string()]);
+    assert_eq!(store.get::<i32>(&"a".to_string()).unwrap(), 10);
+    assert_eq!(store.get::<i32>(&"b".to_string()).unwrap(), 20);
+
+    // Each failure leaves every value untouched
+    let bump = |_: &String, v: &mut i32| *v += 1;
+    assert!(matches!(
+        store.with_each_mut(&["a".to_string(), "a".to_string()], bump),
+        Err(MapError::DuplicateKey(_))
+    ));
+    assert!(matches!(
+        store.with_each_mut(&["a".to_string(), "missing".to_string()], bump),
+        Err(MapError::KeyNotFound(_))
+    ));
+    assert!(matches!(
+        store.with_each_mut(&["a".to_string(), "name".to_string()], bump),
+        Err(MapError::TypeMismatch { .. })
+    ));
+    assert_eq!(store.get::<i32>(&"a".to_string()).unwrap(), 10);
+}
+
+#[test]
+fn test_share_returns_handle_over_same_data() {
+    let store: TypeMap<String> = TypeMap::new();
+    let shared = store.share();
+
+    shared.set("count".to_string(), 1u32).unwrap();
+    store
+        .with_mut(&"count".to_string(), |c: &mut u32| *c += 1)
+        .unwrap();
+
+    assert_eq!(shared.get::<u32>(&"count".to_string()).unwrap(), 2);
+    assert!(store.remove(&"count".to_string()).unwrap());
+    assert!(shared.is_empty().unwrap());
+}
+
+#[test]
+fn test_set_into_stores_converted_type() {
+    let store: TypeMap<String> = TypeMap::new();
+    store
+        .set_into::<String, _>("name".to_string(), "literal")
+        .unwrap();
+    store
+        .set_into::<Vec<u8>, _>("bytes".to_string(), "abc")
+        .unwrap();
+
+    assert_eq!(store.get::<String>(&"name".to_string()).unwrap(), "literal");
+    assert_eq!(
+        store.get::<Vec<u8>>(&"bytes".to_string()).unwrap(),
+        b"abc".to_vec()
+    );
+    // The source type is not what gets stored
+    assert!(matches!(
+        store.with(&"name".to_string(), |_: &&str| ()),
+        Err(MapError::TypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_modify_or_default_counts_from_zero() {
+    let store: TypeMap<String> = TypeMap::new();
+
+    for _ in 0..3 {
+        store
+            .modify_or_default("hits".to_string(), |n: &mut u64| *n += 1)
+            .unwrap();
+    }
+    assert_eq!(store.get::<u64>(&"hits".to_string()).unwrap(), 3);
+
+    let len = store
+        .modify_or_default("log".to_string(), |v: &mut Vec<String>| {
+            v.push("start".to_string());
+            v.len()
+        })
+        .unwrap();
+    assert_eq!(len, 1);
+
+    // An existing value of another type is left alone
+    assert!(matches!(
+        store.modify_or_default("hits".to_string(), |s: &mut String| s.push('x')),
+        Err(MapError::TypeMismatch { .. })
+    ));
+    assert_eq!(store.get::<u64>(&"hits".to_string()).unwrap(), 3);
+}
+
+#[test]
+fn test_count_keys_with_mixed_keys() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("session:1".to_string(), 1u32).unwrap();
+    store
+        .set("session:2".to_string(), "two".to_string())
+        .unwrap();
+    store.set("session:3".to_string(), vec![3u8]).unwrap();
+    store.set("user:1".to_string(), 1u32).unwrap();
+    store.set("sessions".to_string(), 0u32).unwrap();
+
+    assert_eq!(store.count_keys(|k| k.starts_with("session:")).unwrap(), 3);
+    assert_eq!(store.count_keys(|k| k.contains(':')).unwrap(), 4);
+    assert_eq!(store.count_keys(|_| false).unwrap(), 0);
+    assert_eq!(store.count_keys(|_| true).unwrap(), store.len().unwrap());
+}
+
+#[test]
+fn test_raw_shares_underlying_map() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("n".to_string(), 7u8).unwrap();
+
+    let raw = store.raw();
+    {
+        let mut map = raw.lock().unwrap();
+        let entry = map.get_mut(&"n".to_string()).unwrap();
+        assert_eq!(entry.type_id(), std::any::TypeId::of::<u8>());
+        assert_eq!(entry.type_name(), "u8");
+        *entry.downcast_mut::<u8>().unwrap() += 1;
+
+        map.insert("s".to_string(), AnyValue::new("raw".to_string()));
+    }
+
+    assert_eq!(store.get::<u8>(&"n".to_string()).unwrap(), 8);
+    assert_eq!(store.get::<String>(&"s".to_string()).unwrap(), "raw");
+
+    let boxed = raw
+        .lock()
+        .unwrap()
+        .remove(&"s".to_string())
+        .unwrap()
+        .into_boxed();
+    assert!(boxed.is::<String>());
+}
+
+#[test]
+fn test_prefix_scan_and_removal() {
+    let store: TypeMap<String> = TypeMap::new();
+    store
+        .set("config.db.host".to_string(), "db".to_string())
+        .unwrap();
+    store.set("config.db.port".to_string(), 5432u16).unwrap();
+    store.set("config.dbx".to_string(), true).unwrap();
+    store.set("cache.ttl".to_string(), 60u32).unwrap();
+
+    let mut keys = store.keys_with_prefix("config.db.").unwrap();
+    keys.sort();
+    assert_eq!(keys, vec!["config.db.host", "config.db.port"]);
+    assert_eq!(store.keys_with_prefix("config.").unwrap().len(), 3);
+    assert!(store.keys_with_prefix("missing.").unwrap().is_empty());
+
+    assert_eq!(store.remove_with_prefix("config.db.").unwrap(), 2);
+    assert_eq!(store.remove_with_prefix("config.db.").unwrap(), 0);
+    assert_eq!(store.len().unwrap(), 2);
+    assert!(store.contains_key(&"config.dbx".to_string()).unwrap());
+
+    // Works for any key type that is AsRef<str>
+    let borrowed: TypeMap<&'static str> = TypeMap::new();
+    borrowed.set("a.1", 1i32).unwrap();
+    borrowed.set("b.1", 2i32).unwrap();
+    assert_eq!(borrowed.keys_with_prefix("a.").unwrap(), vec!["a.1"]);
+}
+
+#[test]
+fn test_with_or_falls_back_only_when_absent() {
+    let store: TypeMap<String> = TypeMap::new();
+    let fallback = vec!["default".to_string()];
+
+    let len = store
+        .with_or(&"hosts".to_string(), &fallback, |hosts| hosts.len())
+        .unwrap();
+    assert_eq!(len, 1);
+
+    store
+        .set("hosts".to_string(), vec!["a".to_string(), "b".to_string()])
+        .unwrap();
+    let first = store
+        .with_or(&"hosts".to_string(), &fallback, |hosts| hosts[0].clone())
+        .unwrap();
+    assert_eq!(first, "a");
+
+    store.set("port".to_string(), 80u16).unwrap();
+    assert!(matches!(
+        store.with_or(&"port".to_string(), &0u32, |p| *p),
+        Err(MapError::TypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_locked_key_rejects_every_write() {
+    let store: TypeMap<String> = TypeMap::new();
+    let auth = "auth".to_string();
+    store.set(auth.clone(), 1u32).unwrap();
+    store.set("other".to_string(), 2u32).unwrap();
+
+    assert!(matches!(
+        store.lock_key(&"missing".to_string()),
+        Err(MapError::KeyNotFound(_))
+    ));
+    store.lock_key(&auth).unwrap();
+    assert!(store.is_key_locked(&auth).unwrap());
+    assert!(!store.is_key_locked(&"other".to_string()).unwrap());
+
+    let locked = |r: Result<_, MapError>| matches!(r, Err(MapError::Locked(_)));
+    assert!(locked(store.set(auth.clone(), 9u32)));
+    assert!(locked(store.set_boxed(auth.clone(), Box::new(9u32))));
+    assert!(locked(store.with_mut(&auth, |v: &mut u32| *v = 9)));
+    assert!(locked(
+        store.modify_or_default(auth.clone(), |v: &mut u32| *v = 9)
+    ));
+    assert!(locked(store.remove(&auth).map(|_| ())));
+
+    // Batch operations fail as a whole
+    assert!(locked(
+        store.set_all(vec![("new".to_string(), 0u32), (auth.clone(), 9u32),])
+    ));
+    assert!(!store.contains_key(&"new".to_string()).unwrap());
+    assert!(locked(
+        store
+            .remove_many(vec!["other".to_string(), auth.clone()])
+            .map(|_| ())
+    ));
+    assert!(store.contains_key(&"other".to_string()).unwrap());
+
+    // Reads and other keys still work
+    assert_eq!(store.get::<u32>(&auth).unwrap(), 1);
+    store.set("other".to_string(), 3u32).unwrap();
+    assert!(store.remove(&"other".to_string()).unwrap());
+}
+
+#[test]
+fn test_locked_keys_are_not_evicted() {
+    let cache = TypeMap::<String>::new().with_lru_capacity(2);
+    cache.set("core".to_string(), 0u8).unwrap();
+    cache.lock_key(&"core".to_string()).unwrap();
+    cache.set("a".to_string(), 1u8).unwrap();
+    cache.set("b".to_string(), 2u8).unwrap();
+
+    assert!(cache.contains_key(&"core".to_string()).unwrap());
+    assert!(!cache.contains_key(&"a".to_string()).unwrap());
+    assert_eq!(cache.len().unwrap(), 2);
+}
+
+#[test]
+fn test_manifest_lists_keys_and_type_names() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("count".to_string(), 1i64).unwrap();
+    store.set("name".to_string(), "svc".to_string()).unwrap();
+    store
+        .set_boxed("opaque".to_string(), Box::new(1u8))
+        .unwrap();
+
+    let mut manifest = store.manifest().unwrap();
+    manifest.sort();
+    assert_eq!(
+        manifest,
+        vec![
+            ("count".to_string(), "i64"),
+            ("name".to_string(), std::any::type_name::<String>()),
+            ("opaque".to_string(), "<unknown>"),
+        ]
+    );
+}
+
+#[test]
+fn test_take_if_removes_only_when_predicate_holds() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("job".to_string(), vec![1, 2, 3]).unwrap();
+
+    let taken = store
+        .take_if(&"job".to_string(), |v: &Vec<i32>| v.len() > 5)
+        .unwrap();
+    assert!(taken.is_none());
+    assert!(store.contains_key(&"job".to_string()).unwrap());
+
+    assert!(matches!(
+        store.take_if(&"job".to_string(), |_: &String| true),
+        Err(MapError::TypeMismatch { .. })
+    ));
+    assert!(matches!(
+        store.take_if(&"missing".to_string(), |_: &Vec<i32>| true),
+        Err(MapError::KeyNotFound(_))
+    ));
+
+    let taken = store
+        .take_if(&"job".to_string(), |v: &Vec<i32>| v.len() == 3)
+        .unwrap();
+    assert_eq!(taken, Some(vec![1, 2, 3]));
+    assert!(store.is_empty().unwrap());
+}
+
+#[test]
+fn test_with_ctx_reaches_other_keys_under_one_lock() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("rate".to_string(), 3u32).unwrap();
+    store.set("base".to_string(), 10u32).unwrap();
+    store.set("stale".to_string(), true).unwrap();
+
+    let total = store
+        .with_ctx(&"rate".to_string(), |rate: &u32, ctx| {
+            let base = ctx.get::<u32>(&"base".to_string())?;
+            ctx.set("total".to_string(), base * rate)?;
+            ctx.remove(&"stale".to_string())?;
+            ctx.with(&"total".to_string(), |t: &u32| *t)
+        })
+        .unwrap()
+        .unwrap();
+    assert_eq!(total, 30);
+    assert_eq!(store.get::<u32>(&"total".to_string()).unwrap(), 30);
+    assert!(!store.contains_key(&"stale".to_string()).unwrap());
+
+    // The lent key can't be touched through the context, and survives the call
+    let conflicts = store
+        .with_ctx(&"rate".to_string(), |_: &u32, ctx| {
+            assert!(ctx.contains_key(&"rate".to_string()).unwrap());
+            (
+                ctx.get::<u32>(&"rate".to_string()),
+                ctx.remove(&"rate".to_string()),
+            )
+        })
+        .unwrap();
+    assert!(matches!(conflicts.0, Err(MapError::BorrowConflict)));
+    assert!(matches!(conflicts.1, Err(MapError::BorrowConflict)));
+    assert_eq!(store.get::<u32>(&"rate".to_string()).unwrap(), 3);
+
+    assert!(matches!(
+        store.with_ctx(&"rate".to_string(), |_: &String, _| ()),
+        Err(MapError::TypeMismatch { .. })
+    ));
+    assert_eq!(store.len().unwrap(), 3);
+}
+
+#[test]
+fn test_with_ctx_respects_lru_capacity() {
+    let cache = TypeMap::<String>::new().with_lru_capacity(2);
+    cache.set("a".to_string(), 1u8).unwrap();
+    cache.set("b".to_string(), 2u8).unwrap();
+
+    cache
+        .with_ctx(&"a".to_string(), |_: &u8, ctx| {
+            ctx.set("c".to_string(), 3u8).unwrap();
+        })
+        .unwrap();
+
+    assert_eq!(cache.len().unwrap(), 2);
+    assert!(cache.contains_key(&"a".to_string()).unwrap());
+}
+
+#[test]
+fn test_for_each_value_visits_all_entries() {
+    let store: TypeMap<u8> = TypeMap::new();
+    store.set(1, 10u64).unwrap();
+    store.set(2, "two".to_string()).unwrap();
+    store.set(3, vec![3u8]).unwrap();
+
+    let mut visited = 0;
+    let mut strings = Vec::new();
+    store
+        .for_each_value(|value| {
+            visited += 1;
+            if let Some(s) = value.downcast_ref::<String>() {
+                strings.push(s.clone());
+            }
+        })
+        .unwrap();
+
+    assert_eq!(visited, 3);
+    assert_eq!(strings, vec!["two".to_string()]);
+}
+
+#[test]
+fn test_set_if_absent_with_initializes_once_under_contention() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let store: TypeMap<String> = TypeMap::new();
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let store = store.clone();
+            let calls = Arc::clone(&calls);
+            thread::spawn(move || {
+                store
+                    .set_if_absent_with("shared".to_string(), || {
+                        calls.fetch_add(1, Ordering::SeqCst);
+                        i
+                    })
+                    .unwrap()
+            })
+        })
+        .collect();
+    let inserted: Vec<bool> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+    assert_eq!(inserted.iter().filter(|&&b| b).count(), 1);
+
+    // An existing value of another type is left untouched
+    assert!(!store
+        .set_if_absent_with("shared".to_string(), || "text".to_string())
+        .unwrap());
+    assert!(store.get::<i32>(&"shared".to_string()).is_ok());
+}
+
+#[test]
+fn test_keys_ordered_is_sorted() {
+    let store: TypeMap<u64> = TypeMap::new();
+    for key in [42, 7, 19, 3] {
+        store.set(key, key.to_string()).unwrap();
+    }
+    assert_eq!(store.keys_ordered().unwrap(), vec![3, 7, 19, 42]);
+}
+
+#[test]
+fn test_successful_lookups_do_not_format_key() {
+    use std::fmt;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static FORMATTED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Clone, PartialEq, Eq, Hash)]
+    struct CountingKey(u32);
+
+    impl fmt::Debug for CountingKey {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            FORMATTED.fetch_add(1, Ordering::SeqCst);
+            write!(f, "CountingKey({})", self.0)
+        }
+    }
+
+    let store: TypeMap<CountingKey> = TypeMap::new();
+    let key = CountingKey(1);
+    store.set(key.clone(), 10u32).unwrap();
+
+    assert_eq!(store.get::<u32>(&key).unwrap(), 10);
+    store.with(&key, |n: &u32| *n).unwrap();
+    store.with_mut(&key, |n: &mut u32| *n += 1).unwrap();
+    assert!(store.contains_key(&key).unwrap());
+    assert!(store.remove(&key).unwrap());
+    assert_eq!(FORMATTED.load(Ordering::SeqCst), 0);
+
+    // The key is only formatted once a lookup actually fails
+    assert!(matches!(
+        store.get::<u32>(&key),
+        Err(MapError::KeyNotFound(name)) if name == "CountingKey(1)"
+    ));
+    assert_eq!(FORMATTED.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_with_raw_reports_stored_type() {
+    use std::any::TypeId;
+
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("name".to_string(), "sovran".to_string()).unwrap();
+
+    let (type_id, len) = store
+        .with_raw(&"name".to_string(), |type_id, value| {
+            (type_id, value.downcast_ref::<String>().map(String::len))
+        })
+        .unwrap();
+    assert_eq!(type_id, TypeId::of::<String>());
+    assert_eq!(len, Some(6));
+
+    assert!(matches!(
+        store.with_raw(&"missing".to_string(), |_, _| ()),
+        Err(MapError::KeyNotFound(_))
+    ));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "reentrant lock on TypeMap — use with_ctx")]
+fn test_reentrant_with_panics_in_debug() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("a".to_string(), 1i32).unwrap();
+    store.set("b".to_string(), 2i32).unwrap();
+
+    // Without the check this would deadlock
+    let _ = store.with(&"a".to_string(), |_: &i32| {
+        store.get::<i32>(&"b".to_string())
+    });
+}
+
+#[test]
+fn test_get_or_insert_with_checked_vs_force() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("port".to_string(), "8080".to_string()).unwrap();
+
+    // Strict: the mismatched value survives and the initializer never runs
+    let result = store.get_or_insert_with_checked("port".to_string(), || -> u16 {
+        unreachable!("key is present")
+    });
+    assert!(matches!(result, Err(MapError::TypeMismatch { .. })));
+    assert_eq!(store.get::<String>(&"port".to_string()).unwrap(), "8080");
+
+    // Force: the mismatched value is replaced
+    assert_eq!(
+        store
+            .get_or_insert_with_force("port".to_string(), || 8080u16)
+            .unwrap(),
+        8080
+    );
+
+    // Both return the existing value once the types agree
+    assert_eq!(
+        store
+            .get_or_insert_with_checked("port".to_string(), || 1u16)
+            .unwrap(),
+        8080
+    );
+    assert_eq!(
+        store
+            .get_or_insert_with_force("port".to_string(), || 1u16)
+            .unwrap(),
+        8080
+    );
+
+    // Force still respects locked keys
+    store.lock_key(&"port".to_string()).unwrap();
+    assert!(matches!(
+        store.get_or_insert_with_force("port".to_string(), || 1u32),
+        Err(MapError::Locked(_))
+    ));
+}
+
+#[test]
+fn test_arc_str_keys() {
+    let store: TypeMap<Arc<str>> = TypeMap::new();
+    store.set_str("host", "localhost".to_string()).unwrap();
+    let first = store.keys().unwrap().remove(0);
+
+    // Overwriting keeps the original key allocation
+    store.set_str("host", "example.com".to_string()).unwrap();
+    assert!(Arc::ptr_eq(&first, &store.keys().unwrap()[0]));
+    assert_eq!(store.get_str::<String>("host").unwrap(), "example.com");
+    assert_eq!(store.with_str("host", |h: &String| h.len()).unwrap(), 11);
+    assert!(matches!(
+        store.get_str::<u32>("host"),
+        Err(MapError::TypeMismatch { .. })
+    ));
+    assert!(matches!(
+        store.get_str::<String>("port"),
+        Err(MapError::KeyNotFound(_))
+    ));
+}
+
+#[test]
+fn test_key_interner_shares_and_purges() {
+    let interner = KeyInterner::new();
+    let a: TypeMap<Arc<str>> = TypeMap::new();
+    let b: TypeMap<Arc<str>> = TypeMap::new();
+
+    a.set_interned(&interner, "shared", 1u8).unwrap();
+    b.set_interned(&interner, "shared", 2u8).unwrap();
+    b.set_interned(&interner, "only-b", 3u8).unwrap();
+    assert_eq!(interner.len().unwrap(), 2);
+    assert!(Arc::ptr_eq(
+        &a.keys().unwrap()[0],
+        &interner.intern("shared").unwrap()
+    ));
+
+    // Strings still used by a map survive a purge
+    b.remove(&Arc::from("only-b")).unwrap();
+    assert_eq!(interner.purge().unwrap(), 1);
+    assert_eq!(interner.len().unwrap(), 1);
+}
+
+#[test]
+fn test_remove_types() {
+    use std::any::TypeId;
+
+    let store: TypeMap<u32> = TypeMap::new();
+    store.set(1, "a".to_string()).unwrap();
+    store.set(2, "b".to_string()).unwrap();
+    store.set(3, 3u8).unwrap();
+    store.set(4, 4u64).unwrap();
+    store.set(5, vec![5u8]).unwrap();
+
+    let removed = store
+        .remove_types(&[TypeId::of::<String>(), TypeId::of::<u64>()])
+        .unwrap();
+    assert_eq!(removed, 3);
+    assert_eq!(store.keys_ordered().unwrap(), vec![3, 5]);
+
+    // Types with no entries remove nothing
+    assert_eq!(store.remove_types(&[TypeId::of::<bool>()]).unwrap(), 0);
+
+    // A locked match aborts the whole purge
+    store.lock_key(&5).unwrap();
+    assert!(matches!(
+        store.remove_types(&[TypeId::of::<u8>(), TypeId::of::<Vec<u8>>()]),
+        Err(MapError::Locked(_))
+    ));
+    assert_eq!(store.len().unwrap(), 2);
+}
+
+#[test]
+fn test_contains_all_and_missing_keys() {
+    let store: TypeMap<&'static str> = TypeMap::new();
+    store.set("host", "localhost".to_string()).unwrap();
+    store.set("port", 8080u16).unwrap();
+
+    assert!(store.contains_all(&["host", "port"]).unwrap());
+    assert!(store.contains_all(&[]).unwrap());
+    assert!(!store.contains_all(&["host", "user", "port"]).unwrap());
+
+    assert_eq!(
+        store
+            .missing_keys(&["user", "host", "password", "port"])
+            .unwrap(),
+        vec!["user", "password"]
+    );
+    assert!(store.missing_keys(&["host"]).unwrap().is_empty());
+}
+
+#[test]
+fn test_for_each_mut_of_type() {
+    let store: TypeMap<u32> = TypeMap::new();
+    store.set(1, 10i64).unwrap();
+    store.set(2, 20i64).unwrap();
+    store.set(3, 30i32).unwrap();
+
+    let mut visited = Vec::new();
+    store
+        .for_each_mut_of_type(|key, n: &mut i64| {
+            visited.push(*key);
+            *n *= 2;
+        })
+        .unwrap();
+    visited.sort();
+    assert_eq!(visited, vec![1, 2]);
+    assert_eq!(store.get::<i64>(&2).unwrap(), 40);
+    assert_eq!(store.get::<i32>(&3).unwrap(), 30);
+
+    // A locked entry of the visited type blocks the whole pass
+    store.lock_key(&1).unwrap();
+    assert!(matches!(
+        store.for_each_mut_of_type(|_, n: &mut i64| *n = 0),
+        Err(MapError::Locked(_))
+    ));
+    assert_eq!(store.get::<i64>(&2).unwrap(), 40);
+
+    // ...but a locked entry of another type doesn't
+    store
+        .for_each_mut_of_type(|_, n: &mut i32| *n += 1)
+        .unwrap();
+    assert_eq!(store.get::<i32>(&3).unwrap(), 31);
+}
+
+#[test]
+fn test_with_mut_or_remove() {
+    let sessions: TypeMap<&'static str> = TypeMap::new();
+    sessions.set("alive", 1u32).unwrap();
+    sessions.set("expiring", 0u32).unwrap();
+
+    // Decrement the remaining lifetime of each session, dropping any that run out
+    for key in ["alive", "expiring"] {
+        sessions
+            .with_mut_or_remove(&key, |ttl: &mut u32| match ttl.checked_sub(1) {
+                Some(left) => {
+                    *ttl = left;
+                    true
+                }
+                None => false,
+            })
+            .unwrap();
+    }
+    assert_eq!(sessions.get::<u32>(&"alive").unwrap(), 0);
+    assert!(!sessions.contains_key(&"expiring").unwrap());
+
+    // Errors leave the entry untouched
+    assert!(matches!(
+        sessions.with_mut_or_remove(&"alive", |_: &mut String| false),
+        Err(MapError::TypeMismatch { .. })
+    ));
+    sessions.lock_key(&"alive").unwrap();
+    assert!(matches!(
+        sessions.with_mut_or_remove(&"alive", |_: &mut u32| false),
+        Err(MapError::Locked(_))
+    ));
+    assert!(sessions.contains_key(&"alive").unwrap());
+}
+
+#[test]
+fn test_try_deep_clone_copies_registered_types() {
+    let store: TypeMap<u8> = TypeMap::new().with_lru_capacity(4);
+    store.register_clone::<String>().unwrap();
+    store.set(1, "one".to_string()).unwrap();
+    store.set(2, 2u64).unwrap();
+    store.lock_key(&1).unwrap();
+
+    let copy = store.try_deep_clone().unwrap();
+    assert_eq!(copy.keys().unwrap(), vec![1]);
+    assert_eq!(copy.lru_capacity(), Some(4));
+    assert!(copy.is_key_locked(&1).unwrap());
+
+    // The copy has its own storage and its own registrations
+    copy.register_clone::<u64>().unwrap();
+    copy.set(3, 3u64).unwrap();
+    assert!(!store.contains_key(&3).unwrap());
+    assert_eq!(copy.try_deep_clone().unwrap().len().unwrap(), 2);
+    assert_eq!(store.try_deep_clone().unwrap().len().unwrap(), 1);
+}
+
+#[test]
+fn test_drain_from() {
+    let inbox: TypeMap<u8> = TypeMap::new();
+    inbox.set(1, vec![5u32, 12, 7, 30, 2]).unwrap();
+
+    let large = inbox.drain_from(&1, |n: &u32| *n >= 10).unwrap();
+    assert_eq!(large, vec![12, 30]);
+    assert_eq!(inbox.get::<Vec<u32>>(&1).unwrap(), vec![5, 7, 2]);
+
+    // Nothing matches: the Vec is left as it was
+    assert!(inbox.drain_from(&1, |n: &u32| *n > 100).unwrap().is_empty());
+    assert_eq!(inbox.get::<Vec<u32>>(&1).unwrap(), vec![5, 7, 2]);
+
+    assert!(matches!(
+        inbox.drain_from(&1, |_: &u64| true),
+        Err(MapError::TypeMismatch { .. })
+    ));
+    assert!(matches!(
+        inbox.drain_from(&2, |_: &u32| true),
+        Err(MapError::KeyNotFound(_))
+    ));
+    inbox.lock_key(&1).unwrap();
+    assert!(matches!(
+        inbox.drain_from(&1, |_: &u32| true),
+        Err(MapError::Locked(_))
+    ));
+    assert_eq!(inbox.get::<Vec<u32>>(&1).unwrap().len(), 3);
+}
+
+#[test]
+fn test_drain_from_keeps_items_when_pred_panics() {
+    let inbox: TypeMap<u8> = TypeMap::new();
+    inbox.set(1, vec![10u32, 1, 20, 2, 99, 30, 3]).unwrap();
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        inbox.drain_from(&1, |n: &u32| {
+            assert_ne!(*n, 99, "bad item");
+            *n >= 10
+        })
+    }));
+    assert!(result.is_err());
+
+    // Only the items drained before the panic are gone; the rest are still stored
+    let raw = inbox.raw();
+    let store = raw.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    assert_eq!(
+        store[&1].downcast_ref::<Vec<u32>>().unwrap(),
+        &vec![1, 2, 99, 30, 3]
+    );
+}
+
+#[test]
+fn test_get_path() {
+    use sovran_typemap::Descend;
+    use std::any::Any;
+    use std::collections::HashMap;
+
+    struct Server {
+        name: String,
+        limits: HashMap<String, u32>,
+    }
+
+    impl Descend for Server {
+        fn descend(&self, segment: &str) -> Option<&dyn Any> {
+            match segment {
+                "name" => Some(&self.name),
+                "limits" => Some(&self.limits),
+                _ => None,
+            }
+        }
+    }
+
+    let config: TypeMap<String> = TypeMap::new();
+    config.register_descent::<Server>().unwrap();
+    config.register_descent::<HashMap<String, u32>>().unwrap();
+    config
+        .set(
+            "server".to_string(),
+            Server {
+                name: "edge-1".to_string(),
+                limits: HashMap::from([("conns".to_string(), 512)]),
+            },
+        )
+        .unwrap();
+
+    assert_eq!(config.get_path::<String>("server.name").unwrap(), "edge-1");
+    assert_eq!(config.get_path::<u32>("server.limits.conns").unwrap(), 512);
+    let len = config
+        .with_path("server.limits", |limits: &HashMap<String, u32>| {
+            limits.len()
+        })
+        .unwrap();
+    assert_eq!(len, 1);
+
+    // A single segment is a plain key lookup
+    assert!(config.with_path("server", |_: &Server| ()).is_ok());
+
+    // The error names the part of the path that couldn't be resolved
+    match config.get_path::<u32>("server.limits.reqs.max") {
+        Err(MapError::KeyNotFound(path)) => assert_eq!(path, "\"server.limits.reqs\""),
+        other => panic!("expected KeyNotFound, got {:?}", other),
+    }
+    // String has no registered descent
+    assert!(matches!(
+        config.get_path::<u32>("server.name.len"),
+        Err(MapError::KeyNotFound(_))
+    ));
+    assert!(matches!(
+        config.get_path::<u64>("server.limits.conns"),
+        Err(MapError::TypeMismatch { .. })
+    ));
+    assert!(matches!(
+        config.get_path::<u32>("client.limits"),
+        Err(MapError::KeyNotFound(_))
+    ));
+}
+
+#[test]
+fn test_get_path_requires_registered_string_maps() {
+    use std::collections::HashMap;
+
+    let config: TypeMap<String> = TypeMap::new();
+    let db = HashMap::from([("port".to_string(), 5432u16)]);
+    let services = HashMap::from([("db".to_string(), db)]);
+    config
+        .set(
+            "env".to_string(),
+            HashMap::from([("prod".to_string(), services)]),
+        )
+        .unwrap();
+
+    // The error points at the value whose type isn't registered
+    match config.get_path::<u16>("env.prod.db.port") {
+        Err(MapError::KeyNotFound(message)) => {
+            assert!(message.contains("no descent registered"));
+            assert!(message.contains("\"env\""));
+        }
+        other => panic!("expected KeyNotFound, got {:?}", other),
+    }
+
+    type Db = HashMap<String, u16>;
+    config
+        .register_descent::<HashMap<String, HashMap<String, Db>>>()
+        .unwrap();
+    config.register_descent::<HashMap<String, Db>>().unwrap();
+    match config.get_path::<u16>("env.prod.db.port") {
+        Err(MapError::KeyNotFound(message)) => assert!(message.contains("\"env.prod.db\"")),
+        other => panic!("expected KeyNotFound, got {:?}", other),
+    }
+
+    config.register_descent::<Db>().unwrap();
+    assert_eq!(config.get_path::<u16>("env.prod.db.port").unwrap(), 5432);
+    assert!(matches!(
+        config.get_path::<u16>("env.prod.cache.port"),
+        Err(MapError::KeyNotFound(_))
+    ));
+}
+
+#[test]
+fn test_for_each_of_type() {
+    let store: TypeMap<u32> = TypeMap::new();
+    store.set(1, "one".to_string()).unwrap();
+    store.set(2, 2u64).unwrap();
+    store.set(3, "three".to_string()).unwrap();
+    store.set(4, vec!["four".to_string()]).unwrap();
+
+    let mut seen = Vec::new();
+    store
+        .for_each_of_type(|key, value: &String| seen.push((*key, value.clone())))
+        .unwrap();
+    seen.sort();
+    assert_eq!(seen, vec![(1, "one".to_string()), (3, "three".to_string())]);
+
+    let mut visits = 0;
+    store.for_each_of_type(|_, _: &i8| visits += 1).unwrap();
+    assert_eq!(visits, 0);
+}
+
+#[test]
+fn test_set_by() {
+    #[derive(Clone, Debug, PartialEq)]
+    struct Device {
+        serial: String,
+        online: bool,
+    }
+
+    let devices: TypeMap<String> = TypeMap::new();
+    for serial in ["A-1", "B-2"] {
+        let device = Device {
+            serial: serial.to_string(),
+            online: true,
+        };
+        devices.set_by(device, |d| d.serial.clone()).unwrap();
+    }
+    assert_eq!(devices.len().unwrap(), 2);
+    assert!(devices.get::<Device>(&"B-2".to_string()).unwrap().online);
+
+    devices.lock_key(&"A-1".to_string()).unwrap();
+    let replacement = Device {
+        serial: "A-1".to_string(),
+        online: false,
+    };
+    assert!(matches!(
+        devices.set_by(replacement, |d| d.serial.clone()),
+        Err(MapError::Locked(_))
+    ));
+}
+
+#[test]
+fn test_display_summary() {
+    let store: TypeMap<String> = TypeMap::new();
+    assert_eq!(store.to_string(), "TypeMap(0 entries)");
+    store.set("a".to_string(), 1i32).unwrap();
+    assert_eq!(store.to_string(), "TypeMap(1 entry)");
+    store.set("b".to_string(), "two".to_string()).unwrap();
+    assert_eq!(format!("{}", store), "TypeMap(2 entries)");
+
+    // Formatting from inside a closure reports the held lock instead of panicking
+    let inside = store
+        .with(&"a".to_string(), |_: &i32| store.to_string())
+        .unwrap();
+    assert_eq!(inside, "TypeMap(<busy>)");
+
+    // A lock timeout doesn't change the label, and formatting never waits
+    let timed: TypeMap<String> =
+        TypeMap::new().with_lock_timeout(std::time::Duration::from_millis(10));
+    timed.set("a".to_string(), 1i32).unwrap();
+    let inside = timed
+        .with(&"a".to_string(), |_: &i32| timed.to_string())
+        .unwrap();
+    assert_eq!(inside, "TypeMap(<busy>)");
+
+    let poisoner = store.clone();
+    let result = std::thread::spawn(move || {
+        let _ = poisoner.with_mut(&"a".to_string(), |_: &mut i32| panic!("poison"));
+    })
+    .join();
+    assert!(result.is_err());
+    assert_eq!(store.to_string(), "TypeMap(<poisoned>)");
+}
+
+#[test]
+fn test_count_of_type_stays_in_step() {
+    fn assert_counts_match(store: &TypeMap<u32>) {
+        let scanned_u8 = store.values::<u8>().unwrap().len();
+        let scanned_string = store.values::<String>().unwrap().len();
+        let scanned_vec = store.values::<Vec<u16>>().unwrap().len();
+        assert_eq!(store.count_of_type::<u8>().unwrap(), scanned_u8);
+        assert_eq!(store.count_of_type::<String>().unwrap(), scanned_string);
+        assert_eq!(store.count_of_type::<Vec<u16>>().unwrap(), scanned_vec);
+        assert_eq!(
+            scanned_u8 + scanned_string + scanned_vec,
+            store.len().unwrap()
+        );
+    }
+
+    let store: TypeMap<u32> = TypeMap::new().with_lru_capacity(40);
+    let handles: Vec<_> = (0..4u32)
+        .map(|t| {
+            let store = store.clone();
+            std::thread::spawn(move || {
+                for i in 0..2000u32 {
+                    let key = (i * 7 + t) % 50;
+                    match (i + t) % 5 {
+                        0 => store.set(key, i as u8).unwrap(),
+                        1 => store.set(key, i.to_string()).unwrap(),
+                        2 => store.set(key, vec![i as u16]).unwrap(),
+                        3 => {
+                            store.remove(&key).unwrap();
+                        }
+                        _ => {
+                            let _ = store.take_if(&key, |_: &String| true);
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_counts_match(&store);
+
+    store.remove_types(&[std::any::TypeId::of::<u8>()]).unwrap();
+    assert_eq!(store.count_of_type::<u8>().unwrap(), 0);
+    assert_counts_match(&store);
+
+    let copy = {
+        store.register_clone::<String>().unwrap();
+        store.try_deep_clone().unwrap()
+    };
+    assert_eq!(copy.count_of_type::<Vec<u16>>().unwrap(), 0);
+    assert_eq!(
+        copy.count_of_type::<String>().unwrap(),
+        store.count_of_type::<String>().unwrap()
+    );
+}
+
+#[test]
+fn test_count_of_type_sees_raw_changes() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("a".to_string(), 1i32).unwrap();
+    store.set("b".to_string(), 2i32).unwrap();
+    store.set("c".to_string(), "three".to_string()).unwrap();
+
+    // Removals through the escape hatch, even on another handle, are counted
+    let raw = store.share().raw();
+    raw.lock()
+        .unwrap()
+        .retain(|_, value| !value.is_type::<i32>());
+    assert_eq!(store.count_of_type::<i32>().unwrap(), 0);
+    assert_eq!(store.count_of_type::<String>().unwrap(), 1);
+
+    // ...and so are later changes through the same handle
+    raw.lock().unwrap().clear();
+    store.set("d".to_string(), 4i32).unwrap();
+    assert_eq!(store.count_of_type::<String>().unwrap(), 0);
+    assert_eq!(store.count_of_type::<i32>().unwrap(), 1);
+    assert!(format!("{:?}", store.typed_view::<i32>()).contains("count: Some(1)"));
+
+    // Once the escape hatch is dropped the counters are rebuilt and kept up again
+    raw.lock()
+        .unwrap()
+        .insert("e".to_string(), AnyValue::new(5i32));
+    drop(raw);
+    assert_eq!(store.count_of_type::<i32>().unwrap(), 2);
+    store.remove(&"d".to_string()).unwrap();
+    store.set("f".to_string(), "six".to_string()).unwrap();
+    assert_eq!(store.count_of_type::<i32>().unwrap(), 1);
+    assert_eq!(store.count_of_type::<String>().unwrap(), 1);
+}
+
+#[test]
+fn test_try_into_inner() {
+    // A unique owner gets every entry back
+    let store: TypeMap<u32> = TypeMap::new();
+    store.set(1, "one".to_string()).unwrap();
+    store.set(2, 2u64).unwrap();
+    let entries = store.try_into_inner().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[&1].downcast_ref::<String>().unwrap(), "one");
+    assert_eq!(entries[&2].downcast_ref::<u64>(), Some(&2));
+
+    // A shared owner gets the map back untouched, settings included
+    let store: TypeMap<u32> = TypeMap::new().with_lru_capacity(5);
+    store.set(1, 1u8).unwrap();
+    let view = store.typed_view::<u8>();
+    let store = store.try_into_inner().unwrap_err();
+    assert_eq!(store.lru_capacity(), Some(5));
+    assert_eq!(view.get(&1).unwrap(), 1);
+    store.set(2, 2u8).unwrap();
+
+    drop(view);
+    let entries = store.try_into_inner().unwrap();
+    assert_eq!(entries.len(), 2);
+}
+
+#[test]
+fn test_into_parts_and_from_parts_share_storage() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("a".to_string(), 1i32).unwrap();
+    let other = store.clone();
+
+    let parts = store.into_parts();
+    let items = std::sync::Arc::clone(&parts.items);
+    let rebuilt = TypeMap::from_parts(parts);
+    rebuilt.set("b".to_string(), 2i32).unwrap();
+
+    // All three views agree on one backing store
+    assert_eq!(other.get::<i32>(&"b".to_string()).unwrap(), 2);
+    assert_eq!(items.lock().unwrap().len(), 2);
+
+    // Counts stay right even when the storage is changed behind the map's back
+    items.lock().unwrap().remove("a");
+    assert_eq!(rebuilt.count_of_type::<i32>().unwrap(), 1);
+    assert_eq!(other.count_of_type::<i32>().unwrap(), 1);
+}
+
+#[test]
+fn test_from_parts_keeps_versions_and_wakes_waiters() {
+    use std::time::Duration;
+
+    let store: TypeMap<String> = TypeMap::new();
+    let key = "phase".to_string();
+    store.set(key.clone(), 1u32).unwrap();
+    store.set(key.clone(), 2u32).unwrap();
+    let seen = store.version(&key).unwrap();
+
+    // A handle rebuilt from the parts wakes waiters on the original
+    let waiter = store.clone();
+    let rebuilt = TypeMap::from_parts(store.into_parts());
+    let writer = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        rebuilt.set("phase".to_string(), 3u32).unwrap();
+        rebuilt
+    });
+    let version = waiter
+        .wait_for_change(&key, seen, Duration::from_secs(5))
+        .unwrap();
+    let rebuilt = writer.join().unwrap();
+    assert!(version > seen);
+    assert_eq!(waiter.get::<u32>(&key).unwrap(), 3);
+
+    // A bare Arc seeds the clock past every stored version
+    let wrapped = TypeMap::from_parts(rebuilt.into_parts().items);
+    wrapped.set(key.clone(), 4u32).unwrap();
+    assert!(wrapped.version(&key).unwrap() > version);
+    assert!(matches!(
+        waiter.wait_for_change(&key, version, Duration::ZERO),
+        Ok(v) if v > version
+    ));
+}
+
+#[test]
+fn test_absorb_moves_entries() {
+    use sovran_typemap::OnConflict;
+
+    fn fresh() -> (TypeMap<&'static str>, TypeMap<&'static str>) {
+        let base: TypeMap<&'static str> = TypeMap::new();
+        base.set("shared", 1u32).unwrap();
+        base.set("base_only", "kept".to_string()).unwrap();
+        let other: TypeMap<&'static str> = TypeMap::new();
+        other.set("shared", 2u32).unwrap();
+        other.set("other_only", vec![3u8]).unwrap();
+        (base, other)
+    }
+
+    let (base, other) = fresh();
+    let other_handle = other.share();
+    base.absorb(other, OnConflict::Overwrite).unwrap();
+    assert_eq!(base.get::<u32>(&"shared").unwrap(), 2);
+    assert_eq!(base.get::<Vec<u8>>(&"other_only").unwrap(), vec![3]);
+    assert_eq!(base.len().unwrap(), 3);
+    assert!(other_handle.is_empty().unwrap());
+    assert_eq!(other_handle.count_of_type::<u32>().unwrap(), 0);
+    assert_eq!(base.count_of_type::<u32>().unwrap(), 1);
+
+    let (base, other) = fresh();
+    base.absorb(other, OnConflict::Keep).unwrap();
+    assert_eq!(base.get::<u32>(&"shared").unwrap(), 1);
+    assert_eq!(base.len().unwrap(), 3);
+
+    // A locked key blocks an overwriting merge before anything moves
+    let (base, other) = fresh();
+    let other_handle = other.share();
+    base.lock_key(&"shared").unwrap();
+    assert!(matches!(
+        base.absorb(other, OnConflict::Overwrite),
+        Err(MapError::Locked(_))
+    ));
+    assert_eq!(other_handle.len().unwrap(), 2);
+    assert_eq!(base.get::<u32>(&"shared").unwrap(), 1);
+
+    // Absorbing a handle onto the same storage is a no-op
+    base.absorb(base.share(), OnConflict::Overwrite).unwrap();
+    assert_eq!(base.len().unwrap(), 2);
+}
+
+#[test]
+fn test_wait_for_change() {
+    use std::time::Duration;
+
+    let state: TypeMap<String> = TypeMap::new();
+    let key = "leader".to_string();
+
+    // A key that doesn't exist yet is waited on until it is set
+    let writer = state.clone();
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(20));
+        writer.set("leader".to_string(), 1u32).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        writer
+            .with_mut(&"leader".to_string(), |n: &mut u32| *n = 2)
+            .unwrap();
+    });
+
+    let first = state
+        .wait_for_change(&key, 0, Duration::from_secs(5))
+        .unwrap();
+    let second = state
+        .wait_for_change(&key, first, Duration::from_secs(5))
+        .unwrap();
+    handle.join().unwrap();
+    assert!(second > first);
+    assert_eq!(state.version(&key).unwrap(), second);
+    assert_eq!(state.get::<u32>(&key).unwrap(), 2);
+
+    // Already changed since the given version: returns without waiting
+    assert_eq!(
+        state.wait_for_change(&key, first, Duration::ZERO).unwrap(),
+        second
+    );
+    assert!(matches!(
+        state.wait_for_change(&key, second, Duration::from_millis(10)),
+        Err(MapError::Timeout)
+    ));
+
+    // Versions keep increasing across removal and re-insertion
+    state.remove(&key).unwrap();
+    assert!(matches!(state.version(&key), Err(MapError::KeyNotFound(_))));
+    state.set(key.clone(), 3u32).unwrap();
+    assert!(state.version(&key).unwrap() > second);
+}
+
+#[test]
+fn test_wait_for_change_waiters_on_different_keys() {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let state: TypeMap<&'static str> = TypeMap::new();
+    let (done, finished) = mpsc::channel();
+    let waiters: Vec<_> = ["a", "b"]
+        .into_iter()
+        .map(|key| {
+            let state = state.clone();
+            let done = done.clone();
+            std::thread::spawn(move || {
+                let version = state
+                    .wait_for_change(&key, 0, Duration::from_secs(5))
+                    .unwrap();
+                done.send((key, version)).unwrap();
+            })
+        })
+        .collect();
+
+    // Writes to unrelated keys don't release either waiter
+    std::thread::sleep(Duration::from_millis(20));
+    state.set("c", 0u32).unwrap();
+    assert!(finished.recv_timeout(Duration::from_millis(50)).is_err());
+
+    // Each waiter returns only once its own key is written
+    state.set("a", 1u32).unwrap();
+    let (key, version) = finished.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(key, "a");
+    assert_eq!(version, state.version(&"a").unwrap());
+    assert!(finished.recv_timeout(Duration::from_millis(50)).is_err());
+
+    state.set("b", 2u32).unwrap();
+    let (key, _) = finished.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(key, "b");
+    for waiter in waiters {
+        waiter.join().unwrap();
+    }
+
+    // With nobody waiting, writes still stamp new versions
+    let before = state.version(&"a").unwrap();
+    state.set("a", 3u32).unwrap();
+    assert!(state.version(&"a").unwrap() > before);
+}
+
+#[test]
+fn test_auto_shrink_after_mass_removal() {
+    let capacity = |map: &TypeMap<u32>| map.raw().lock().unwrap().capacity();
+
+    let map = TypeMap::<u32>::new().with_auto_shrink(0.5);
+    assert_eq!(map.auto_shrink_threshold(), Some(0.5));
+    map.set_all((0..4096).map(|i| (i, i))).unwrap();
+    let grown = capacity(&map);
+
+    // A few removals leave the table well above the threshold
+    map.remove_many(0..64).unwrap();
+    assert!(capacity(&map) > grown / 2);
+
+    map.remove_types(&[std::any::TypeId::of::<u32>()]).unwrap();
+    assert!(map.is_empty().unwrap());
+    assert!(capacity(&map) < 16);
+
+    // Off by default
+    let plain: TypeMap<u32> = TypeMap::new();
+    assert_eq!(plain.auto_shrink_threshold(), None);
+    plain.set_all((0..4096).map(|i| (i, i))).unwrap();
+    let grown = capacity(&plain);
+    plain.remove_many(0..4096).unwrap();
+    assert!(capacity(&plain) > grown / 2);
+}
+
+#[test]
+#[should_panic(expected = "auto-shrink threshold")]
+fn test_auto_shrink_rejects_bad_threshold() {
+    let _ = TypeMap::<u32>::new().with_auto_shrink(1.5);
+}
+
+#[test]
+fn test_get_arc_shares_without_copying() {
+    use std::sync::Arc;
+
+    let store: TypeMap<&'static str> = TypeMap::new();
+    store.set_arc("model", vec![0.5f32; 1024]).unwrap();
+
+    let a = store.get_arc::<Vec<f32>>(&"model").unwrap();
+    let b = store.get_arc::<Vec<f32>>(&"model").unwrap();
+    assert!(Arc::ptr_eq(&a, &b));
+
+    // Replacing the entry leaves existing handles on the old value
+    store.set_arc("model", vec![1.0f32; 8]).unwrap();
+    assert_eq!(a.len(), 1024);
+    assert_eq!(store.get_arc::<Vec<f32>>(&"model").unwrap().len(), 8);
+
+    // A plain value isn't an Arc
+    store.set("plain", vec![1.0f32]).unwrap();
+    assert!(matches!(
+        store.get_arc::<Vec<f32>>(&"plain"),
+        Err(MapError::TypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_lock_timeout_applies_to_every_method() {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    let store = TypeMap::<u8>::new().with_lock_timeout(Duration::from_millis(20));
+    assert_eq!(store.lock_timeout(), Some(Duration::from_millis(20)));
+    assert_eq!(TypeMap::<u8>::new().lock_timeout(), None);
+    store.set(1, 10u32).unwrap();
+
+    let (locked_tx, locked_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    let holder = store.clone();
+    let handle = std::thread::spawn(move || {
+        holder
+            .with(&1, |_: &u32| {
+                locked_tx.send(()).unwrap();
+                release_rx.recv().unwrap();
+            })
+            .unwrap();
+    });
+    locked_rx.recv().unwrap();
+
+    assert!(matches!(store.get::<u32>(&1), Err(MapError::Timeout)));
+    assert!(matches!(store.set(2, 0u32), Err(MapError::Timeout)));
+    assert!(matches!(store.remove(&1), Err(MapError::Timeout)));
+    assert!(matches!(store.len(), Err(MapError::Timeout)));
+    assert!(matches!(
+        store.with_timeout_mut(&1, Duration::from_millis(5), |n: &mut u32| *n += 1),
+        Err(MapError::Timeout)
+    ));
+
+    release_tx.send(()).unwrap();
+    handle.join().unwrap();
+    store
+        .with_timeout_mut(&1, Duration::from_millis(5), |n: &mut u32| *n += 1)
+        .unwrap();
+    assert_eq!(store.get::<u32>(&1).unwrap(), 11);
+}
+
+#[test]
+fn test_with_read_and_with_write_aliases() {
+    let store: TypeMap<&'static str> = TypeMap::new();
+    store.set("queue", vec![1u32, 2]).unwrap();
+
+    store
+        .with_write(&"queue", |q: &mut Vec<u32>| q.push(3))
+        .unwrap();
+    let len = store.with_read(&"queue", |q: &Vec<u32>| q.len()).unwrap();
+    assert_eq!(len, 3);
+
+    store.lock_key(&"queue").unwrap();
+    assert!(matches!(
+        store.with_write(&"queue", |q: &mut Vec<u32>| q.clear()),
+        Err(MapError::Locked(_))
+    ));
+}
+
+#[test]
+fn test_accept_visitor() {
+    #[derive(Default)]
+    struct Collect {
+        numbers: Vec<(String, u32)>,
+        skipped: usize,
+    }
+
+    impl Visitor<String> for Collect {
+        fn visit_any(&mut self, key: &String, value: &dyn std::any::Any) {
+            match value.downcast_ref::<u32>() {
+                Some(n) => self.numbers.push((key.clone(), *n)),
+                None => self.skipped += 1,
+            }
+        }
+    }
+
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("a".to_string(), 1u32).unwrap();
+    store.set("b".to_string(), 2u32).unwrap();
+    store.set("c".to_string(), "text".to_string()).unwrap();
+
+    let mut collect = Collect::default();
+    store.accept(&mut collect).unwrap();
+    collect.numbers.sort();
+    assert_eq!(
+        collect.numbers,
+        vec![("a".to_string(), 1), ("b".to_string(), 2)]
+    );
+    assert_eq!(collect.skipped, 1);
+
+    // The same visitor keeps accumulating across maps
+    let other: TypeMap<String> = TypeMap::new();
+    other.set("d".to_string(), 4u32).unwrap();
+    other.accept(&mut collect).unwrap();
+    assert_eq!(collect.numbers.len(), 3);
+}
+
+#[test]
+fn test_entry_stats() {
+    let store: TypeMap<String> = TypeMap::new();
+    let key = "hits".to_string();
+    assert!(store.stats(&key).unwrap().is_none());
+
+    store.set(key.clone(), 0u32).unwrap();
+    let created = store.stats(&key).unwrap().unwrap();
+    assert_eq!((created.reads, created.writes), (0, 1));
+
+    store.get::<u32>(&key).unwrap();
+    store.with(&key, |_: &u32| ()).unwrap();
+    store.with_any(&key, |_| ()).unwrap();
+    store.with_mut(&key, |n: &mut u32| *n += 1).unwrap();
+    // Failed reads don't count
+    assert!(store.get::<String>(&key).is_err());
+
+    // Overwriting keeps the counts and creation time
+    store.set(key.clone(), 5u32).unwrap();
+    let stats = store.stats(&key).unwrap().unwrap();
+    assert_eq!((stats.reads, stats.writes), (3, 3));
+    assert_eq!(stats.created_at, created.created_at);
+
+    // Removal starts over
+    store.remove(&key).unwrap();
+    store.set(key.clone(), 1u32).unwrap();
+    let stats = store.stats(&key).unwrap().unwrap();
+    assert_eq!((stats.reads, stats.writes), (0, 1));
+    assert!(stats.created_at >= created.created_at);
+}
+
+#[test]
+fn test_get_or_recompute() {
+    let store: TypeMap<String> = TypeMap::new();
+    let key = "squares".to_string();
+    let runs = std::cell::Cell::new(0);
+    let squares = |n: u32| {
+        store.get_or_recompute(key.clone(), n, |n: &u32| {
+            runs.set(runs.get() + 1);
+            (1..=*n).map(|i| i * i).collect::<Vec<u32>>()
+        })
+    };
+
+    assert_eq!(squares(3).unwrap(), vec![1, 4, 9]);
+    assert_eq!(squares(3).unwrap(), vec![1, 4, 9]);
+    assert_eq!(runs.get(), 1);
+
+    assert_eq!(squares(2).unwrap(), vec![1, 4]);
+    assert_eq!(runs.get(), 2);
+    let (_, stored) = store.get::<(u64, Vec<u32>)>(&key).unwrap();
+    assert_eq!(stored, vec![1, 4]);
+
+    // A plain value under the key is a mismatch and is left alone
+    store.set(key.clone(), 7u32).unwrap();
+    assert!(matches!(squares(2), Err(MapError::TypeMismatch { .. })));
+    assert_eq!(runs.get(), 2);
+
+    // A locked key can still be read but not recomputed
+    store.remove(&key).unwrap();
+    squares(2).unwrap();
+    store.lock_key(&key).unwrap();
+    assert_eq!(squares(2).unwrap(), vec![1, 4]);
+    assert!(matches!(squares(4), Err(MapError::Locked(_))));
+}
+
+#[test]
+fn test_with_mut_catch() {
+    let store: TypeMap<String> = TypeMap::new();
+    let key = "counter".to_string();
+    store.set(key.clone(), 1u32).unwrap();
+
+    let result = store.with_mut_catch(&key, |n: &mut u32| {
+        *n += 1;
+        *n * 10
+    });
+    assert_eq!(result.unwrap(), 20);
+
+    // Changes made before the panic are kept and the map still works
+    let result = store.with_mut_catch(&key, |n: &mut u32| {
+        *n += 1;
+        panic!("counter overflowed at {}", n);
+    });
+    match result {
+        Err(MapError::ClosurePanicked(message)) => {
+            assert_eq!(message, "counter overflowed at 3")
+        }
+        other => panic!("expected ClosurePanicked, got {:?}", other),
+    }
+    assert_eq!(store.get::<u32>(&key).unwrap(), 3);
+    store.with_mut(&key, |n: &mut u32| *n = 0).unwrap();
+
+    let result = store.with_mut_catch(&key, |_: &mut u32| {
+        std::panic::panic_any(7u8);
+    });
+    assert_eq!(
+        result.unwrap_err().to_string(),
+        "Closure panicked: Box<dyn Any>"
+    );
+
+    assert!(matches!(
+        store.with_mut_catch(&key, |_: &mut String| ()),
+        Err(MapError::TypeMismatch { .. })
+    ));
+}
+
+#[test]
+fn test_singleton() {
+    let store = TypeMap::singleton("name".to_string(), "svc".to_string());
+    assert_eq!(store.len().unwrap(), 1);
+    assert_eq!(store.keys().unwrap(), vec!["name".to_string()]);
+    assert_eq!(store.get::<String>(&"name".to_string()).unwrap(), "svc");
+    assert_eq!(store.count_of_type::<String>().unwrap(), 1);
+    assert_eq!(store.stats(&"name".to_string()).unwrap().unwrap().writes, 1);
+
+    // It's an ordinary map afterwards
+    store.set("port".to_string(), 8080u16).unwrap();
+    assert_eq!(store.len().unwrap(), 2);
+}
+
+#[test]
+fn test_find_and_any() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("a".to_string(), 1u32).unwrap();
+    store.set("b".to_string(), 20u32).unwrap();
+    store.set("c".to_string(), "20".to_string()).unwrap();
+
+    assert_eq!(
+        store.find(|_, n: &u32| *n > 10).unwrap(),
+        Some(("b".to_string(), 20))
+    );
+    assert_eq!(store.find(|_, n: &u32| *n > 100).unwrap(), None);
+    // Only values of the requested type are considered
+    assert_eq!(
+        store.find(|_, s: &String| s == "20").unwrap(),
+        Some(("c".to_string(), "20".to_string()))
+    );
+
+    assert!(store.any(|key, _: &u32| key == "a").unwrap());
+    assert!(!store.any(|key, _: &u32| key == "c").unwrap());
+
+    // The search stops at the first match
+    let mut visited = 0;
+    assert!(store
+        .any(|_, _: &u32| {
+            visited += 1;
+            true
+        })
+        .unwrap());
+    assert_eq!(visited, 1);
+}
+
+#[test]
+fn test_extract_if() {
+    let store: TypeMap<u32> = TypeMap::new();
+    for id in 1..=6 {
+        store.set(id, format!("task-{}", id)).unwrap();
+    }
+    store.set(100, 100u64).unwrap();
+
+    let mut even = store.extract_if(|id, _: &String| id % 2 == 0).unwrap();
+    even.sort();
+    assert_eq!(
+        even,
+        vec![
+            (2, "task-2".to_string()),
+            (4, "task-4".to_string()),
+            (6, "task-6".to_string())
+        ]
+    );
+    assert_eq!(store.keys_ordered().unwrap(), vec![1, 3, 5, 100]);
+    assert_eq!(store.count_of_type::<String>().unwrap(), 3);
+
+    // Other types are never offered to the predicate
+    assert!(store.extract_if(|_, _: &u64| false).unwrap().is_empty());
+    assert!(store.contains_key(&100).unwrap());
+
+    // A locked match aborts the whole extraction
+    store.lock_key(&3).unwrap();
+    assert!(matches!(
+        store.extract_if(|_, _: &String| true),
+        Err(MapError::Locked(_))
+    ));
+    assert_eq!(store.len().unwrap(), 4);
+}
+
+#[test]
+fn test_with_ctx_panic_keeps_entry() {
+    let store: TypeMap<String> = TypeMap::new();
+    store.set("held".to_string(), 1u32).unwrap();
+    store.set("other".to_string(), 2u32).unwrap();
+
+    let panicker = store.clone();
+    let result = std::thread::spawn(move || {
+        let _ = panicker.with_ctx(&"held".to_string(), |_: &u32, ctx| {
+            ctx.set("added".to_string(), 3u32).unwrap();
+            panic!("boom");
+        });
+    })
+    .join();
+    assert!(result.is_err());
+
+    // The lent entry was put back, so nothing is missing from the recovered map
+    assert_eq!(store.len_or_zero(), 3);
+    let raw = store.raw();
+    let recovered = raw.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    assert_eq!(recovered["held"].downcast_ref::<u32>(), Some(&1));
+    assert!(recovered.contains_key("added"));
+}